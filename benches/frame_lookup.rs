@@ -0,0 +1,45 @@
+//! Per-frame sprite lookup, before and after compiling the sheet
+//! - format_and_hash : format!("Run (n).png") then hash into Sheet.frames
+//! - indexed         : AnimationId + frame index into the compiled AnimationSet
+//!
+//! cargo bench --bench frame_lookup
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use rust_webpack_template::engine::Sheet;
+use rust_webpack_template::sprite::{AnimationOverrides, AnimationSet, FRAME_TICK_RATE};
+
+const SHEET_JSON: &str = include_str!("../static/rhb.json");
+// one full run cycle, 8 frames held for FRAME_TICK_RATE ticks each
+const RUN_TICKS: u8 = 8 * FRAME_TICK_RATE;
+
+fn frame_lookup(c: &mut Criterion) {
+    let sheet: Sheet = serde_json::from_str(SHEET_JSON).expect("rhb.json should parse");
+    let animations = AnimationSet::from_sheet(&sheet, &AnimationOverrides::default());
+    let run = animations
+        .id("Run")
+        .expect("rhb.json should ship Run frames");
+    assert!(
+        (0..RUN_TICKS).all(|tick| animations.frame_at(run, tick / FRAME_TICK_RATE).is_some()),
+        "every lookup should hit, or the comparison is meaningless"
+    );
+
+    let mut group = c.benchmark_group("frame_lookup");
+    group.bench_function("format_and_hash", |b| {
+        b.iter(|| {
+            for tick in 0..RUN_TICKS {
+                let key = format!("{} ({}).png", "Run", tick / FRAME_TICK_RATE + 1);
+                black_box(sheet.frames.get(&key));
+            }
+        })
+    });
+    group.bench_function("indexed", |b| {
+        b.iter(|| {
+            for tick in 0..RUN_TICKS {
+                black_box(animations.frame_at(run, black_box(tick / FRAME_TICK_RATE)));
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, frame_lookup);
+criterion_main!(benches);
@@ -0,0 +1,59 @@
+//! Pack every image + JSON file in a folder into a single asset bundle
+//!
+//! cargo run --bin pack_assets -- [static dir] [output]
+//! - defaults to `static` -> `static/assets.pack`
+//! - see engine::bundle for the format
+use rust_webpack_template::engine::bundle::{Bundle, EntryKind};
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
+const DEFAULT_SOURCE: &str = "static";
+const DEFAULT_OUTPUT: &str = "static/assets.pack";
+
+fn main() {
+    let mut args = env::args().skip(1);
+    let source = PathBuf::from(args.next().unwrap_or_else(|| DEFAULT_SOURCE.to_string()));
+    let output = PathBuf::from(args.next().unwrap_or_else(|| DEFAULT_OUTPUT.to_string()));
+
+    if let Err(err) = pack(&source, &output) {
+        eprintln!("pack_assets: {}", err);
+        process::exit(1);
+    }
+}
+
+fn pack(source: &Path, output: &Path) -> Result<(), String> {
+    let files = collect(source)?;
+    let bytes = Bundle::build(&files);
+    fs::write(output, &bytes).map_err(|err| format!("{} : {}", output.display(), err))?;
+
+    for (name, data) in &files {
+        println!("  {:>9} bytes  {}", data.len(), name);
+    }
+    println!(
+        "packed {} files into {} ({} bytes)",
+        files.len(),
+        output.display(),
+        bytes.len()
+    );
+    Ok(())
+}
+
+/// Images + JSON directly inside `source`, sorted so the bundle is stable
+/// - other files (and previous bundles) are skipped
+fn collect(source: &Path) -> Result<Vec<(String, Vec<u8>)>, String> {
+    let entries = fs::read_dir(source).map_err(|err| format!("{} : {}", source.display(), err))?;
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(|err| err.to_string())?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if !path.is_file() || EntryKind::from_name(name) == EntryKind::Raw {
+            continue;
+        }
+        let data = fs::read(&path).map_err(|err| format!("{} : {}", path.display(), err))?;
+        files.push((name.to_string(), data));
+    }
+    files.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(files)
+}
@@ -0,0 +1,545 @@
+//! Pack a folder of frame PNGs into a sprite atlas + rhb.json style sheet
+//!
+//! cargo run --bin pack_atlas -- <frames dir> <atlas.png> <atlas.json>
+//!     [--padding N] [--trim] [--max-width W]
+//!
+//! - frames are named like the animations expect : "Run (1).png" ...
+//! - the JSON matches the TexturePacker "hash" layout engine::Sheet reads
+//! - --trim crops transparent borders, recording the crop in
+//!   spriteSourceSize / sourceSize : the game draws each frame back at its
+//!   offset, and hitboxes stay relative to the untrimmed frame
+//! - the atlas must fit engine::Sheet's i16 coordinates, 32767px a side
+//!
+//! ELI5: (shelf packing, tallest frames first)
+//! ┌────────── max width ──────────┐
+//! │┌────┐┌────┐┌───┐┌───┐         │ ◄── shelf 1 : height of tallest
+//! ││Jump││Jump││Run││Run│         │
+//! │└────┘└────┘└───┘└───┘         │
+//! │┌───┐┌───┐┌──┐┌──┐┌──┐┌──┐     │ ◄── shelf 2 : starts when a frame
+//! ││Run││Run││Sl││Sl││Sl││Sl│     │     no longer fits on shelf 1
+//! │└───┘└───┘└──┘└──┘└──┘└──┘     │
+//! └───────────────────────────────┘
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::{env, fs, process};
+
+const DEFAULT_MAX_WIDTH: u32 = 1024;
+// engine::SheetRect stores i16, every frame has to end inside it
+const MAX_SIDE: u32 = i16::MAX as u32;
+
+fn main() {
+    let options = match Options::parse(env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("pack_atlas: {}", err);
+            eprintln!(
+                "usage: pack_atlas <frames dir> <atlas.png> <atlas.json> \
+                 [--padding N] [--trim] [--max-width W]"
+            );
+            process::exit(2);
+        }
+    };
+    if let Err(err) = run(&options) {
+        eprintln!("pack_atlas: {}", err);
+        process::exit(1);
+    }
+}
+
+#[derive(Debug)]
+struct Options {
+    frames: PathBuf,
+    image: PathBuf,
+    sheet: PathBuf,
+    padding: u32,
+    trim: bool,
+    max_width: u32,
+}
+
+impl Options {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut paths = Vec::new();
+        let mut padding = 0;
+        let mut trim = false;
+        let mut max_width = DEFAULT_MAX_WIDTH;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--trim" => trim = true,
+                "--padding" => padding = number(args.next(), "--padding")?,
+                "--max-width" => max_width = number(args.next(), "--max-width")?,
+                flag if flag.starts_with("--") => return Err(format!("unknown flag {}", flag)),
+                path => paths.push(PathBuf::from(path)),
+            }
+        }
+        let [frames, image, sheet] = <[PathBuf; 3]>::try_from(paths)
+            .map_err(|_| "expected <frames dir> <atlas.png> <atlas.json>".to_string())?;
+        Ok(Self {
+            frames,
+            image,
+            sheet,
+            padding,
+            trim,
+            max_width,
+        })
+    }
+}
+
+fn number(value: Option<String>, flag: &str) -> Result<u32, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or_else(|| format!("{} expects a number", flag))
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    let mut frames = load_frames(&options.frames)?;
+    if frames.is_empty() {
+        return Err(format!("no .png frames in {}", options.frames.display()));
+    }
+    if options.trim {
+        frames = frames.into_iter().map(Frame::trimmed).collect();
+    }
+
+    let sizes: Vec<(u32, u32)> = frames.iter().map(|f| (f.width, f.height)).collect();
+    let layout = pack(&sizes, options.max_width, options.padding)?;
+    let atlas = compose(&frames, &layout);
+    write_png(&options.image, &atlas)?;
+
+    let image_name = options
+        .image
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or("atlas.png");
+    let sheet = sheet_json(&frames, &layout, image_name);
+    let json = serde_json::to_string_pretty(&sheet).map_err(|err| err.to_string())?;
+    fs::write(&options.sheet, json)
+        .map_err(|err| format!("{} : {}", options.sheet.display(), err))?;
+
+    println!(
+        "packed {} frames into {} ({}x{}) + {}",
+        frames.len(),
+        options.image.display(),
+        layout.width,
+        layout.height,
+        options.sheet.display()
+    );
+    Ok(())
+}
+
+/// RGBA8 pixels of a single frame
+#[derive(Debug, Clone)]
+struct Frame {
+    name: String,
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+    /// where this (possibly trimmed) frame sits in the original image
+    source: SourceRect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct SourceRect {
+    x: u32,
+    y: u32,
+    /// size of the original, untrimmed image
+    width: u32,
+    height: u32,
+}
+
+impl Frame {
+    fn new(name: String, width: u32, height: u32, pixels: Vec<u8>) -> Self {
+        Self {
+            name,
+            width,
+            height,
+            pixels,
+            source: SourceRect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            },
+        }
+    }
+
+    fn alpha(&self, x: u32, y: u32) -> u8 {
+        self.pixels[((y * self.width + x) * 4 + 3) as usize]
+    }
+
+    /// Crop fully transparent rows / columns off every edge
+    /// - a fully transparent frame keeps a single pixel, never 0x0
+    fn trimmed(self) -> Self {
+        let opaque = |x: u32, y: u32| self.alpha(x, y) > 0;
+        let rows: Vec<u32> = (0..self.height)
+            .filter(|&y| (0..self.width).any(|x| opaque(x, y)))
+            .collect();
+        let columns: Vec<u32> = (0..self.width)
+            .filter(|&x| (0..self.height).any(|y| opaque(x, y)))
+            .collect();
+        let (Some(&top), Some(&bottom), Some(&left), Some(&right)) =
+            (rows.first(), rows.last(), columns.first(), columns.last())
+        else {
+            return Self {
+                width: 1,
+                height: 1,
+                pixels: vec![0; 4],
+                ..self
+            };
+        };
+
+        let (width, height) = (right - left + 1, bottom - top + 1);
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for y in top..=bottom {
+            let start = ((y * self.width + left) * 4) as usize;
+            pixels.extend_from_slice(&self.pixels[start..start + (width * 4) as usize]);
+        }
+        Self {
+            width,
+            height,
+            pixels,
+            source: SourceRect {
+                x: left,
+                y: top,
+                ..self.source
+            },
+            name: self.name,
+        }
+    }
+
+    fn is_trimmed(&self) -> bool {
+        self.width != self.source.width || self.height != self.source.height
+    }
+}
+
+/// Every .png directly inside `dir`, sorted by name for stable output
+fn load_frames(dir: &Path) -> Result<Vec<Frame>, String> {
+    let entries = fs::read_dir(dir).map_err(|err| format!("{} : {}", dir.display(), err))?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+        })
+        .collect();
+    paths.sort();
+    paths.iter().map(|path| read_png(path)).collect()
+}
+
+fn read_png(path: &Path) -> Result<Frame, String> {
+    let error = |err: &dyn std::fmt::Display| format!("{} : {}", path.display(), err);
+    let file = File::open(path).map_err(|err| error(&err))?;
+    let mut decoder = png::Decoder::new(file);
+    // palette -> rgb, 16 bit -> 8 bit, so only 4 colour types remain
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| error(&err))?;
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buffer).map_err(|err| error(&err))?;
+    let bytes = &buffer[..info.buffer_size()];
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => bytes.to_vec(),
+        png::ColorType::Rgb => bytes
+            .chunks_exact(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], u8::MAX])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => bytes
+            .chunks_exact(2)
+            .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+            .collect(),
+        png::ColorType::Grayscale => bytes.iter().flat_map(|&g| [g, g, g, u8::MAX]).collect(),
+        png::ColorType::Indexed => return Err(error(&"palette was not expanded")),
+    };
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| error(&"file name is not utf-8"))?
+        .to_string();
+    Ok(Frame::new(name, info.width, info.height, pixels))
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Layout {
+    width: u32,
+    height: u32,
+    /// top left of every frame in the atlas, same order as the input
+    positions: Vec<(u32, u32)>,
+}
+
+/// Shelf bin packing : tallest first, left to right, a new shelf whenever the
+/// next frame would cross max_width
+fn pack(sizes: &[(u32, u32)], max_width: u32, padding: u32) -> Result<Layout, String> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&i| (std::cmp::Reverse(sizes[i].1), std::cmp::Reverse(sizes[i].0)));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut y, mut shelf_height, mut width) = (0, 0, 0, 0);
+    for i in order {
+        let (w, h) = sizes[i];
+        if w > max_width {
+            return Err(format!(
+                "a {}px wide frame can't fit --max-width {}",
+                w, max_width
+            ));
+        }
+        if x > 0 && x + w > max_width {
+            y += shelf_height + padding;
+            x = 0;
+            shelf_height = 0;
+        }
+        positions[i] = (x, y);
+        width = width.max(x + w);
+        shelf_height = shelf_height.max(h);
+        x += w + padding;
+    }
+
+    let height = y + shelf_height;
+    if width > MAX_SIDE || height > MAX_SIDE {
+        return Err(format!(
+            "a {}x{} atlas doesn't fit the sheet's i16 coordinates, {} at most",
+            width, height, MAX_SIDE
+        ));
+    }
+    Ok(Layout {
+        width,
+        height,
+        positions,
+    })
+}
+
+struct Atlas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+fn compose(frames: &[Frame], layout: &Layout) -> Atlas {
+    let mut pixels = vec![0; (layout.width * layout.height * 4) as usize];
+    for (frame, &(x, y)) in frames.iter().zip(&layout.positions) {
+        let row_len = (frame.width * 4) as usize;
+        for row in 0..frame.height {
+            let src = (row * frame.width * 4) as usize;
+            let dst = (((y + row) * layout.width + x) * 4) as usize;
+            pixels[dst..dst + row_len].copy_from_slice(&frame.pixels[src..src + row_len]);
+        }
+    }
+    Atlas {
+        width: layout.width,
+        height: layout.height,
+        pixels,
+    }
+}
+
+fn write_png(path: &Path, atlas: &Atlas) -> Result<(), String> {
+    let error = |err: &dyn std::fmt::Display| format!("{} : {}", path.display(), err);
+    let file = File::create(path).map_err(|err| error(&err))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), atlas.width, atlas.height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().map_err(|err| error(&err))?;
+    writer
+        .write_image_data(&atlas.pixels)
+        .map_err(|err| error(&err))
+}
+
+// ==================== Sheet JSON ====================
+// Same shape as the TexturePacker export in static/rhb.json
+
+#[derive(Serialize)]
+struct SheetJson {
+    frames: BTreeMap<String, FrameJson>,
+    meta: MetaJson,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct FrameJson {
+    frame: RectJson,
+    rotated: bool,
+    trimmed: bool,
+    sprite_source_size: RectJson,
+    source_size: SizeJson,
+}
+
+#[derive(Serialize)]
+struct RectJson {
+    x: u32,
+    y: u32,
+    w: u32,
+    h: u32,
+}
+
+#[derive(Serialize)]
+struct SizeJson {
+    w: u32,
+    h: u32,
+}
+
+#[derive(Serialize)]
+struct MetaJson {
+    app: &'static str,
+    version: &'static str,
+    image: String,
+    format: &'static str,
+    size: SizeJson,
+    scale: &'static str,
+}
+
+fn sheet_json(frames: &[Frame], layout: &Layout, image: &str) -> SheetJson {
+    let frames = frames
+        .iter()
+        .zip(&layout.positions)
+        .map(|(frame, &(x, y))| {
+            let json = FrameJson {
+                frame: RectJson {
+                    x,
+                    y,
+                    w: frame.width,
+                    h: frame.height,
+                },
+                rotated: false,
+                trimmed: frame.is_trimmed(),
+                sprite_source_size: RectJson {
+                    x: frame.source.x,
+                    y: frame.source.y,
+                    w: frame.width,
+                    h: frame.height,
+                },
+                source_size: SizeJson {
+                    w: frame.source.width,
+                    h: frame.source.height,
+                },
+            };
+            (frame.name.clone(), json)
+        })
+        .collect();
+
+    SheetJson {
+        frames,
+        meta: MetaJson {
+            app: "pack_atlas",
+            version: "1.0",
+            image: image.to_string(),
+            format: "RGBA8888",
+            size: SizeJson {
+                w: layout.width,
+                h: layout.height,
+            },
+            scale: "1",
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_webpack_template::engine::Sheet;
+    use rust_webpack_template::sprite::{AnimationOverrides, AnimationSet};
+
+    /// width x height frame, opaque only inside `opaque` (x, y, w, h)
+    fn frame(name: &str, width: u32, height: u32, opaque: (u32, u32, u32, u32)) -> Frame {
+        let mut pixels = vec![0; (width * height * 4) as usize];
+        let (ox, oy, ow, oh) = opaque;
+        for y in oy..oy + oh {
+            for x in ox..ox + ow {
+                pixels[((y * width + x) * 4 + 3) as usize] = u8::MAX;
+            }
+        }
+        Frame::new(name.to_string(), width, height, pixels)
+    }
+
+    #[test]
+    fn test_pack_shelves_without_overlap() {
+        let sizes = [(40, 10), (30, 30), (30, 20), (50, 30)];
+        let layout = pack(&sizes, 100, 2).unwrap();
+
+        // tallest first : (50x30) (30x30) share shelf 1, the rest move down
+        assert_eq!(layout.positions, [(32, 32), (52, 0), (0, 32), (0, 0)]);
+        assert_eq!((layout.width, layout.height), (82, 52));
+
+        for (i, a) in layout.positions.iter().enumerate() {
+            for (j, b) in layout.positions.iter().enumerate().skip(i + 1) {
+                let overlap_x = a.0 < b.0 + sizes[j].0 && b.0 < a.0 + sizes[i].0;
+                let overlap_y = a.1 < b.1 + sizes[j].1 && b.1 < a.1 + sizes[i].1;
+                assert!(!(overlap_x && overlap_y), "frames {} and {} overlap", i, j);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pack_rejects_frames_wider_than_max() {
+        assert!(pack(&[(200, 10)], 100, 0).is_err());
+    }
+
+    #[test]
+    fn test_pack_rejects_atlases_past_i16() {
+        assert!(pack(&[(10, 32_767)], 100, 0).is_ok());
+        assert!(pack(&[(10, 32_768)], 100, 0).is_err());
+        // 4 shelves of 10_000 rows end past 32767
+        assert!(pack(&[(60, 10_000); 4], 100, 0).is_err());
+        assert!(pack(&[(40_000, 10)], 50_000, 0).is_err());
+    }
+
+    #[test]
+    fn test_trim_records_source_offset() {
+        let trimmed = frame("Run (1).png", 10, 8, (2, 3, 4, 2)).trimmed();
+
+        assert_eq!((trimmed.width, trimmed.height), (4, 2));
+        assert_eq!(
+            trimmed.source,
+            SourceRect {
+                x: 2,
+                y: 3,
+                width: 10,
+                height: 8
+            }
+        );
+        assert!(trimmed.is_trimmed());
+        assert!(trimmed.pixels.chunks(4).all(|px| px[3] == u8::MAX));
+
+        let empty = frame("Run (2).png", 10, 8, (0, 0, 0, 0)).trimmed();
+        assert_eq!((empty.width, empty.height), (1, 1));
+    }
+
+    #[test]
+    fn test_trimmed_sheet_keeps_frames_in_place_in_game() {
+        let frames: Vec<Frame> = [(2, 3, 4, 2), (0, 1, 10, 6)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, opaque)| frame(&format!("Run ({}).png", i + 1), 10, 8, opaque).trimmed())
+            .collect();
+        let sizes: Vec<_> = frames.iter().map(|f| (f.width, f.height)).collect();
+        let layout = pack(&sizes, 64, 1).unwrap();
+        let json = serde_json::to_string(&sheet_json(&frames, &layout, "run.png")).unwrap();
+
+        let sheet: Sheet = serde_json::from_str(&json).unwrap();
+        let animations = AnimationSet::from_sheet(&sheet, &AnimationOverrides::default());
+        let run = animations.id("Run").unwrap();
+        let source = animations.frame_at(run, 0).unwrap();
+        assert_eq!((source.size.width, source.size.height), (4, 2));
+        let trim = animations.trim_at(run, 0).unwrap();
+        assert_eq!((trim.offset.x, trim.offset.y), (2, 3));
+        assert_eq!((trim.size.width, trim.size.height), (10, 8));
+        // every frame keeps the untrimmed box, the state's bounding box
+        let second = animations.trim_at(run, 1).unwrap();
+        assert_eq!((second.offset.x, second.offset.y), (0, 1));
+        assert_eq!(animations.get("Run").unwrap().size, trim.size);
+    }
+
+    #[test]
+    fn test_sheet_json_reads_as_engine_sheet() {
+        let frames = vec![
+            frame("Run (1).png", 4, 4, (0, 0, 4, 4)),
+            frame("Run (2).png", 2, 6, (0, 0, 2, 6)),
+        ];
+        let sizes: Vec<_> = frames.iter().map(|f| (f.width, f.height)).collect();
+        let layout = pack(&sizes, 64, 1).unwrap();
+        let json = serde_json::to_string(&sheet_json(&frames, &layout, "run.png")).unwrap();
+
+        let sheet: Sheet = serde_json::from_str(&json).unwrap();
+        let run_2 = &sheet.frames["Run (2).png"].frame;
+        assert_eq!((run_2.x, run_2.y, run_2.w, run_2.h), (0, 0, 2, 6));
+        let run_1 = &sheet.frames["Run (1).png"].frame;
+        assert_eq!((run_1.x, run_1.y, run_1.w, run_1.h), (3, 0, 4, 4));
+    }
+}
@@ -0,0 +1,57 @@
+//! Render RedHatBoy's transition table as a state diagram
+//!
+//! cargo run --bin state_diagram             rewrites docs/red_hat_boy.{dot,mmd}
+//! cargo run --bin state_diagram -- dot      prints Graphviz DOT to stdout
+//! cargo run --bin state_diagram -- mermaid  prints a Mermaid stateDiagram
+//!
+//! - the table comes from the state_machine! invocation in red_hat_boy.rs,
+//!   the same one the game runs, so the diagram can't describe another machine
+//! - the files in docs/ are checked in, a test fails when they drift from the
+//!   code : rerun this after changing a transition
+//! - render the DOT with `dot -Tsvg docs/red_hat_boy.dot -o red_hat_boy.svg`,
+//!   the Mermaid file renders as is on GitHub / GitLab
+use rust_webpack_template::sprite::{to_dot, to_mermaid, RedHatBoySimulation};
+use std::path::Path;
+use std::{env, fs, process};
+
+// the crate's docs/, wherever cargo is run from : the test reads them from
+// there with include_str!
+const DOCS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/docs");
+
+fn main() {
+    let dot = to_dot(
+        "RedHatBoy",
+        RedHatBoySimulation::states(),
+        RedHatBoySimulation::transition_table(),
+    );
+    let mermaid = to_mermaid(
+        RedHatBoySimulation::states(),
+        RedHatBoySimulation::transition_table(),
+    );
+    match env::args().nth(1).as_deref() {
+        None => {
+            if let Err(err) = write_docs(&dot, &mermaid) {
+                eprintln!("state_diagram: {}", err);
+                process::exit(1);
+            }
+        }
+        Some("dot") => print!("{}", dot),
+        Some("mermaid") => print!("{}", mermaid),
+        Some(other) => {
+            eprintln!("state_diagram: unknown format {:?}", other);
+            eprintln!("usage: state_diagram [dot|mermaid]");
+            process::exit(2);
+        }
+    }
+}
+
+fn write_docs(dot: &str, mermaid: &str) -> Result<(), String> {
+    let dir = Path::new(DOCS_DIR);
+    fs::create_dir_all(dir).map_err(|err| format!("{}: {}", dir.display(), err))?;
+    for (name, contents) in [("red_hat_boy.dot", dot), ("red_hat_boy.mmd", mermaid)] {
+        let path = dir.join(name);
+        fs::write(&path, contents).map_err(|err| format!("{}: {}", path.display(), err))?;
+        println!("wrote {}", path.display());
+    }
+    Ok(())
+}
@@ -1,12 +1,12 @@
 use std::future::Future;
 
 use anyhow::{anyhow, Result};
-use serde::de::DeserializeOwned;
 use wasm_bindgen::closure::{Closure, WasmClosure, WasmClosureFnOnce};
 use wasm_bindgen::{JsCast, JsValue}; // TODO: Explain why rustanalyzer can't auto import?
 use wasm_bindgen_futures::JsFuture;
 use web_sys::{
-    CanvasRenderingContext2d, Document, HtmlCanvasElement, HtmlImageElement, Response, Window,
+    Blob, BlobPropertyBag, CanvasRenderingContext2d, Document, HtmlCanvasElement, HtmlImageElement,
+    Response, Url, Window,
 };
 
 // ==================== Constants ====================
@@ -105,24 +105,86 @@ where
     wasm_bindgen_futures::spawn_local(future);
 }
 
-pub async fn fetch_json<T>(json_path: &str) -> Result<T>
-where
-    T: DeserializeOwned,
-{
-    let resp_value = fetch_with_str(json_path).await?;
-    let resp: Response = resp_value
+/// Fetch a resource, returning the HTTP status and the raw body
+/// - a non 2xx status is NOT an error here, callers decide what it means
+pub async fn fetch_bytes(resource: &str) -> Result<(u16, Vec<u8>)> {
+    let resp: Response = fetch_with_str(resource)
+        .await?
         .dyn_into()
         .map_err(|element| anyhow!("error converting [{:#?}] to Response", element))?;
-    let json = resp
-        .json()
-        .map_err(|err| anyhow!("Could not get JSON from response [{:#?}]", err))?;
+    let buffer = resp
+        .array_buffer()
+        .map_err(|err| anyhow!("Could not read body from response [{:#?}]", err))?;
+    let buffer = JsFuture::from(buffer)
+        .await
+        .map_err(|err| anyhow!("error reading body [{:#?}]", err))?;
+
+    Ok((resp.status(), js_sys::Uint8Array::new(&buffer).to_vec()))
+}
 
-    let json_value = JsFuture::from(json)
+/// Wrap bytes in a Blob and return a `blob:` URL an <img> can load
+/// - call revoke_object_url() once done, the Blob lives until then
+pub fn create_object_url(bytes: &[u8], mime: &str) -> Result<String> {
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let options = BlobPropertyBag::new();
+    options.set_type(mime);
+    let blob = Blob::new_with_u8_array_sequence_and_options(&parts, &options)
+        .map_err(|err| anyhow!("Could not create blob : {:#?}", err))?;
+    Url::create_object_url_with_blob(&blob)
+        .map_err(|err| anyhow!("Could not create object URL : {:#?}", err))
+}
+
+pub fn revoke_object_url(url: &str) {
+    let _ = Url::revoke_object_url(url);
+}
+
+/// Resolve after `ms` milliseconds, via setTimeout
+pub async fn sleep(ms: u32) -> Result<()> {
+    let window = window()?;
+    let mut timer_error = None;
+    let promise = js_sys::Promise::new(&mut |resolve, _reject| {
+        let ms = i32::try_from(ms).unwrap_or(i32::MAX);
+        if let Err(err) = window.set_timeout_with_callback_and_timeout_and_arguments_0(&resolve, ms)
+        {
+            timer_error = Some(err);
+        }
+    });
+    if let Some(err) = timer_error {
+        return Err(anyhow!("Could not set timeout : {:#?}", err));
+    }
+
+    JsFuture::from(promise)
         .await
-        .map_err(|err| anyhow!("error fetching [{:#?}]", err))?;
+        .map(|_| ())
+        .map_err(|err| anyhow!("error waiting on timeout : {:#?}", err))
+}
+
+/// A query parameter of the page URL, e.g. "bug.wtdr" for ?replay=bug.wtdr
+pub fn query_param(name: &str) -> Option<String> {
+    let search = window().ok()?.location().search().ok()?;
+    web_sys::UrlSearchParams::new_with_str(&search)
+        .ok()?
+        .get(name)
+}
+
+fn local_storage() -> Result<web_sys::Storage> {
+    window()?
+        .local_storage()
+        .map_err(|err| anyhow!("localStorage is not accessible : {:#?}", err))?
+        .ok_or_else(|| anyhow!("No localStorage Found"))
+}
+
+/// Read a saved string, None when nothing was saved under `key`
+pub fn local_storage_get(key: &str) -> Result<Option<String>> {
+    local_storage()?
+        .get_item(key)
+        .map_err(|err| anyhow!("error reading {} from localStorage : {:#?}", key, err))
+}
 
-    serde_wasm_bindgen::from_value(json_value)
-        .map_err(|err| anyhow!("error converting response : {:#?}", err))
+pub fn local_storage_set(key: &str, value: &str) -> Result<()> {
+    local_storage()?
+        .set_item(key, value)
+        .map_err(|err| anyhow!("error writing {} to localStorage : {:#?}", key, err))
 }
 
 async fn fetch_with_str(resource: &str) -> Result<JsValue> {
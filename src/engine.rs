@@ -1,24 +1,32 @@
 use crate::browser;
+use crate::engine::action::{ActionState, Bindings};
 use crate::engine::input::*;
-use anyhow::{anyhow, Error, Result};
+use crate::engine::pointer::PointerState;
+use crate::engine::replay::Playback;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use futures::channel::oneshot::channel;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::collections::HashMap;
 // web assembly is a single threaded environment, so Rc RefCell > Mutex
 use std::rc::Rc;
-use wasm_bindgen::{
-    // unchecked_ref (unsafe) cast from Javascript type to Rust type
-    // - because we control the closure creation and specify the expected type,
-    // in principle this should be generally safe (unsafe) code
-    JsCast,
-    JsValue,
-};
 use web_sys::{CanvasRenderingContext2d, HtmlImageElement};
 
-// length of a frame in milliseconds
-const FRAME_SIZE: f32 = 1.0 / 60.0 * 1000.0;
+pub mod action;
+pub mod asset;
+pub mod buffer;
+pub mod bundle;
+pub mod gamepad;
+pub mod hit;
+#[cfg(debug_assertions)]
+pub mod hot_reload;
+pub mod input;
+pub mod keycode;
+pub mod pointer;
+pub mod replay;
+
+// length of a frame (one fixed update) in milliseconds
+pub const FRAME_SIZE: f32 = 1.0 / 60.0 * 1000.0;
 
 /// TABLE:
 /// ┌──────────── Game Architecture Overview ──────────────┐
@@ -41,7 +49,7 @@ pub trait Game {
     /// TABLE:
     /// ┌────────────── Input Processing Flow ──────────────────┐
     /// │                                                       │
-    /// │ KeyboardEvent                                         │
+    /// │ KeyboardEvent ──code()──► KeyCode                     │
     /// │     │                                                 │
     /// │     ▼                                                 │
     /// │ KeyPress(enum)        UnboundedReceiver               │
@@ -49,23 +57,32 @@ pub trait Game {
     /// │  └─KeyDown                    │                       │
     /// │     │                         │                       │
     /// │     ▼                         ▼                       │
-    /// │ InputHandler ──────────► KeyState(HashMap)            │
+    /// │ InputHandler ──────────► KeyState(KeyCode sets)       │
     /// │     │                    │                            │
     /// │     └──update()──────────┘                            │
-    /// │                                                       │
+    /// │                          │ Bindings                   │
+    /// │                          ▼                            │
+    /// │                     ActionState                       │
     /// └───────────────────────────────────────────────────────┘
-    fn update(&mut self, keystate: &KeyState);
+    ///
+    /// Called once per fixed tick, and must stay deterministic : the same
+    /// ActionStates from the same start always give the same game, so read
+    /// no clocks and no randomness here (replays depend on it)
+    fn update(&mut self, actions: &ActionState);
+    /// Where the pointer is this tick, called right before update()
+    /// - for hovering / clicking things on the canvas (see engine::hit)
+    /// - not part of replays, a replay sees no pointer : gameplay input
+    ///   belongs in actions (pointer gestures already are)
+    fn pointer(&mut self, _pointer: &PointerState) {}
     /// TABLE:
     /// ┌────────────── Animation Frame Flow ──────────────────┐
     /// │                                                      │
     /// │ RAF Closure                                          │
     /// │     │                                                │
     /// │     ▼                                                │
-    /// │ Update Input                                         │
-    /// │     │                                                │
-    /// │     ▼                                                │
-    /// │ While Loop                                           │
-    /// │  └─► Update Physics (if accumulated_delta > FRAME)   │
+    /// │ While Loop (if accumulated_delta > FRAME)            │
+    /// │  ├─► Update Input (one tick)                         │
+    /// │  └─► Update Physics                                  │
     /// │     │                                                │
     /// │     ▼                                                │
     /// │ Draw Frame                                           │
@@ -85,10 +102,57 @@ pub struct GameLoop {
 
 type SharedLoopClosure = Rc<RefCell<Option<browser::LoopClosure>>>;
 
+/// Where each fixed update's input comes from
+pub(crate) enum TickInput {
+    /// the player, every tick is also recorded for export_replay()
+    Live(Box<InputHandler>),
+    /// a recorded replay, then no input once it runs out
+    Replay(Playback),
+}
+
+impl TickInput {
+    fn next_tick(&mut self) -> ActionState {
+        match self {
+            TickInput::Live(input_handler) => {
+                input_handler.update();
+                let actions = *input_handler.get_actions();
+                replay::record_tick(&actions);
+                actions
+            }
+            TickInput::Replay(playback) => playback.next().unwrap_or_default(),
+        }
+    }
+
+    fn pointer(&self) -> PointerState {
+        match self {
+            TickInput::Live(input_handler) => *input_handler.get_pointer(),
+            TickInput::Replay(_) => PointerState::default(),
+        }
+    }
+
+    /// One fixed update : advance input one tick and hand it to `game`
+    /// - input advances one tick per fixed update, so just pressed edges
+    ///   reach exactly one update however many run this frame
+    /// - it's also what makes a replay land on the same updates
+    pub(crate) fn feed(&mut self, game: &mut dyn Game) {
+        let actions = self.next_tick();
+        game.pointer(&self.pointer());
+        game.update(&actions);
+    }
+}
+
 impl GameLoop {
     pub async fn start(game: impl Game + 'static) -> Result<()> {
-        let mut input_handler = InputHandler::new()?;
+        let input_handler = InputHandler::new(Bindings::load())?;
+        Self::run(game, TickInput::Live(Box::new(input_handler))).await
+    }
+
+    /// Run `game` on recorded input instead of the player's
+    pub async fn start_replay(game: impl Game + 'static, playback: Playback) -> Result<()> {
+        Self::run(game, TickInput::Replay(playback)).await
+    }
 
+    async fn run(game: impl Game + 'static, mut input: TickInput) -> Result<()> {
         let mut game = game.initialize().await?;
         let mut game_loop = GameLoop {
             last_frame: browser::now()?,
@@ -103,17 +167,13 @@ impl GameLoop {
         let g = f.clone();
 
         *g.borrow_mut() = Some(browser::create_raf_closure(move |perf: f64| {
-            input_handler.update();
-
             game_loop.accumulated_delta += (perf - game_loop.last_frame) as f32;
             // a) catch up on physics update
             // - multiple updates can occur in a single frame to catch up
             // - doesn't block browser responsiveness via requestAnimationFrame
             // ELI5: why did I think moving draw() inside is more performant?
             while game_loop.accumulated_delta > FRAME_SIZE {
-                // TODO: clarify if we are able to also ref keystate here
-                // because it's not mutable?
-                game.update(input_handler.get_keystate());
+                input.feed(game.as_mut());
                 game_loop.accumulated_delta -= FRAME_SIZE;
             }
             // b) draw after while loop updates
@@ -167,18 +227,81 @@ impl Renderer {
             .expect("Drawing (draw_sprite) is throwing exceptions! Unrecoverable error");
     }
 
+    /// draw_sprite() mirrored left to right, inside the same destination
+    /// - scale(-1, 1) flips the x axis around 0, so translate to the
+    ///   destination's right edge first and draw from there at x = 0
+    pub fn draw_sprite_flipped(
+        &self,
+        image_src: &HtmlImageElement,
+        frame_id: &Rect,
+        destination: &Rect,
+    ) {
+        self.context.save();
+        let right = destination.position.x as f64 + destination.size.width as f64;
+        self.context
+            .translate(right, 0.0)
+            .expect("Drawing (draw_sprite_flipped) is throwing exceptions! Unrecoverable error");
+        self.context
+            .scale(-1.0, 1.0)
+            .expect("Drawing (draw_sprite_flipped) is throwing exceptions! Unrecoverable error");
+        self.draw_sprite(
+            image_src,
+            frame_id,
+            &Rect {
+                position: Point {
+                    x: 0,
+                    y: destination.position.y,
+                },
+                size: destination.size,
+            },
+        );
+        self.context.restore();
+    }
+
     pub fn draw_image(&self, image: &HtmlImageElement, position: &Point) {
         self.context
             .draw_image_with_html_image_element(image, position.x.into(), position.y.into())
             .expect("Drawing (draw_entire_image) is throwing exceptions! Unrecoverable error");
     }
 
-    #[cfg(debug_assertions)]
+    /// `text` filled in `font` (a CSS font) and `color` (any CSS color),
+    /// `position` is its baseline start
+    pub fn draw_text(&self, text: &str, font: &str, color: &str, position: &Point) {
+        self.context.save();
+        self.context.set_font(font);
+        self.context
+            .set_fill_style(&wasm_bindgen::JsValue::from_str(color));
+        self.context
+            .fill_text(text, position.x.into(), position.y.into())
+            .expect("Drawing (draw_text) is throwing exceptions! Unrecoverable error");
+        self.context.restore();
+    }
+
+    /// A filled circle, `color` is any CSS color
+    pub fn fill_circle(&self, center: &Point, radius: i16, color: &str) {
+        self.context.save();
+        self.context.begin_path();
+        self.context
+            .arc(
+                center.x.into(),
+                center.y.into(),
+                radius.into(),
+                0.0,
+                std::f64::consts::TAU,
+            )
+            .expect("Drawing (fill_circle) is throwing exceptions! Unrecoverable error");
+        self.context
+            .set_fill_style(&wasm_bindgen::JsValue::from_str(color));
+        self.context.fill();
+        self.context.restore();
+    }
+
     pub fn draw_bounding_box(&self, bbox: &Rect, color: &str) {
         // Save current context
         self.context.save();
         // Set debug visual style
-        self.context.set_stroke_style(&JsValue::from_str(color));
+        self.context
+            .set_stroke_style(&wasm_bindgen::JsValue::from_str(color));
         self.context.set_line_width(2.0);
         // Draw debug bounding box
         self.context.stroke_rect(
@@ -201,20 +324,26 @@ pub struct Image {
 impl Image {
     pub fn new(element: HtmlImageElement, position: Point) -> Self {
         // TODO: Explain why we couldn't into() and had to as i16 explicitly?
-        let bounding_box = Rect::new(
-            position,
-            Size {
-                width: element.width() as i16,
-                height: element.height() as i16,
-            },
-        );
+        let size = Size {
+            width: element.width() as i16,
+            height: element.height() as i16,
+        };
+        Self::with_size(element, position, size)
+    }
+
+    /// An image whose size is already known, without asking the element
+    pub fn with_size(element: HtmlImageElement, position: Point, size: Size) -> Self {
         Self {
             element,
             position,
-            bounding_box,
+            bounding_box: Rect::new(position, size),
         }
     }
 
+    pub fn bounding_box(&self) -> Rect {
+        self.bounding_box
+    }
+
     pub fn draw(&self, renderer: &Renderer) {
         renderer.draw_image(&self.element, &self.position);
         #[cfg(debug_assertions)]
@@ -234,7 +363,7 @@ pub struct Size {
     pub height: i16,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Rect {
     pub position: Point,
     pub size: Size,
@@ -247,56 +376,72 @@ impl Rect {
     pub fn new(position: Point, size: Size) -> Self {
         Self { position, size }
     }
-}
 
-#[cfg(debug_assertions)]
-impl DebugDraw for Rect {
-    fn draw_debug(&self, renderer: &Renderer) {
-        renderer.draw_bounding_box(self, "#00ff00");
+    /// Overlapping, rects that only share an edge don't
+    pub fn intersects(&self, other: &Rect) -> bool {
+        let span = |position: i16, size: i16| {
+            let start = i32::from(position);
+            (start, start + i32::from(size))
+        };
+        let (left, right) = span(self.position.x, self.size.width);
+        let (top, bottom) = span(self.position.y, self.size.height);
+        let (other_left, other_right) = span(other.position.x, other.size.width);
+        let (other_top, other_bottom) = span(other.position.y, other.size.height);
+        left < other_right && other_left < right && top < other_bottom && other_top < bottom
     }
-}
 
-/// Asynchronously load an image from a given source path
-/// # Arguments
-/// * `source` - string slice to path/url
-/// # Returns
-/// * `Ok(HtmlImageElement)` - on load success
-/// * `Err` - on load fail
-pub async fn load_image(source: &str) -> Result<HtmlImageElement> {
-    let image = browser::create_html_image_element()?;
-    let (tx, rx) = channel::<Result<(), Error>>();
-    let success_tx = Rc::new(RefCell::new(Some(tx)));
-    let error_tx = success_tx.clone();
-
-    let success_callback = browser::closure_once(move || {
-        if let Some(tx) = success_tx.borrow_mut().take() {
-            let _ = tx.send(Ok(()));
-        }
-    });
-
-    let error_callback = browser::closure_once(move |err: JsValue| {
-        if let Some(tx) = error_tx.borrow_mut().take() {
-            let _ = tx.send(Err(anyhow!(
-                "[engine.rs::load_image] Error loading image: {:#?}",
-                err
-            )));
-        }
-    });
+    /// Inside, counting the top / left edges but not the bottom / right
+    pub fn contains(&self, point: Point) -> bool {
+        let (x, y) = (i32::from(point.x), i32::from(point.y));
+        let left = i32::from(self.position.x);
+        let top = i32::from(self.position.y);
+        x >= left
+            && x < left + i32::from(self.size.width)
+            && y >= top
+            && y < top + i32::from(self.size.height)
+    }
+}
 
-    image.set_onload(Some(success_callback.as_ref().unchecked_ref()));
-    image.set_onerror(Some(error_callback.as_ref().unchecked_ref()));
-    image.set_src(source);
+/// What part of the world the canvas shows
+/// - `position` : world point drawn at the canvas' top left
+/// - `zoom` : canvas pixels per world pixel
+///
+/// Walk draws in world = canvas coordinates today (Camera::default()), the
+/// camera is where scrolling plugs in, hit testing already goes through it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub position: Point,
+    pub zoom: f32,
+}
 
-    // keep callback alive until image is loaded or errors
-    success_callback.forget();
-    error_callback.forget();
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            position: Point { x: 0, y: 0 },
+            zoom: 1.0,
+        }
+    }
+}
 
-    // ?? - double unwrap because Result<Result<(), Error>, oneshot::Canceled>
-    // - first unwrap yields channel result : Result<(), Error>
-    // - second unwrap yields image load result : () or propagating Error
-    rx.await??;
+impl Camera {
+    /// Canvas (screen) point -> world point
+    pub fn to_world(&self, screen: Point) -> Point {
+        let zoom = if self.zoom > 0.0 { self.zoom } else { 1.0 };
+        let axis = |origin: i16, offset: i16| {
+            (f32::from(origin) + f32::from(offset) / zoom).round() as i16
+        };
+        Point {
+            x: axis(self.position.x, screen.x),
+            y: axis(self.position.y, screen.y),
+        }
+    }
+}
 
-    Ok(image)
+#[cfg(debug_assertions)]
+impl DebugDraw for Rect {
+    fn draw_debug(&self, renderer: &Renderer) {
+        renderer.draw_bounding_box(self, "#00ff00");
+    }
 }
 
 // ELI5: MEMORY LAYOUT
@@ -312,6 +457,9 @@ pub async fn load_image(source: &str) -> Result<HtmlImageElement> {
 // │  │                │    │  w: i16    │                             │     │
 // │  │                │    │  h: i16    │                             │     │
 // │  │                │    └────────────┘                             │     │
+// │  │                │    sprite_source_size: Option<SheetRect>      │     │
+// │  │                │    source_size: Option<SheetSize>             │     │
+// │  │                │    (trimmed frames only, see SheetRect above) │     │
 // │  └────────────────┴───────────────────────────────────────────────┘     │
 // │                                                                         │
 // └─────────────────────────────────────────────────────────────────────────┘
@@ -324,7 +472,7 @@ pub async fn load_image(source: &str) -> Result<HtmlImageElement> {
 // ├────────────────┼────────────┼────────────┼───────────────────────────────┤
 // │ String (key)   │ 24 bytes   │ Heap       │ Per key + string content      │
 // ├────────────────┼────────────┼────────────┼───────────────────────────────┤
-// │ Cell           │ 8 bytes    │ Stack      │ Contains SheetRect            │
+// │ Cell           │ 24 bytes   │ Stack      │ SheetRect + optional trim     │
 // ├────────────────┼────────────┼────────────┼───────────────────────────────┤
 // │ SheetRect      │ 8 bytes    │ Stack      │ Four i16 values (2 bytes each)│
 // └────────────────┴────────────┴────────────┴───────────────────────────────┘
@@ -337,9 +485,34 @@ pub struct Sheet {
 #[derive(Debug, Deserialize, Serialize)]
 pub struct Cell {
     pub frame: SheetRect,
+    /// where `frame` sits inside the untrimmed image, None when untrimmed
+    #[serde(
+        rename = "spriteSourceSize",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sprite_source_size: Option<SheetRect>,
+    /// size of the untrimmed image, None when untrimmed
+    #[serde(
+        rename = "sourceSize",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub source_size: Option<SheetSize>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+impl Cell {
+    /// An untrimmed cell, `frame` is the whole image
+    pub fn new(frame: SheetRect) -> Self {
+        Cell {
+            frame,
+            sprite_source_size: None,
+            source_size: None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
 pub struct SheetRect {
     pub x: i16,
     pub y: i16,
@@ -347,153 +520,10 @@ pub struct SheetRect {
     pub h: i16,
 }
 
-pub mod input {
-    use crate::browser;
-    use anyhow::{Context, Result};
-    use futures::channel::mpsc::{unbounded, UnboundedReceiver};
-    use std::cell::RefCell;
-    use std::collections::HashMap;
-    use std::rc::Rc;
-    use wasm_bindgen::JsCast;
-    use web_sys::KeyboardEvent;
-
-    #[derive(Debug)]
-    /// Because we can't determine what kind of KeyboardEvent is returned :
-    /// - this enum wraps the event as a key up or key down
-    /// - effectively let's us manage one channel (as opposed to two+)
-    enum KeyPress {
-        KeyUp(KeyboardEvent),
-        KeyDown(KeyboardEvent),
-    }
-
-    #[derive(Debug)]
-    /// HashMap values represent a generic physical keyboard as defined by :
-    /// - https://developer.mozilla.org/en-US/docs/Web/API/UI_Events/Keyboard_event_code_values
-    pub struct KeyState {
-        pressed_keys: HashMap<String, KeyboardEvent>,
-    }
-
-    impl KeyState {
-        pub fn new() -> Self {
-            KeyState {
-                pressed_keys: HashMap::new(),
-            }
-        }
-
-        pub fn is_pressed(&self, code: &str) -> bool {
-            self.pressed_keys.contains_key(code)
-        }
-
-        fn set_pressed(&mut self, code: &str, e: KeyboardEvent) {
-            // Explain why .into() on insert, but not contains_key + remove?
-            // - Hashmap `insert` takes ownership of the key, and into()
-            // converts &str to String
-            // - `contains_key` and `remove` only reference : into() is unneeded
-            self.pressed_keys.insert(code.into(), e);
-        }
-
-        fn set_released(&mut self, code: &str) {
-            self.pressed_keys.remove(code);
-        }
-    }
-
-    /// TABLE:
-    /// ┌────────────── Input Processing Flow ──────────────────┐
-    /// │                                                       │
-    /// │ KeyboardEvent                                         │
-    /// │     │                                                 │
-    /// │     ▼                                                 │
-    /// │ KeyPress(enum)        UnboundedReceiver               │
-    /// │  ├─KeyUp ─────────────────────┐                       │
-    /// │  └─KeyDown                    │                       │
-    /// │     │                         │                       │
-    /// │     ▼                         ▼                       │
-    /// │ InputHandler ──────────► KeyState(HashMap)            │
-    /// │     │                    │                            │
-    /// │     └──update()──────────┘                            │
-    /// └───────────────────────────────────────────────────────┘
-    ///
-    /// InputHandler encapsulates both :
-    /// - keystate: KeyState
-    /// - receiver: UnboundedReceiver<KeyPress>
-    ///
-    /// Provides a cleaner interface and hides implemntation
-    /// details of input processing
-    pub struct InputHandler {
-        keystate: KeyState,
-        receiver: UnboundedReceiver<KeyPress>,
-    }
-
-    impl InputHandler {
-        // a) Self (capital S) refers to the TYPE itself (InputHandler)
-        //  - Self in new() is good practice, easier to maintain because it
-        //  reduces change, like if the type name changes
-        // b) self (lowercase s) refers to an INSTANCE of the type
-        pub fn new() -> Result<Self> {
-            let (keystate, receiver) = prepare_input()?;
-            Ok(InputHandler { keystate, receiver })
-        }
-
-        pub fn update(&mut self) {
-            process_input(&mut self.keystate, &mut self.receiver);
-        }
-
-        pub fn get_keystate(&self) -> &KeyState {
-            &self.keystate
-        }
-    }
-
-    /// Prepare Input :
-    /// - listens for key events (KeyPress)
-    /// - puts key events into a channel
-    fn prepare_input() -> Result<(KeyState, UnboundedReceiver<KeyPress>)> {
-        // unbounded() channels have no limits on it buffer size, used here:
-        // - we don't expect keyboard events to overflow memory
-        // - we process events quickly in each frame
-        // - avoiding backpressure handling simplifies the code
-        let (keydown_sender, keyevent_receiver) = unbounded();
-        let keydown_sender = Rc::new(RefCell::new(keydown_sender));
-        let keyup_sender = Rc::clone(&keydown_sender);
-
-        let onkeydown = browser::closure_wrap(Box::new(move |keycode: KeyboardEvent| {
-            log!("Key pressed: {}", keycode.key());
-            let _ = keydown_sender
-                .borrow_mut()
-                .start_send(KeyPress::KeyDown(keycode));
-        }) as Box<dyn FnMut(KeyboardEvent)>);
-        let onkeyup = browser::closure_wrap(Box::new(move |keycode: KeyboardEvent| {
-            log!("Key released: {}", keycode.key());
-            let _ = keyup_sender
-                .borrow_mut()
-                .start_send(KeyPress::KeyUp(keycode));
-        }) as Box<dyn FnMut(KeyboardEvent)>);
-
-        let window = browser::window().context("Window element not found")?;
-
-        window.set_onkeydown(Some(onkeydown.as_ref().unchecked_ref()));
-        window.set_onkeyup(Some(onkeyup.as_ref().unchecked_ref()));
-
-        onkeydown.forget();
-        onkeyup.forget();
-
-        Ok((KeyState::new(), keyevent_receiver))
-    }
-
-    /// Process Input :
-    /// - Grab all events from key press channel
-    /// - Reduce them to KeyState
-    fn process_input(state: &mut KeyState, keyevent_receiver: &mut UnboundedReceiver<KeyPress>) {
-        loop {
-            match keyevent_receiver.try_next() {
-                Ok(None) => break,
-                Err(_err) => break,
-                Ok(Some(e)) => match e {
-                    KeyPress::KeyUp(e) => state.set_released(&e.code()),
-                    KeyPress::KeyDown(e) => state.set_pressed(&e.code(), e),
-                },
-            };
-        }
-    }
+#[derive(Debug, Copy, Clone, Deserialize, Serialize)]
+pub struct SheetSize {
+    pub w: i16,
+    pub h: i16,
 }
 
 #[cfg(debug_assertions)]
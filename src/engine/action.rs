@@ -0,0 +1,613 @@
+/// Input action layer : game code asks "is Jump held?" not "is Space held?"
+/// - Bindings map each Action to one or more physical inputs : keys,
+///   gamepad buttons, stick directions or touch gestures
+/// - bindings round trip through JSON and are saved to localStorage
+/// - JS can rebind at runtime : rebind_action("jump", "KeyW") (see lib.rs)
+///
+/// ELI5:
+/// ┌──────────────── Action Mapping Flow ──────────────────────────┐
+/// │                                                               │
+/// │  KeyState { Space, ArrowRight } + gamepads + gestures         │
+/// │        │                                                      │
+/// │        ▼  Bindings::resolve()                                 │
+/// │  ┌── Action ──┬── Bindings ─────────────────────┐             │
+/// │  │ Run        │ [{"key": "ArrowRight"}]         │             │
+/// │  │ Jump       │ [{"key": "Space"}]              │             │
+/// │  └────────────┴─────────────────────────────────┘             │
+/// │        │                                                      │
+/// │        ▼                                                      │
+/// │  ActionState { Run, Jump } ──► Game::update()                 │
+/// └───────────────────────────────────────────────────────────────┘
+use crate::browser;
+use crate::engine::gamepad::{Button, GamepadState, StickDirection};
+use crate::engine::input::KeyState;
+use crate::engine::keycode::KeyCode;
+use crate::engine::pointer::{Gesture, GestureRecognizer};
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+
+/// localStorage key the current bindings are saved under
+const STORAGE_KEY: &str = "walk-the-dog.bindings";
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    /// run right, the way the level scrolls
+    Run,
+    Slide,
+    Jump,
+    Pause,
+    // last so the bits of the actions above, and old replays, don't move
+    RunLeft,
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [
+        Action::Run,
+        Action::Slide,
+        Action::Jump,
+        Action::Pause,
+        Action::RunLeft,
+    ];
+
+    /// Parse the snake_case JSON name, e.g. "jump" or "run_left"
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Run => "run",
+            Action::Slide => "slide",
+            Action::Jump => "jump",
+            Action::Pause => "pause",
+            Action::RunLeft => "run_left",
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// One physical input, serialized as e.g. { "key": "Space" }
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Binding {
+    /// KeyboardEvent.code, the physical key regardless of layout
+    Key(KeyCode),
+    /// standard mapping gamepad button, e.g. { "button": "south" }
+    Button(Button),
+    /// stick pushed past the deadzone, e.g. { "stick": "left_right" }
+    Stick(StickDirection),
+    /// pointer gesture on the canvas, e.g. { "gesture": "swipe_down" }
+    Gesture(Gesture),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct Bindings {
+    // BTreeMap keeps the saved JSON in a stable order
+    actions: BTreeMap<Action, Vec<Binding>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let key = Binding::Key;
+        Self {
+            actions: BTreeMap::from([
+                (
+                    Action::Run,
+                    vec![
+                        key(KeyCode::ArrowRight),
+                        Binding::Button(Button::DpadRight),
+                        Binding::Stick(StickDirection::LeftRight),
+                        Binding::Gesture(Gesture::LongPress),
+                    ],
+                ),
+                (
+                    Action::RunLeft,
+                    vec![
+                        key(KeyCode::ArrowLeft),
+                        Binding::Button(Button::DpadLeft),
+                        Binding::Stick(StickDirection::LeftLeft),
+                    ],
+                ),
+                (
+                    Action::Slide,
+                    vec![
+                        key(KeyCode::ArrowDown),
+                        Binding::Button(Button::DpadDown),
+                        Binding::Stick(StickDirection::LeftDown),
+                        Binding::Gesture(Gesture::SwipeDown),
+                    ],
+                ),
+                (
+                    Action::Jump,
+                    vec![
+                        key(KeyCode::Space),
+                        Binding::Button(Button::South),
+                        Binding::Gesture(Gesture::Tap),
+                        Binding::Gesture(Gesture::SwipeUp),
+                    ],
+                ),
+                (
+                    Action::Pause,
+                    vec![key(KeyCode::Escape), Binding::Button(Button::Start)],
+                ),
+            ]),
+        }
+    }
+}
+
+impl Bindings {
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("Bindings always serialize")
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.actions.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Add `binding` to `action`
+    /// - an input drives one action only, it's taken off any other action
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        for bindings in self.actions.values_mut() {
+            bindings.retain(|bound| *bound != binding);
+        }
+        self.actions.entry(action).or_default().push(binding);
+    }
+
+    pub fn unbind(&mut self, action: Action, binding: &Binding) {
+        if let Some(bindings) = self.actions.get_mut(&action) {
+            bindings.retain(|bound| bound != binding);
+        }
+    }
+
+    /// Make `binding` the only input for `action`
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        self.actions.remove(&action);
+        self.bind(action, binding);
+    }
+
+    /// Which actions the current tick's inputs trigger
+    /// - `previous` is last tick's state, to carry held durations over
+    pub fn resolve(&self, inputs: &Inputs, previous: &ActionState) -> ActionState {
+        let mut state = ActionState::default();
+        for (action, bindings) in &self.actions {
+            let edges = bindings.iter().map(|binding| inputs.edges(binding));
+            let (held, tapped_down, tapped_up, pressed_again) = edges.fold(
+                (false, false, false, false),
+                |(held, down, up, again), edges| {
+                    // held, yet it went up and down : let go and pressed
+                    // again inside one tick
+                    let repress = edges.0 && edges.1 && edges.2;
+                    (
+                        held || edges.0,
+                        down || edges.1,
+                        up || edges.2,
+                        again || repress,
+                    )
+                },
+            );
+            let was_held = previous.is_active(*action) && !pressed_again;
+            // a second input on an already held action is not a new press,
+            // and letting go of one of two held inputs is not a release
+            let pressed = (held || tapped_down) && !was_held;
+            let released = ((was_held || tapped_up) && !held) || pressed_again;
+            let held_ticks = if held && !pressed_again {
+                previous.held_ticks(*action).saturating_add(1)
+            } else if held {
+                1
+            } else {
+                0
+            };
+            state.set(*action, held, pressed, released, held_ticks);
+        }
+        state
+    }
+
+    /// Bindings saved by a previous session, or the defaults
+    pub fn load() -> Self {
+        let saved = browser::local_storage_get(STORAGE_KEY).ok().flatten();
+        match saved.as_deref().map(Self::from_json) {
+            Some(Ok(bindings)) => bindings,
+            Some(Err(err)) => {
+                log!("Ignoring saved bindings : {}", err);
+                Self::default()
+            }
+            None => Self::default(),
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(err) = browser::local_storage_set(STORAGE_KEY, &self.to_json()) {
+            log!("Could not save bindings : {:#}", err);
+        }
+    }
+}
+
+/// Every input device, as of the current tick
+pub struct Inputs<'a> {
+    pub keys: &'a KeyState,
+    pub gamepads: &'a GamepadState,
+    pub gestures: &'a GestureRecognizer,
+}
+
+impl Inputs<'_> {
+    /// (held, went down this tick, went up this tick) for one binding
+    /// - keys and gestures can go down and up inside one tick, pads are
+    ///   polled so their edges come from comparing with the previous tick
+    fn edges(&self, binding: &Binding) -> (bool, bool, bool) {
+        match binding {
+            Binding::Key(code) => (
+                self.keys.is_pressed(*code),
+                self.keys.just_pressed(*code),
+                self.keys.just_released(*code),
+            ),
+            Binding::Button(button) => (self.gamepads.is_pressed(*button), false, false),
+            Binding::Stick(direction) => (self.gamepads.is_pushed(*direction), false, false),
+            Binding::Gesture(gesture) => {
+                let holding = self.gestures.is_holding(*gesture);
+                let fired = self.gestures.fired(*gesture);
+                (holding, fired, fired && !holding)
+            }
+        }
+    }
+}
+
+/// Actions for one tick (one fixed update), a bit per Action per edge
+/// - mirrors KeyState's edges, see the table there
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct ActionState {
+    held: u8,
+    pressed: u8,
+    released: u8,
+    held_ticks: [u32; Action::ALL.len()],
+}
+
+impl ActionState {
+    /// Held right now
+    pub fn is_active(&self, action: Action) -> bool {
+        self.held & action.bit() != 0
+    }
+
+    /// Went down this tick, even if it already went back up
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.pressed & action.bit() != 0
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.released & action.bit() != 0
+    }
+
+    /// Ticks the action has been held, 0 when not held
+    pub fn held_ticks(&self, action: Action) -> u32 {
+        self.held_ticks[action as usize]
+    }
+
+    /// (held, pressed, released) bit sets, all a replay needs to store
+    pub fn to_bits(&self) -> [u8; 3] {
+        [self.held, self.pressed, self.released]
+    }
+
+    /// Rebuild a tick from its bits, held durations carry on from `previous`
+    pub fn from_bits(bits: [u8; 3], previous: &ActionState) -> Self {
+        let [held, pressed, released] = bits;
+        let mut state = ActionState {
+            held,
+            pressed,
+            released,
+            ..ActionState::default()
+        };
+        for action in Action::ALL {
+            if state.is_active(action) {
+                state.held_ticks[action as usize] = previous.held_ticks(action).saturating_add(1);
+            }
+        }
+        state
+    }
+
+    fn set(&mut self, action: Action, held: bool, pressed: bool, released: bool, held_ticks: u32) {
+        let bit = |on: bool| if on { action.bit() } else { 0 };
+        self.held = self.held & !action.bit() | bit(held);
+        self.pressed = self.pressed & !action.bit() | bit(pressed);
+        self.released = self.released & !action.bit() | bit(released);
+        self.held_ticks[action as usize] = held_ticks;
+    }
+}
+
+/// A binding change asked for from outside the game loop
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindingCommand {
+    Rebind(Action, Binding),
+    Replace(Bindings),
+}
+
+impl Bindings {
+    pub fn apply(&mut self, command: BindingCommand) {
+        match command {
+            BindingCommand::Rebind(action, binding) => self.rebind(action, binding),
+            BindingCommand::Replace(bindings) => *self = bindings,
+        }
+    }
+}
+
+thread_local! {
+    // same single queue idea as hot_reload : JS entry points push here and
+    // InputHandler::update drains it
+    static COMMANDS: RefCell<Vec<BindingCommand>> = const { RefCell::new(Vec::new()) };
+    // the bindings the InputHandler resolves with, None while no live input
+    // runs (replays, or before the loop starts)
+    static LIVE: RefCell<Option<Bindings>> = const { RefCell::new(None) };
+}
+
+pub fn request_binding_change(command: BindingCommand) {
+    COMMANDS.with(|commands| commands.borrow_mut().push(command));
+}
+
+pub fn take_binding_changes() -> Vec<BindingCommand> {
+    COMMANDS.with(|commands| commands.take())
+}
+
+/// Record the bindings the InputHandler is using now
+pub fn publish_bindings(bindings: &Bindings) {
+    LIVE.with(|live| *live.borrow_mut() = Some(bindings.clone()));
+}
+
+/// The bindings in use, with changes still queued for the next tick applied
+/// - None when no live input is running
+pub fn live_bindings() -> Option<Bindings> {
+    let mut bindings = LIVE.with(|live| live.borrow().clone())?;
+    COMMANDS.with(|commands| {
+        for command in commands.borrow().iter() {
+            bindings.apply(command.clone());
+        }
+    });
+    Some(bindings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode) -> Binding {
+        Binding::Key(code)
+    }
+
+    #[test]
+    fn test_default_bindings_roundtrip_json() {
+        let bindings = Bindings::default();
+        let json = bindings.to_json();
+        assert!(json.contains(r#""jump": ["#));
+        assert!(json.contains(r#""key": "Space""#));
+        assert!(json.contains(r#""button": "south""#));
+        assert!(json.contains(r#""stick": "left_right""#));
+        assert_eq!(Bindings::from_json(&json).unwrap(), bindings);
+    }
+
+    /// Run one tick of key events through `bindings`
+    fn tick(
+        bindings: &Bindings,
+        keys: &mut KeyState,
+        previous: &ActionState,
+        events: &[(KeyCode, bool)],
+    ) -> ActionState {
+        keys.begin_tick();
+        for (code, down) in events {
+            if *down {
+                keys.press(*code);
+            } else {
+                keys.release(*code);
+            }
+        }
+        keys.end_tick();
+        let inputs = Inputs {
+            keys,
+            gamepads: &GamepadState::default(),
+            gestures: &GestureRecognizer::default(),
+        };
+        bindings.resolve(&inputs, previous)
+    }
+
+    #[test]
+    fn test_resolve_maps_held_keys_to_actions() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Action::Jump, key(KeyCode::ArrowUp));
+        let mut keys = KeyState::new();
+
+        let state = tick(
+            &bindings,
+            &mut keys,
+            &ActionState::default(),
+            &[(KeyCode::ArrowUp, true), (KeyCode::ArrowRight, true)],
+        );
+        assert!(state.is_active(Action::Jump));
+        assert!(state.is_active(Action::Run));
+        assert!(!state.is_active(Action::Slide));
+        assert!(!state.is_active(Action::Pause));
+    }
+
+    #[test]
+    fn test_action_edges_across_ticks() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Action::Jump, key(KeyCode::ArrowUp));
+        let mut keys = KeyState::new();
+
+        let first = tick(
+            &bindings,
+            &mut keys,
+            &ActionState::default(),
+            &[(KeyCode::Space, true)],
+        );
+        assert!(first.just_pressed(Action::Jump));
+        assert_eq!(first.held_ticks(Action::Jump), 1);
+
+        // second binding on a held action : still held, not a new press
+        let second = tick(&bindings, &mut keys, &first, &[(KeyCode::ArrowUp, true)]);
+        assert!(!second.just_pressed(Action::Jump));
+        assert_eq!(second.held_ticks(Action::Jump), 2);
+
+        let third = tick(&bindings, &mut keys, &second, &[(KeyCode::Space, false)]);
+        assert!(third.is_active(Action::Jump) && !third.just_released(Action::Jump));
+
+        let fourth = tick(&bindings, &mut keys, &third, &[(KeyCode::ArrowUp, false)]);
+        assert!(fourth.just_released(Action::Jump));
+        assert_eq!(fourth.held_ticks(Action::Jump), 0);
+
+        // a tap inside one tick still registers a press
+        let tap = tick(
+            &bindings,
+            &mut keys,
+            &fourth,
+            &[(KeyCode::Space, true), (KeyCode::Space, false)],
+        );
+        assert!(tap.just_pressed(Action::Jump) && !tap.is_active(Action::Jump));
+    }
+
+    #[test]
+    fn test_release_and_press_in_one_tick_is_a_new_press() {
+        let bindings = Bindings::default();
+        let mut keys = KeyState::new();
+        let held = tick(
+            &bindings,
+            &mut keys,
+            &ActionState::default(),
+            &[(KeyCode::Space, true)],
+        );
+        let held = tick(&bindings, &mut keys, &held, &[]);
+        assert_eq!(held.held_ticks(Action::Jump), 2);
+
+        let again = tick(
+            &bindings,
+            &mut keys,
+            &held,
+            &[(KeyCode::Space, false), (KeyCode::Space, true)],
+        );
+        assert!(again.is_active(Action::Jump));
+        assert!(again.just_released(Action::Jump) && again.just_pressed(Action::Jump));
+        assert_eq!(again.held_ticks(Action::Jump), 1);
+    }
+
+    #[test]
+    fn test_gamepad_feeds_the_same_actions() {
+        use crate::engine::gamepad::fake::{pad, FakeGamepads};
+
+        let bindings = Bindings::default();
+        let keys = KeyState::new();
+        let mut gamepads = GamepadState::default();
+        let mut source = FakeGamepads {
+            polls: vec![
+                vec![pad(0, &[Button::South], [0.9, 0.0, 0.0, 0.0])],
+                vec![pad(0, &[], [0.1, 0.0, 0.0, 0.0])],
+            ],
+        };
+
+        let gestures = GestureRecognizer::default();
+        let resolve = |gamepads: &GamepadState, previous: &ActionState| {
+            let inputs = Inputs {
+                keys: &keys,
+                gamepads,
+                gestures: &gestures,
+            };
+            bindings.resolve(&inputs, previous)
+        };
+
+        gamepads.update(&mut source);
+        let first = resolve(&gamepads, &ActionState::default());
+        assert!(first.just_pressed(Action::Jump));
+        assert!(first.is_active(Action::Run));
+
+        gamepads.update(&mut source);
+        let second = resolve(&gamepads, &first);
+        assert!(second.just_released(Action::Jump));
+        // inside the deadzone
+        assert!(second.just_released(Action::Run));
+    }
+
+    #[test]
+    fn test_gestures_feed_the_same_actions() {
+        use crate::engine::pointer::{PointerPhase, PointerSample};
+        use crate::engine::Point;
+
+        let bindings = Bindings::default();
+        let keys = KeyState::new();
+        let gamepads = GamepadState::default();
+        let mut gestures = GestureRecognizer::default();
+        let touch = |phase, time_ms| PointerSample {
+            id: 1,
+            phase,
+            position: Point { x: 300, y: 300 },
+            buttons: u8::from(phase == PointerPhase::Down),
+            time_ms,
+        };
+
+        gestures.begin_tick();
+        gestures.feed(touch(PointerPhase::Down, 0.0));
+        gestures.feed(touch(PointerPhase::Up, 80.0));
+        let inputs = Inputs {
+            keys: &keys,
+            gamepads: &gamepads,
+            gestures: &gestures,
+        };
+        let tap = bindings.resolve(&inputs, &ActionState::default());
+        assert!(tap.just_pressed(Action::Jump));
+        assert!(!tap.is_active(Action::Jump));
+    }
+
+    #[test]
+    fn test_bind_moves_input_off_other_actions() {
+        let mut bindings = Bindings::default();
+        bindings.bind(Action::Jump, key(KeyCode::ArrowDown));
+        assert!(!bindings
+            .bindings(Action::Slide)
+            .contains(&key(KeyCode::ArrowDown)));
+        assert_eq!(
+            bindings.bindings(Action::Jump),
+            [
+                key(KeyCode::Space),
+                Binding::Button(Button::South),
+                Binding::Gesture(Gesture::Tap),
+                Binding::Gesture(Gesture::SwipeUp),
+                key(KeyCode::ArrowDown)
+            ]
+        );
+
+        bindings.rebind(Action::Jump, key(KeyCode::KeyW));
+        assert_eq!(bindings.bindings(Action::Jump), [key(KeyCode::KeyW)]);
+
+        bindings.unbind(Action::Jump, &key(KeyCode::KeyW));
+        assert!(bindings.bindings(Action::Jump).is_empty());
+    }
+
+    #[test]
+    fn test_live_bindings_include_queued_changes() {
+        assert_eq!(live_bindings(), None);
+
+        publish_bindings(&Bindings::default());
+        request_binding_change(BindingCommand::Rebind(Action::Jump, key(KeyCode::KeyW)));
+        let live = live_bindings().unwrap();
+        assert_eq!(live.bindings(Action::Jump), [key(KeyCode::KeyW)]);
+
+        // draining the queue, as InputHandler::update does, keeps the result
+        let mut bindings = Bindings::default();
+        for change in take_binding_changes() {
+            bindings.apply(change);
+        }
+        publish_bindings(&bindings);
+        assert_eq!(live_bindings(), Some(live));
+    }
+
+    #[test]
+    fn test_action_names_roundtrip() {
+        for action in Action::ALL {
+            assert_eq!(Action::from_name(action.name()), Some(action));
+        }
+        assert_eq!(Action::from_name("dance"), None);
+    }
+}
@@ -0,0 +1,453 @@
+/// Asset loading with typed errors, timeouts and retries
+/// - AssetError says WHAT went wrong and for WHICH path, instead of a
+///   stringly anyhow! (a 404 page is NotFound, not a serde error)
+/// - Fetcher hides the browser, so the retry/timeout logic can run against
+///   a stub in native tests
+///
+/// ELI5:
+/// ┌──────────────── fetch_json(path) ───────────────────────────┐
+/// │                                                             │
+/// │  attempt ──► fetch ─┬─ before timeout ──► status ok? ──┐    │
+/// │     ▲               │                                  │    │
+/// │     │               └─ timer wins ──► Timeout          │    │
+/// │     │                                   │              ▼    │
+/// │     └── sleep(backoff * 2^n) ◄── retryable?      serde_json │
+/// │                                         │              │    │
+/// │                                 NotFound/Http     Parse{line}│
+/// └─────────────────────────────────────────────────────────────┘
+use crate::browser;
+use async_trait::async_trait;
+use futures::channel::oneshot::channel;
+use futures::future::{self, Either};
+use serde::de::DeserializeOwned;
+use std::cell::RefCell;
+use std::fmt;
+use std::future::Future;
+use std::rc::Rc;
+use wasm_bindgen::{
+    // unchecked_ref (unsafe) cast from Javascript type to Rust type
+    // - because we control the closure creation and specify the expected type,
+    // in principle this should be generally safe (unsafe) code
+    JsCast,
+    JsValue,
+};
+use web_sys::HtmlImageElement;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AssetError {
+    /// server answered 404
+    NotFound { path: String },
+    /// server answered with any other non 2xx status
+    Http { path: String, status: u16 },
+    /// request never got an answer (offline, CORS, aborted)
+    Network { path: String, reason: String },
+    /// body arrived but isn't a usable image / blob
+    Decode { path: String, reason: String },
+    /// body isn't valid JSON for the expected type
+    Parse {
+        path: String,
+        line: usize,
+        column: usize,
+        reason: String,
+    },
+    /// no answer within LoadOptions::timeout_ms
+    Timeout { path: String, after_ms: u32 },
+    /// bundle entry doesn't match the hash recorded when it was packed
+    Integrity { path: String, entry: String },
+}
+
+impl AssetError {
+    /// Worth trying again : the same request may succeed a moment later
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AssetError::Timeout { .. } | AssetError::Network { .. } => true,
+            // 429 Too Many Requests + 5xx server errors are transient
+            AssetError::Http { status, .. } => *status == 429 || *status >= 500,
+            AssetError::NotFound { .. }
+            | AssetError::Decode { .. }
+            | AssetError::Parse { .. }
+            | AssetError::Integrity { .. } => false,
+        }
+    }
+}
+
+impl fmt::Display for AssetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssetError::NotFound { path } => write!(f, "{} : not found (404)", path),
+            AssetError::Http { path, status } => write!(f, "{} : HTTP status {}", path, status),
+            AssetError::Network { path, reason } => {
+                write!(f, "{} : network error {}", path, reason)
+            }
+            AssetError::Decode { path, reason } => {
+                write!(f, "{} : could not decode {}", path, reason)
+            }
+            AssetError::Parse {
+                path,
+                line,
+                column,
+                reason,
+            } => write!(f, "{}:{}:{} : {}", path, line, column, reason),
+            AssetError::Timeout { path, after_ms } => {
+                write!(f, "{} : timed out after {}ms", path, after_ms)
+            }
+            AssetError::Integrity { path, entry } => {
+                write!(f, "{} : entry '{}' failed its integrity check", path, entry)
+            }
+        }
+    }
+}
+
+impl std::error::Error for AssetError {}
+
+/// How hard to try before giving up on an asset
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoadOptions {
+    /// per attempt, not total
+    pub timeout_ms: u32,
+    /// extra attempts after the first one fails with a retryable error
+    pub retries: u32,
+    /// wait before the first retry, doubled for every retry after that
+    pub backoff_ms: u32,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            timeout_ms: 10_000,
+            retries: 2,
+            backoff_ms: 250,
+        }
+    }
+}
+
+/// Raw answer to a fetch, before it's interpreted as an asset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AssetResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+/// Where assets come from :
+/// - BrowserFetcher : window.fetch + setTimeout
+/// - a stub in tests, that answers from a script of responses
+#[async_trait(?Send)]
+pub trait Fetcher {
+    async fn fetch(&self, path: &str) -> Result<AssetResponse, AssetError>;
+    async fn sleep(&self, ms: u32);
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BrowserFetcher;
+
+#[async_trait(?Send)]
+impl Fetcher for BrowserFetcher {
+    async fn fetch(&self, path: &str) -> Result<AssetResponse, AssetError> {
+        browser::fetch_bytes(path)
+            .await
+            .map(|(status, body)| AssetResponse { status, body })
+            .map_err(|err| AssetError::Network {
+                path: path.to_string(),
+                reason: format!("{:#}", err),
+            })
+    }
+
+    async fn sleep(&self, ms: u32) {
+        // a timer that can't be set never fires, so the caller just waits on
+        // the other future instead
+        if browser::sleep(ms).await.is_err() {
+            future::pending::<()>().await;
+        }
+    }
+}
+
+/// Race `attempt` against a timer
+async fn with_timeout<T>(
+    fetcher: &impl Fetcher,
+    path: &str,
+    timeout_ms: u32,
+    attempt: impl Future<Output = Result<T, AssetError>>,
+) -> Result<T, AssetError> {
+    let attempt = Box::pin(attempt);
+    let timer = Box::pin(fetcher.sleep(timeout_ms));
+    match future::select(attempt, timer).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(AssetError::Timeout {
+            path: path.to_string(),
+            after_ms: timeout_ms,
+        }),
+    }
+}
+
+/// Run `attempt` until it succeeds, fails for good or runs out of retries
+/// - each attempt is bounded by options.timeout_ms
+/// - waits backoff_ms, 2 * backoff_ms, 4 * backoff_ms ... between attempts
+pub async fn with_retry<T, F, Fut>(
+    fetcher: &impl Fetcher,
+    path: &str,
+    options: LoadOptions,
+    mut attempt: F,
+) -> Result<T, AssetError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, AssetError>>,
+{
+    let mut retry = 0;
+    loop {
+        match with_timeout(fetcher, path, options.timeout_ms, attempt()).await {
+            Err(err) if err.is_retryable() && retry < options.retries => {
+                let backoff = options.backoff_ms.saturating_mul(1 << retry.min(16));
+                fetcher.sleep(backoff).await;
+                retry += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Fetch the raw bytes of `path`, non 2xx statuses are errors
+pub async fn fetch_bytes(
+    fetcher: &impl Fetcher,
+    path: &str,
+    options: LoadOptions,
+) -> Result<Vec<u8>, AssetError> {
+    with_retry(fetcher, path, options, || async {
+        let response = fetcher.fetch(path).await?;
+        match response.status {
+            200..=299 => Ok(response.body),
+            404 => Err(AssetError::NotFound {
+                path: path.to_string(),
+            }),
+            status => Err(AssetError::Http {
+                path: path.to_string(),
+                status,
+            }),
+        }
+    })
+    .await
+}
+
+/// Fetch `path` and deserialize it, parse errors carry line + column
+pub async fn fetch_json<T>(
+    fetcher: &impl Fetcher,
+    path: &str,
+    options: LoadOptions,
+) -> Result<T, AssetError>
+where
+    T: DeserializeOwned,
+{
+    let body = fetch_bytes(fetcher, path, options).await?;
+    parse_json(path, &body)
+}
+
+pub fn parse_json<T>(path: &str, body: &[u8]) -> Result<T, AssetError>
+where
+    T: DeserializeOwned,
+{
+    serde_json::from_slice(body).map_err(|err| AssetError::Parse {
+        path: path.to_string(),
+        line: err.line(),
+        column: err.column(),
+        reason: err.to_string(),
+    })
+}
+
+/// Asynchronously load an image from a given source path
+/// # Arguments
+/// * `source` - string slice to path/url
+/// * `options` - timeout + retries, see LoadOptions
+/// # Returns
+/// * `Ok(HtmlImageElement)` - on load success
+/// * `Err(AssetError)` - on load fail, Timeout or Decode
+pub async fn load_image(
+    source: &str,
+    options: LoadOptions,
+) -> Result<HtmlImageElement, AssetError> {
+    with_retry(&BrowserFetcher, source, options, || load_image_once(source)).await
+}
+
+async fn load_image_once(source: &str) -> Result<HtmlImageElement, AssetError> {
+    let decode_error = |reason: String| AssetError::Decode {
+        path: source.to_string(),
+        reason,
+    };
+    let image =
+        browser::create_html_image_element().map_err(|err| decode_error(format!("{:#}", err)))?;
+    let (tx, rx) = channel::<Result<(), AssetError>>();
+    let success_tx = Rc::new(RefCell::new(Some(tx)));
+    let error_tx = success_tx.clone();
+
+    let success_callback = browser::closure_once(move || {
+        if let Some(tx) = success_tx.borrow_mut().take() {
+            let _ = tx.send(Ok(()));
+        }
+    });
+
+    // <img> onerror doesn't say why, a 404 and a corrupt PNG look the same
+    let path = source.to_string();
+    let error_callback = browser::closure_once(move |err: JsValue| {
+        if let Some(tx) = error_tx.borrow_mut().take() {
+            let _ = tx.send(Err(AssetError::Decode {
+                path,
+                reason: format!("{:#?}", err),
+            }));
+        }
+    });
+
+    image.set_onload(Some(success_callback.as_ref().unchecked_ref()));
+    image.set_onerror(Some(error_callback.as_ref().unchecked_ref()));
+    image.set_src(source);
+
+    // keep callback alive until image is loaded or errors
+    success_callback.forget();
+    error_callback.forget();
+
+    // ?? - double unwrap because Result<Result<(), AssetError>, oneshot::Canceled>
+    // - first unwrap yields channel result : Result<(), AssetError>
+    // - second unwrap yields image load result : () or propagating AssetError
+    rx.await
+        .map_err(|_| decode_error("load callback dropped".to_string()))??;
+
+    Ok(image)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use serde::Deserialize;
+    use std::collections::VecDeque;
+
+    /// Answers fetches from a script, `None` never answers (hangs)
+    #[derive(Default)]
+    struct StubFetcher {
+        responses: RefCell<VecDeque<Option<Result<AssetResponse, AssetError>>>>,
+        fetches: RefCell<u32>,
+        sleeps: RefCell<Vec<u32>>,
+    }
+
+    impl StubFetcher {
+        fn new(responses: Vec<Option<Result<AssetResponse, AssetError>>>) -> Self {
+            Self {
+                responses: RefCell::new(responses.into()),
+                ..Self::default()
+            }
+        }
+    }
+
+    #[async_trait(?Send)]
+    impl Fetcher for StubFetcher {
+        async fn fetch(&self, _path: &str) -> Result<AssetResponse, AssetError> {
+            *self.fetches.borrow_mut() += 1;
+            let next = self.responses.borrow_mut().pop_front().flatten();
+            match next {
+                Some(response) => response,
+                None => future::pending().await,
+            }
+        }
+
+        // time passes instantly in the stub, just record how long was asked
+        async fn sleep(&self, ms: u32) {
+            self.sleeps.borrow_mut().push(ms);
+        }
+    }
+
+    fn ok(body: &str) -> Option<Result<AssetResponse, AssetError>> {
+        Some(Ok(AssetResponse {
+            status: 200,
+            body: body.as_bytes().to_vec(),
+        }))
+    }
+
+    fn status(status: u16) -> Option<Result<AssetResponse, AssetError>> {
+        Some(Ok(AssetResponse {
+            status,
+            body: b"<html>error page</html>".to_vec(),
+        }))
+    }
+
+    #[derive(Debug, Deserialize, PartialEq)]
+    struct Asset {
+        name: String,
+    }
+
+    const OPTIONS: LoadOptions = LoadOptions {
+        timeout_ms: 1_000,
+        retries: 2,
+        backoff_ms: 100,
+    };
+
+    #[test]
+    fn test_fetch_json_ok() {
+        let stub = StubFetcher::new(vec![ok(r#"{ "name": "rhb" }"#)]);
+        let asset: Asset = block_on(fetch_json(&stub, "rhb.json", OPTIONS)).unwrap();
+        assert_eq!(asset.name, "rhb");
+    }
+
+    #[test]
+    fn test_404_is_not_found_and_not_retried() {
+        let stub = StubFetcher::new(vec![status(404), ok("{}")]);
+        let result = block_on(fetch_json::<Asset>(&stub, "rhb.json", OPTIONS));
+        assert_eq!(
+            result,
+            Err(AssetError::NotFound {
+                path: "rhb.json".to_string()
+            })
+        );
+        assert_eq!(*stub.fetches.borrow(), 1);
+    }
+
+    #[test]
+    fn test_parse_error_has_line_and_column() {
+        let stub = StubFetcher::new(vec![ok("{\n  \"name\": 3\n}")]);
+        let result = block_on(fetch_json::<Asset>(&stub, "rhb.json", OPTIONS));
+        match result {
+            Err(AssetError::Parse { path, line, .. }) => {
+                assert_eq!(path, "rhb.json");
+                assert_eq!(line, 2);
+            }
+            other => panic!("expected a Parse error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_server_errors_retry_with_backoff() {
+        let stub = StubFetcher::new(vec![status(503), status(500), ok(r#"{ "name": "rhb" }"#)]);
+        let asset: Asset = block_on(fetch_json(&stub, "rhb.json", OPTIONS)).unwrap();
+        assert_eq!(asset.name, "rhb");
+        assert_eq!(*stub.fetches.borrow(), 3);
+        // answers beat their timeout timers, so only the backoffs slept
+        assert_eq!(*stub.sleeps.borrow(), [100, 200]);
+    }
+
+    #[test]
+    fn test_gives_up_after_retries() {
+        let stub = StubFetcher::new(vec![status(500), status(500), status(502), ok("{}")]);
+        let result = block_on(fetch_bytes(&stub, "rhb.json", OPTIONS));
+        assert_eq!(
+            result,
+            Err(AssetError::Http {
+                path: "rhb.json".to_string(),
+                status: 502
+            })
+        );
+        assert_eq!(*stub.fetches.borrow(), 3);
+    }
+
+    #[test]
+    fn test_hung_fetch_times_out_then_retries() {
+        let stub = StubFetcher::new(vec![None, ok(r#"{ "name": "rhb" }"#)]);
+        let asset: Asset = block_on(fetch_json(&stub, "rhb.json", OPTIONS)).unwrap();
+        assert_eq!(asset.name, "rhb");
+
+        let stub = StubFetcher::new(vec![None, None, None]);
+        let result = block_on(fetch_bytes(&stub, "rhb.json", OPTIONS));
+        assert_eq!(
+            result,
+            Err(AssetError::Timeout {
+                path: "rhb.json".to_string(),
+                after_ms: 1_000
+            })
+        );
+    }
+}
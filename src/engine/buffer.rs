@@ -0,0 +1,245 @@
+/// Buffered intents and coyote time, so presses that come a little early or
+/// a little late still count
+/// - buffer : a press is remembered for `buffer_ticks`, and fires on the
+///   first tick the game is ready for it (e.g. landing)
+/// - coyote : the game stays "ready" for `coyote_ticks` after it stops being
+///   ready without the action firing (e.g. just after leaving the ground)
+///
+/// TABLE: Jump, buffer 6 / coyote 4, ready = boy on the ground
+/// ┌─ tick ─┬─ ready ─┬─ pressed ─┬─ poll() ──────────────────────────────┐
+/// │ 10     │ no      │ yes       │ None, buffered (in the air)           │
+/// │ 13     │ yes     │           │ Some(Now), landed within 6 ticks      │
+/// │ 20     │ no      │           │ (left the ground without jumping)     │
+/// │ 22     │ no      │ yes       │ Some(Grace), 2 ticks <= coyote 4      │
+/// └────────┴─────────┴───────────┴───────────────────────────────────────┘
+use crate::engine::action::{Action, ActionState};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct IntentWindow {
+    pub buffer_ticks: u8,
+    pub coyote_ticks: u8,
+}
+
+/// Window per action, actions without one fire only when pressed + ready
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct IntentWindows {
+    windows: BTreeMap<Action, IntentWindow>,
+}
+
+impl Default for IntentWindows {
+    fn default() -> Self {
+        Self {
+            windows: BTreeMap::from([
+                (
+                    Action::Jump,
+                    IntentWindow {
+                        buffer_ticks: 6,
+                        coyote_ticks: 4,
+                    },
+                ),
+                (
+                    Action::Slide,
+                    IntentWindow {
+                        buffer_ticks: 6,
+                        coyote_ticks: 0,
+                    },
+                ),
+            ]),
+        }
+    }
+}
+
+impl IntentWindows {
+    pub fn get(&self, action: Action) -> IntentWindow {
+        self.windows.get(&action).copied().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, action: Action, window: IntentWindow) {
+        self.windows.insert(action, window);
+    }
+}
+
+/// How a buffered intent fired
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Fire {
+    /// the game is ready for it this tick
+    Now,
+    /// the game was ready a few ticks ago, inside the coyote window
+    Grace,
+}
+
+#[derive(Debug, Copy, Clone, Default)]
+struct Intent {
+    // ticks since the press, None when nothing is buffered
+    pressed_ago: Option<u8>,
+    // ticks since the game was last ready, None when the window was used up
+    ready_ago: Option<u8>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InputBuffer {
+    windows: IntentWindows,
+    intents: [Intent; Action::ALL.len()],
+}
+
+impl InputBuffer {
+    pub fn new(windows: IntentWindows) -> Self {
+        Self {
+            windows,
+            intents: Default::default(),
+        }
+    }
+
+    pub fn windows_mut(&mut self) -> &mut IntentWindows {
+        &mut self.windows
+    }
+
+    /// Age every intent by a tick and buffer this tick's presses
+    /// - call once per fixed update, before poll()
+    pub fn update(&mut self, actions: &ActionState) {
+        for action in Action::ALL {
+            let window = self.windows.get(action);
+            let intent = &mut self.intents[action as usize];
+            intent.pressed_ago = intent
+                .pressed_ago
+                .and_then(|ago| ago.checked_add(1))
+                .filter(|ago| *ago <= window.buffer_ticks);
+            if actions.just_pressed(action) {
+                intent.pressed_ago = Some(0);
+            }
+            intent.ready_ago = intent.ready_ago.and_then(|ago| ago.checked_add(1));
+        }
+    }
+
+    /// Fire `action` if it's buffered and the game is (or just was) ready
+    /// - `ready` : whether the game could act on it this tick
+    /// - firing clears the intent and spends the coyote window
+    pub fn poll(&mut self, action: Action, ready: bool) -> Option<Fire> {
+        self.poll_with_grace(action, ready, true)
+    }
+
+    /// poll(), but a Grace fire only goes through when `grace` is true
+    /// - `grace` : whether the game can act on a late press this tick, the
+    ///   intent stays buffered (and the window unspent) when it can't
+    pub fn poll_with_grace(&mut self, action: Action, ready: bool, grace: bool) -> Option<Fire> {
+        let window = self.windows.get(action);
+        let intent = &mut self.intents[action as usize];
+        if ready {
+            intent.ready_ago = Some(0);
+        }
+        intent.pressed_ago?;
+        let fire = match intent.ready_ago {
+            Some(0) => Fire::Now,
+            Some(ago) if grace && ago <= window.coyote_ticks => Fire::Grace,
+            _ => return None,
+        };
+        *intent = Intent::default();
+        Some(fire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pressed(action: Action) -> ActionState {
+        ActionState::from_bits([0, 1 << action as u8, 0], &ActionState::default())
+    }
+
+    /// Run ticks of (pressed Jump?, ready?) through a buffer
+    fn run(buffer: &mut InputBuffer, ticks: &[(bool, bool)]) -> Vec<Option<Fire>> {
+        ticks
+            .iter()
+            .map(|(press, ready)| {
+                let actions = if *press {
+                    pressed(Action::Jump)
+                } else {
+                    ActionState::default()
+                };
+                buffer.update(&actions);
+                buffer.poll(Action::Jump, *ready)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_press_before_landing_fires_on_landing() {
+        let mut buffer = InputBuffer::default();
+        let fired = run(
+            &mut buffer,
+            &[(true, false), (false, false), (false, false), (false, true)],
+        );
+        assert_eq!(fired, [None, None, None, Some(Fire::Now)]);
+
+        // too early : 7 ticks is past the 6 tick buffer
+        let mut ticks = vec![(true, false)];
+        ticks.extend([(false, false); 7]);
+        ticks.push((false, true));
+        assert!(run(&mut buffer, &ticks).iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn test_coyote_window_after_leaving_ready() {
+        let mut buffer = InputBuffer::default();
+        let fired = run(
+            &mut buffer,
+            &[(false, true), (false, false), (false, false), (true, false)],
+        );
+        assert_eq!(fired[3], Some(Fire::Grace));
+
+        // the window is spent, a second press has to wait for ready again
+        let fired = run(&mut buffer, &[(true, false), (false, true)]);
+        assert_eq!(fired, [None, Some(Fire::Now)]);
+
+        // 5 ticks after ready is past coyote 4
+        let mut buffer = InputBuffer::default();
+        let mut ticks = vec![(false, true)];
+        ticks.extend([(false, false); 4]);
+        ticks.push((true, false));
+        assert!(run(&mut buffer, &ticks).iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn test_grace_waits_until_the_game_takes_it() {
+        let mut buffer = InputBuffer::default();
+        buffer.update(&ActionState::default());
+        buffer.poll(Action::Jump, true);
+        buffer.update(&pressed(Action::Jump));
+        assert_eq!(buffer.poll_with_grace(Action::Jump, false, false), None);
+        // still buffered, with the window unspent
+        buffer.update(&ActionState::default());
+        assert_eq!(
+            buffer.poll_with_grace(Action::Jump, false, true),
+            Some(Fire::Grace)
+        );
+    }
+
+    #[test]
+    fn test_windows_are_per_action() {
+        let mut buffer = InputBuffer::default();
+        buffer.windows_mut().set(
+            Action::Jump,
+            IntentWindow {
+                buffer_ticks: 0,
+                coyote_ticks: 0,
+            },
+        );
+        assert_eq!(
+            run(&mut buffer, &[(true, false), (false, true)]),
+            [None, None]
+        );
+
+        buffer.update(&pressed(Action::Slide));
+        buffer.update(&ActionState::default());
+        assert_eq!(buffer.poll(Action::Slide, true), Some(Fire::Now));
+
+        let json = serde_json::to_string(&IntentWindows::default()).unwrap();
+        assert_eq!(
+            json,
+            r#"{"slide":{"buffer_ticks":6,"coyote_ticks":0},"jump":{"buffer_ticks":6,"coyote_ticks":4}}"#
+        );
+    }
+}
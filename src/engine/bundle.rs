@@ -0,0 +1,412 @@
+/// Single file asset bundle : one request instead of one per PNG / JSON
+/// - built natively from static/ with `cargo run --bin pack_assets`
+/// - fetched once at load, images become object URLs, JSON is parsed in place
+/// - every entry carries a hash, checked on load
+///
+/// TABLE: (all integers little endian)
+/// ┌──────────────── assets.pack layout ─────────────────────────┐
+/// │ magic      4 bytes   b"WTDB"                                │
+/// │ version    u16       BUNDLE_VERSION                         │
+/// │ count      u32       number of entries                      │
+/// ├─────────────────────────────────────────────────────────────┤
+/// │ index      count x :                                        │
+/// │   name_len u16 + name (utf-8, e.g. "rhb.json")              │
+/// │   kind     u8        0 image, 1 json, 2 raw                 │
+/// │   offset   u32       from the start of the blob section     │
+/// │   len      u32                                              │
+/// │   hash     u64       FNV-1a 64 of the blob                  │
+/// ├─────────────────────────────────────────────────────────────┤
+/// │ blobs      raw file contents, back to back                  │
+/// └─────────────────────────────────────────────────────────────┘
+use crate::browser;
+use crate::engine::asset::{self, AssetError, Fetcher, LoadOptions};
+use serde::de::DeserializeOwned;
+use web_sys::HtmlImageElement;
+
+pub const BUNDLE_MAGIC: &[u8; 4] = b"WTDB";
+pub const BUNDLE_VERSION: u16 = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    Image,
+    Json,
+    Raw,
+}
+
+impl EntryKind {
+    /// Guess the kind from a file name's extension
+    pub fn from_name(name: &str) -> Self {
+        match mime_type(name) {
+            "application/json" => EntryKind::Json,
+            mime if mime.starts_with("image/") => EntryKind::Image,
+            _ => EntryKind::Raw,
+        }
+    }
+
+    fn to_byte(self) -> u8 {
+        match self {
+            EntryKind::Image => 0,
+            EntryKind::Json => 1,
+            EntryKind::Raw => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(EntryKind::Image),
+            1 => Some(EntryKind::Json),
+            2 => Some(EntryKind::Raw),
+            _ => None,
+        }
+    }
+}
+
+fn mime_type(name: &str) -> &'static str {
+    let extension = name
+        .rsplit_once('.')
+        .map(|(_, ext)| ext.to_ascii_lowercase());
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("gif") => "image/gif",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("json") => "application/json",
+        _ => "application/octet-stream",
+    }
+}
+
+/// FNV-1a 64 bit : tiny, dependency free, catches truncated or corrupted
+/// blobs (NOT a defense against tampering)
+pub fn fnv1a64(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(PRIME)
+    })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleEntry {
+    pub name: String,
+    pub kind: EntryKind,
+    offset: usize,
+    len: usize,
+}
+
+impl BundleEntry {
+    /// Where the entry's bytes sit in the blobs, None when a corrupt header
+    /// points past usize::MAX
+    fn range(&self) -> Option<std::ops::Range<usize>> {
+        Some(self.offset..self.offset.checked_add(self.len)?)
+    }
+}
+
+/// Parsed bundle, blobs stay in one buffer and entries point into it
+#[derive(Debug)]
+pub struct Bundle {
+    path: String,
+    entries: Vec<BundleEntry>,
+    blobs: Vec<u8>,
+}
+
+impl Bundle {
+    /// Serialize files into the bundle format, used by the packer binary
+    pub fn build(files: &[(String, Vec<u8>)]) -> Vec<u8> {
+        let mut index = Vec::new();
+        let mut blobs = Vec::new();
+        for (name, data) in files {
+            let name_len = u16::try_from(name.len()).expect("entry name longer than u16::MAX");
+            index.extend_from_slice(&name_len.to_le_bytes());
+            index.extend_from_slice(name.as_bytes());
+            index.push(EntryKind::from_name(name).to_byte());
+            index.extend_from_slice(&to_u32(blobs.len()).to_le_bytes());
+            index.extend_from_slice(&to_u32(data.len()).to_le_bytes());
+            index.extend_from_slice(&fnv1a64(data).to_le_bytes());
+            blobs.extend_from_slice(data);
+        }
+
+        let mut bytes = Vec::with_capacity(10 + index.len() + blobs.len());
+        bytes.extend_from_slice(BUNDLE_MAGIC);
+        bytes.extend_from_slice(&BUNDLE_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&to_u32(files.len()).to_le_bytes());
+        bytes.extend(index);
+        bytes.extend(blobs);
+        bytes
+    }
+
+    /// Parse + verify every entry's hash
+    /// - `path` is only used to label errors
+    pub fn parse(path: &str, bytes: &[u8]) -> Result<Self, AssetError> {
+        let malformed = |reason: &str| AssetError::Decode {
+            path: path.to_string(),
+            reason: format!("malformed bundle, {}", reason),
+        };
+        let mut reader = Reader::new(bytes);
+
+        if reader.take(4) != Some(BUNDLE_MAGIC.as_slice()) {
+            return Err(malformed("bad magic"));
+        }
+        let version = reader.u16().ok_or_else(|| malformed("missing version"))?;
+        if version != BUNDLE_VERSION {
+            return Err(malformed(&format!("unsupported version {}", version)));
+        }
+        let count = reader
+            .u32()
+            .ok_or_else(|| malformed("missing entry count"))?;
+
+        let mut entries = Vec::new();
+        let mut hashes = Vec::new();
+        for _ in 0..count {
+            let name_len = reader.u16().ok_or_else(|| malformed("truncated index"))?;
+            let name = reader
+                .take(usize::from(name_len))
+                .and_then(|name| std::str::from_utf8(name).ok())
+                .ok_or_else(|| malformed("bad entry name"))?;
+            let kind = reader
+                .u8()
+                .and_then(EntryKind::from_byte)
+                .ok_or_else(|| malformed("bad entry kind"))?;
+            let offset = reader.u32().ok_or_else(|| malformed("truncated index"))?;
+            let len = reader.u32().ok_or_else(|| malformed("truncated index"))?;
+            let hash = reader.u64().ok_or_else(|| malformed("truncated index"))?;
+            entries.push(BundleEntry {
+                name: name.to_string(),
+                kind,
+                offset: offset as usize,
+                len: len as usize,
+            });
+            hashes.push(hash);
+        }
+
+        let blobs = bytes[reader.at..].to_vec();
+        for (entry, hash) in entries.iter().zip(hashes) {
+            let data = entry
+                .range()
+                .and_then(|range| blobs.get(range))
+                .ok_or_else(|| malformed(&format!("'{}' is out of range", entry.name)))?;
+            if fnv1a64(data) != hash {
+                return Err(AssetError::Integrity {
+                    path: path.to_string(),
+                    entry: entry.name.clone(),
+                });
+            }
+        }
+
+        Ok(Self {
+            path: path.to_string(),
+            entries,
+            blobs,
+        })
+    }
+
+    pub fn entries(&self) -> &[BundleEntry] {
+        &self.entries
+    }
+
+    fn entry(&self, name: &str) -> Result<&BundleEntry, AssetError> {
+        self.entries
+            .iter()
+            .find(|entry| entry.name == name)
+            .ok_or_else(|| AssetError::NotFound {
+                path: format!("{}#{}", self.path, name),
+            })
+    }
+
+    pub fn bytes(&self, name: &str) -> Result<&[u8], AssetError> {
+        let entry = self.entry(name)?;
+        // parse() checked every range, this only fails on a hand built entry
+        entry
+            .range()
+            .and_then(|range| self.blobs.get(range))
+            .ok_or_else(|| AssetError::Decode {
+                path: format!("{}#{}", self.path, name),
+                reason: "entry is out of range".to_string(),
+            })
+    }
+
+    /// Deserialize an embedded JSON entry
+    pub fn json<T>(&self, name: &str) -> Result<T, AssetError>
+    where
+        T: DeserializeOwned,
+    {
+        asset::parse_json(&format!("{}#{}", self.path, name), self.bytes(name)?)
+    }
+
+    /// Create an image from an embedded blob via an object URL
+    /// - the URL is revoked once the image has decoded
+    pub async fn image(&self, name: &str) -> Result<HtmlImageElement, AssetError> {
+        let label = format!("{}#{}", self.path, name);
+        let url =
+            browser::create_object_url(self.bytes(name)?, mime_type(name)).map_err(|err| {
+                AssetError::Decode {
+                    path: label.clone(),
+                    reason: format!("{:#}", err),
+                }
+            })?;
+        // a blob URL is local, retrying or waiting long won't help
+        let options = LoadOptions {
+            retries: 0,
+            ..LoadOptions::default()
+        };
+        let image = asset::load_image(&url, options).await;
+        browser::revoke_object_url(&url);
+        image.map_err(|err| match err {
+            AssetError::Decode { reason, .. } => AssetError::Decode {
+                path: label,
+                reason,
+            },
+            other => other,
+        })
+    }
+}
+
+/// Fetch a bundle in one request and verify it
+pub async fn load_bundle(
+    fetcher: &impl Fetcher,
+    path: &str,
+    options: LoadOptions,
+) -> Result<Bundle, AssetError> {
+    let bytes = asset::fetch_bytes(fetcher, path, options).await?;
+    Bundle::parse(path, &bytes)
+}
+
+fn to_u32(value: usize) -> u32 {
+    u32::try_from(value).expect("bundle larger than u32::MAX bytes")
+}
+
+/// Cursor over little endian bytes, None when reading past the end
+/// - shared with the replay format
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, at: 0 }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.at >= self.bytes.len()
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let slice = self.bytes.get(self.at..self.at.checked_add(len)?)?;
+        self.at += len;
+        Some(slice)
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        self.take(1).map(|bytes| bytes[0])
+    }
+
+    pub(crate) fn u16(&mut self) -> Option<u16> {
+        self.take(2)?.try_into().ok().map(u16::from_le_bytes)
+    }
+
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        self.take(4)?.try_into().ok().map(u32::from_le_bytes)
+    }
+
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        self.take(8)?.try_into().ok().map(u64::from_le_bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    fn files() -> Vec<(String, Vec<u8>)> {
+        vec![
+            ("rhb.json".to_string(), br#"{ "name": "rhb" }"#.to_vec()),
+            ("rhb.png".to_string(), vec![0x89, b'P', b'N', b'G', 1, 2, 3]),
+        ]
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Asset {
+        name: String,
+    }
+
+    #[test]
+    fn test_build_then_parse_roundtrip() {
+        let bytes = Bundle::build(&files());
+        let bundle = Bundle::parse("assets.pack", &bytes).unwrap();
+
+        assert_eq!(bundle.entries().len(), 2);
+        assert_eq!(bundle.entries()[1].kind, EntryKind::Image);
+        assert_eq!(bundle.bytes("rhb.png").unwrap(), files()[1].1.as_slice());
+        assert_eq!(bundle.json::<Asset>("rhb.json").unwrap().name, "rhb");
+        assert_eq!(
+            bundle.bytes("BG.png").unwrap_err(),
+            AssetError::NotFound {
+                path: "assets.pack#BG.png".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_corrupted_blob_fails_integrity_check() {
+        let mut bytes = Bundle::build(&files());
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+
+        assert_eq!(
+            Bundle::parse("assets.pack", &bytes).unwrap_err(),
+            AssetError::Integrity {
+                path: "assets.pack".to_string(),
+                entry: "rhb.png".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn test_malformed_bundles_are_decode_errors() {
+        let bytes = Bundle::build(&files());
+        for bad in [
+            &b"<html>404</html>"[..],
+            &bytes[..20],
+            &bytes[..bytes.len() - 1],
+        ] {
+            assert!(matches!(
+                Bundle::parse("assets.pack", bad),
+                Err(AssetError::Decode { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_out_of_range_entries_are_decode_errors() {
+        // magic, version, count, then rhb.json's name length, name and kind
+        let offset_at = 4 + 2 + 4 + 2 + "rhb.json".len() + 1;
+        let mut bytes = Bundle::build(&files());
+        bytes[offset_at..offset_at + 8].fill(0xff);
+
+        assert!(matches!(
+            Bundle::parse("assets.pack", &bytes),
+            Err(AssetError::Decode { .. })
+        ));
+
+        let bundle = Bundle {
+            path: "assets.pack".to_string(),
+            entries: vec![BundleEntry {
+                name: "rhb.json".to_string(),
+                kind: EntryKind::Json,
+                offset: usize::MAX,
+                len: 1,
+            }],
+            blobs: Vec::new(),
+        };
+        assert!(matches!(
+            bundle.bytes("rhb.json"),
+            Err(AssetError::Decode { .. })
+        ));
+    }
+
+    #[test]
+    fn test_fnv1a64_known_values() {
+        assert_eq!(fnv1a64(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a64(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+}
@@ -0,0 +1,385 @@
+/// Gamepad input, polled every update through the browser Gamepad API
+/// - only "standard" mapping pads drive actions, other layouts are unknown
+/// - connect / disconnect come from diffing polls, so a fake source
+///   produces them just like the browser does
+///
+/// TABLE: standard mapping (https://w3c.github.io/gamepad/#remapping)
+/// ┌─ index ─┬─ Button ───────┬─ index ─┬─ Button ────────────────────┐
+/// │ 0       │ South (A / ✕)  │ 9       │ Start                       │
+/// │ 1       │ East  (B / ○)  │ 10      │ LeftStick click             │
+/// │ 2       │ West  (X / □)  │ 11      │ RightStick click            │
+/// │ 3       │ North (Y / △)  │ 12-15   │ Dpad up/down/left/right     │
+/// │ 4 / 5   │ Bumpers L / R  │ 16      │ Home                        │
+/// │ 6 / 7   │ Triggers L / R │ axes    │ 0,1 left 2,3 right, +y down │
+/// │ 8       │ Select         │         │                             │
+/// └─────────┴────────────────┴─────────┴─────────────────────────────┘
+use crate::browser;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use wasm_bindgen::JsCast;
+use web_sys::GamepadMappingType;
+
+/// Stick values inside this radius read as centered
+pub const DEFAULT_DEADZONE: f64 = 0.25;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Button {
+    South,
+    East,
+    West,
+    North,
+    LeftBumper,
+    RightBumper,
+    LeftTrigger,
+    RightTrigger,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DpadUp,
+    DpadDown,
+    DpadLeft,
+    DpadRight,
+    Home,
+}
+
+impl Button {
+    /// Index in Gamepad.buttons under the standard mapping
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// A stick pushed one way, past the deadzone
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StickDirection {
+    LeftUp,
+    LeftDown,
+    LeftLeft,
+    LeftRight,
+    RightUp,
+    RightDown,
+    RightLeft,
+    RightRight,
+}
+
+impl StickDirection {
+    /// (x axis, y axis) of the stick, and the axis + sign this direction reads
+    fn axes(self) -> ((usize, usize), usize, f64) {
+        use StickDirection::*;
+        let stick = match self {
+            LeftUp | LeftDown | LeftLeft | LeftRight => (0, 1),
+            RightUp | RightDown | RightLeft | RightRight => (2, 3),
+        };
+        match self {
+            LeftUp | RightUp => (stick, stick.1, -1.0),
+            LeftDown | RightDown => (stick, stick.1, 1.0),
+            LeftLeft | RightLeft => (stick, stick.0, -1.0),
+            LeftRight | RightRight => (stick, stick.0, 1.0),
+        }
+    }
+}
+
+/// One poll of one pad, as read from the browser (or a fake)
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamepadSnapshot {
+    pub index: u32,
+    pub id: String,
+    pub standard: bool,
+    pub buttons: Vec<bool>,
+    pub axes: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum GamepadEvent {
+    Connected {
+        index: u32,
+        id: String,
+        standard: bool,
+    },
+    Disconnected {
+        index: u32,
+        id: String,
+    },
+}
+
+/// Where pads are polled from, the browser in game and a fake in tests
+pub trait GamepadSource {
+    /// Every pad connected right now
+    fn poll(&mut self) -> Vec<GamepadSnapshot>;
+}
+
+/// navigator.getGamepads()
+#[derive(Debug, Default)]
+pub struct BrowserGamepads;
+
+impl GamepadSource for BrowserGamepads {
+    fn poll(&mut self) -> Vec<GamepadSnapshot> {
+        let Ok(pads) = browser::window().and_then(|window| {
+            window
+                .navigator()
+                .get_gamepads()
+                .map_err(|err| anyhow::anyhow!("getGamepads failed : {:#?}", err))
+        }) else {
+            return Vec::new();
+        };
+        // the array has holes (null) where a pad was unplugged
+        pads.iter()
+            .filter_map(|pad| pad.dyn_into::<web_sys::Gamepad>().ok())
+            .filter(|pad| pad.connected())
+            .map(|pad| GamepadSnapshot {
+                index: pad.index(),
+                id: pad.id(),
+                standard: pad.mapping() == GamepadMappingType::Standard,
+                buttons: pad
+                    .buttons()
+                    .iter()
+                    .map(|button| {
+                        button
+                            .dyn_into::<web_sys::GamepadButton>()
+                            .is_ok_and(|button| button.pressed())
+                    })
+                    .collect(),
+                axes: pad
+                    .axes()
+                    .iter()
+                    .map(|axis| axis.as_f64().unwrap_or(0.0))
+                    .collect(),
+            })
+            .collect()
+    }
+}
+
+/// Connected pads as of the last update
+#[derive(Debug, Clone, PartialEq)]
+pub struct GamepadState {
+    pads: BTreeMap<u32, GamepadSnapshot>,
+    deadzone: f64,
+}
+
+impl Default for GamepadState {
+    fn default() -> Self {
+        Self::new(DEFAULT_DEADZONE)
+    }
+}
+
+impl GamepadState {
+    pub fn new(deadzone: f64) -> Self {
+        Self {
+            pads: BTreeMap::new(),
+            deadzone: deadzone.clamp(0.0, 0.99),
+        }
+    }
+
+    /// Replace the state with a fresh poll, returning what (dis)connected
+    pub fn update(&mut self, source: &mut dyn GamepadSource) -> Vec<GamepadEvent> {
+        let polled: BTreeMap<u32, GamepadSnapshot> = source
+            .poll()
+            .into_iter()
+            .map(|pad| (pad.index, pad))
+            .collect();
+
+        let mut events: Vec<GamepadEvent> = self
+            .pads
+            .values()
+            .filter(|pad| polled.get(&pad.index).map(|now| &now.id) != Some(&pad.id))
+            .map(|pad| GamepadEvent::Disconnected {
+                index: pad.index,
+                id: pad.id.clone(),
+            })
+            .collect();
+        events.extend(
+            polled
+                .values()
+                .filter(|pad| self.pads.get(&pad.index).map(|was| &was.id) != Some(&pad.id))
+                .map(|pad| GamepadEvent::Connected {
+                    index: pad.index,
+                    id: pad.id.clone(),
+                    standard: pad.standard,
+                }),
+        );
+
+        self.pads = polled;
+        events
+    }
+
+    fn standard_pads(&self) -> impl Iterator<Item = &GamepadSnapshot> {
+        self.pads.values().filter(|pad| pad.standard)
+    }
+
+    /// Held on any standard pad
+    pub fn is_pressed(&self, button: Button) -> bool {
+        self.standard_pads()
+            .any(|pad| pad.buttons.get(button.index()).copied().unwrap_or(false))
+    }
+
+    /// How far the stick is pushed `direction`, 0.0 inside the deadzone and
+    /// rescaled so the edge of the deadzone reads 0.0 and full tilt 1.0
+    pub fn stick(&self, direction: StickDirection) -> f64 {
+        let ((x, y), axis, sign) = direction.axes();
+        self.standard_pads()
+            .map(|pad| {
+                let read = |index: usize| pad.axes.get(index).copied().unwrap_or(0.0);
+                // radial deadzone : a diagonal isn't cut more than a straight push
+                let magnitude = read(x).hypot(read(y));
+                if magnitude <= self.deadzone {
+                    return 0.0;
+                }
+                let scale = ((magnitude - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+                (read(axis) * sign / magnitude * scale).max(0.0)
+            })
+            .fold(0.0, f64::max)
+    }
+
+    /// Pushed mostly `direction`, so a stick reads as one direction at a time
+    /// - the axis pushed furthest wins, horizontal on an exact diagonal
+    /// - with several pads the one pushed furthest wins, they can't cancel
+    ///   out into Run and RunLeft together
+    pub fn is_pushed(&self, direction: StickDirection) -> bool {
+        let ((x, y), axis, sign) = direction.axes();
+        self.standard_pads()
+            .filter_map(|pad| {
+                let read = |index: usize| pad.axes.get(index).copied().unwrap_or(0.0);
+                let magnitude = read(x).hypot(read(y));
+                let dominant = if read(x).abs() >= read(y).abs() { x } else { y };
+                (magnitude > self.deadzone).then(|| (magnitude, dominant, read(dominant).signum()))
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))
+            .is_some_and(|(_, dominant, pushed)| dominant == axis && pushed == sign)
+    }
+}
+
+#[cfg(test)]
+pub mod fake {
+    use super::*;
+
+    /// Scripted polls, one Vec of pads per update, then stays on the last
+    #[derive(Debug, Default)]
+    pub struct FakeGamepads {
+        pub polls: Vec<Vec<GamepadSnapshot>>,
+    }
+
+    impl GamepadSource for FakeGamepads {
+        fn poll(&mut self) -> Vec<GamepadSnapshot> {
+            if self.polls.len() > 1 {
+                self.polls.remove(0)
+            } else {
+                self.polls.first().cloned().unwrap_or_default()
+            }
+        }
+    }
+
+    /// A standard pad with `pressed` buttons held and sticks at `axes`
+    pub fn pad(index: u32, pressed: &[Button], axes: [f64; 4]) -> GamepadSnapshot {
+        let mut buttons = vec![false; 17];
+        for button in pressed {
+            buttons[button.index()] = true;
+        }
+        GamepadSnapshot {
+            index,
+            id: format!("Fake pad {}", index),
+            standard: true,
+            buttons,
+            axes: axes.to_vec(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::*;
+    use super::*;
+
+    #[test]
+    fn test_connect_and_disconnect_events() {
+        let mut source = FakeGamepads {
+            polls: vec![vec![], vec![pad(0, &[], [0.0; 4])], vec![]],
+        };
+        let mut state = GamepadState::default();
+        assert!(state.update(&mut source).is_empty());
+        assert_eq!(
+            state.update(&mut source),
+            [GamepadEvent::Connected {
+                index: 0,
+                id: "Fake pad 0".to_string(),
+                standard: true
+            }]
+        );
+        assert_eq!(
+            state.update(&mut source),
+            [GamepadEvent::Disconnected {
+                index: 0,
+                id: "Fake pad 0".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_buttons_from_any_standard_pad() {
+        let mut other = pad(1, &[Button::Start], [0.0; 4]);
+        other.standard = false;
+        let mut source = FakeGamepads {
+            polls: vec![vec![pad(0, &[Button::South], [0.0; 4]), other]],
+        };
+        let mut state = GamepadState::default();
+        state.update(&mut source);
+        assert!(state.is_pressed(Button::South));
+        // unknown layout, its buttons don't count
+        assert!(!state.is_pressed(Button::Start));
+    }
+
+    #[test]
+    fn test_stick_deadzone_is_radial_and_rescaled() {
+        let mut source = FakeGamepads {
+            polls: vec![vec![pad(0, &[], [0.2, 0.0, 0.0, 1.0])]],
+        };
+        let mut state = GamepadState::new(0.25);
+        state.update(&mut source);
+        assert_eq!(state.stick(StickDirection::LeftRight), 0.0);
+        assert!(!state.is_pushed(StickDirection::LeftRight));
+        approx::assert_relative_eq!(state.stick(StickDirection::RightDown), 1.0);
+        assert_eq!(state.stick(StickDirection::RightUp), 0.0);
+
+        let mut source = FakeGamepads {
+            polls: vec![vec![pad(0, &[], [0.625, 0.0, 0.0, 0.0])]],
+        };
+        state.update(&mut source);
+        approx::assert_relative_eq!(state.stick(StickDirection::LeftRight), 0.5);
+    }
+
+    #[test]
+    fn test_diagonal_push_is_one_direction() {
+        let mut state = GamepadState::new(0.25);
+        let mut pushed = |axes: [f64; 4]| {
+            state.update(&mut FakeGamepads {
+                polls: vec![vec![pad(0, &[], axes)]],
+            });
+            [
+                StickDirection::LeftRight,
+                StickDirection::LeftLeft,
+                StickDirection::LeftDown,
+                StickDirection::LeftUp,
+            ]
+            .map(|direction| state.is_pushed(direction))
+        };
+        assert_eq!(pushed([0.8, 0.6, 0.0, 0.0]), [true, false, false, false]);
+        assert_eq!(pushed([0.6, 0.8, 0.0, 0.0]), [false, false, true, false]);
+        assert_eq!(pushed([-0.7, -0.7, 0.0, 0.0]), [false, true, false, false]);
+    }
+
+    #[test]
+    fn test_furthest_pushed_pad_wins() {
+        let mut source = FakeGamepads {
+            polls: vec![vec![
+                pad(0, &[], [0.5, 0.0, 0.0, 0.0]),
+                pad(1, &[], [-0.9, 0.0, 0.0, 0.0]),
+            ]],
+        };
+        let mut state = GamepadState::new(0.25);
+        state.update(&mut source);
+        assert!(state.is_pushed(StickDirection::LeftLeft));
+        assert!(!state.is_pushed(StickDirection::LeftRight));
+    }
+}
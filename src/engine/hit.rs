@@ -0,0 +1,231 @@
+/// Hit testing : what on the canvas is under the pointer
+/// - the game lists Hitboxes every tick, each with an id it picks (an enum
+///   of entities / UI elements works well), a Rect, a z and a Space
+/// - World boxes are tested through the Camera, Screen boxes (UI) aren't
+/// - where boxes overlap the highest z wins, on equal z the later one
+///   (drawn last, so on top)
+///
+/// TABLE: one click on the stone
+/// ┌─ tick ─┬─ pointer ─────────────┬─ hovered ─┬─ pressed ─┬─ clicked ─┐
+/// │ 1      │ moves over the stone  │ Stone     │           │           │
+/// │ 2      │ primary down          │ Stone     │ Stone     │           │
+/// │ 3      │ primary up            │ Stone     │           │ Stone     │
+/// │ 4      │ down, drag off, up    │           │ Stone     │ (none)    │
+/// └────────┴───────────────────────┴───────────┴───────────┴───────────┘
+/// A click is a press and release over the same target, like a DOM click
+use crate::engine::pointer::{PointerButton, PointerState};
+use crate::engine::{Camera, Point, Rect};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Space {
+    /// moves with the camera, e.g. the boy or the stone
+    World,
+    /// pinned to the canvas, e.g. a pause button
+    Screen,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Hitbox<T> {
+    pub id: T,
+    pub rect: Rect,
+    pub z: i32,
+    pub space: Space,
+}
+
+/// What the pointer did this tick, by target id
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Hits<T> {
+    /// under the pointer, while it's over the canvas
+    pub hovered: Option<T>,
+    /// primary button went down on it this tick
+    pub pressed: Option<T>,
+    /// primary button went up on the same target it went down on
+    pub clicked: Option<T>,
+}
+
+impl<T> Default for Hits<T> {
+    fn default() -> Self {
+        Self {
+            hovered: None,
+            pressed: None,
+            clicked: None,
+        }
+    }
+}
+
+/// The topmost target at `screen`, a canvas point
+pub fn topmost<'a, T>(
+    screen: Point,
+    camera: &Camera,
+    targets: &'a [Hitbox<T>],
+) -> Option<&'a Hitbox<T>> {
+    let world = camera.to_world(screen);
+    targets
+        .iter()
+        .filter(|target| {
+            let point = match target.space {
+                Space::World => world,
+                Space::Screen => screen,
+            };
+            target.rect.contains(point)
+        })
+        // max_by_key keeps the last of equal keys : later wins ties
+        .max_by_key(|target| target.z)
+}
+
+/// Remembers what a press landed on, so its release can become a click
+#[derive(Debug, Clone, Default)]
+pub struct HitTester<T> {
+    pressed_on: Option<T>,
+}
+
+impl<T: Copy + PartialEq> HitTester<T> {
+    pub fn new() -> Self {
+        Self { pressed_on: None }
+    }
+
+    /// Hit test one tick, call it every tick with every target
+    pub fn update(
+        &mut self,
+        pointer: &PointerState,
+        camera: &Camera,
+        targets: &[Hitbox<T>],
+    ) -> Hits<T> {
+        let Some(position) = pointer.position() else {
+            self.pressed_on = None;
+            return Hits::default();
+        };
+        let under = topmost(position, camera, targets).map(|target| target.id);
+        let mut hits = Hits {
+            hovered: under.filter(|_| pointer.is_over()),
+            ..Hits::default()
+        };
+        if pointer.just_pressed(PointerButton::Primary) {
+            self.pressed_on = under;
+            hits.pressed = under;
+        }
+        if pointer.just_released(PointerButton::Primary) {
+            hits.clicked = self.pressed_on.take().filter(|id| under == Some(*id));
+        } else if !pointer.is_down(PointerButton::Primary) {
+            // cancelled, the release is never coming
+            self.pressed_on = None;
+        }
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::pointer::{PointerPhase, PointerSample};
+    use crate::engine::Size;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq)]
+    enum Target {
+        Boy,
+        Stone,
+        Button,
+    }
+
+    fn hitbox(id: Target, x: i16, z: i32, space: Space) -> Hitbox<Target> {
+        Hitbox {
+            id,
+            rect: Rect::new(
+                Point { x, y: 0 },
+                Size {
+                    width: 100,
+                    height: 100,
+                },
+            ),
+            z,
+            space,
+        }
+    }
+
+    /// One tick of pointer samples at x, y = 50
+    fn tick(pointer: &mut PointerState, events: &[(PointerPhase, i16)]) {
+        pointer.begin_tick();
+        for (phase, x) in events {
+            pointer.feed(PointerSample {
+                id: 1,
+                phase: *phase,
+                position: Point { x: *x, y: 50 },
+                buttons: u8::from(*phase == PointerPhase::Down),
+                time_ms: 0.0,
+            });
+        }
+    }
+
+    #[test]
+    fn test_higher_z_then_later_wins() {
+        let camera = Camera::default();
+        let targets = [
+            hitbox(Target::Boy, 0, 1, Space::World),
+            hitbox(Target::Stone, 50, 0, Space::World),
+            hitbox(Target::Button, 50, 0, Space::World),
+        ];
+        let at = |x| topmost(Point { x, y: 50 }, &camera, &targets).map(|target| target.id);
+        assert_eq!(at(75), Some(Target::Boy));
+        assert_eq!(at(125), Some(Target::Button));
+        assert_eq!(at(200), None);
+    }
+
+    #[test]
+    fn test_world_boxes_go_through_the_camera() {
+        let camera = Camera {
+            position: Point { x: 500, y: 0 },
+            zoom: 2.0,
+        };
+        let targets = [
+            hitbox(Target::Stone, 550, 0, Space::World),
+            hitbox(Target::Button, 0, 0, Space::Screen),
+        ];
+        // screen 120 is world 500 + 120 / 2 = 560
+        let at = |x| topmost(Point { x, y: 50 }, &camera, &targets).map(|target| target.id);
+        assert_eq!(at(120), Some(Target::Stone));
+        assert_eq!(at(20), Some(Target::Button));
+    }
+
+    #[test]
+    fn test_click_needs_press_and_release_on_the_same_target() {
+        let camera = Camera::default();
+        let targets = [
+            hitbox(Target::Boy, 0, 0, Space::World),
+            hitbox(Target::Stone, 200, 0, Space::World),
+        ];
+        let mut pointer = PointerState::default();
+        let mut tester = HitTester::new();
+
+        tick(&mut pointer, &[(PointerPhase::Move, 250)]);
+        let hits = tester.update(&pointer, &camera, &targets);
+        assert_eq!(hits.hovered, Some(Target::Stone));
+        assert_eq!(hits.pressed, None);
+
+        tick(&mut pointer, &[(PointerPhase::Down, 250)]);
+        let hits = tester.update(&pointer, &camera, &targets);
+        assert_eq!(hits.pressed, Some(Target::Stone));
+
+        tick(&mut pointer, &[(PointerPhase::Up, 260)]);
+        let hits = tester.update(&pointer, &camera, &targets);
+        assert_eq!(hits.clicked, Some(Target::Stone));
+
+        // dragged off onto the boy before letting go : no click
+        tick(&mut pointer, &[(PointerPhase::Down, 250)]);
+        tester.update(&pointer, &camera, &targets);
+        tick(&mut pointer, &[(PointerPhase::Up, 50)]);
+        let hits = tester.update(&pointer, &camera, &targets);
+        assert_eq!(hits.clicked, None);
+        assert_eq!(hits.hovered, Some(Target::Boy));
+
+        // a finger lifting then leaving in one tick still clicks
+        tick(&mut pointer, &[(PointerPhase::Down, 50)]);
+        tester.update(&pointer, &camera, &targets);
+        tick(
+            &mut pointer,
+            &[(PointerPhase::Up, 50), (PointerPhase::Leave, 50)],
+        );
+        let hits = tester.update(&pointer, &camera, &targets);
+        assert_eq!(hits.clicked, Some(Target::Boy));
+        assert_eq!(hits.hovered, None);
+    }
+}
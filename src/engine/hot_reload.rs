@@ -0,0 +1,130 @@
+/// Development only hot reload of assets, without restarting the game
+/// - a watcher polls a small version endpoint : { "rhb.json": <mtime>, ... }
+/// - JS can also ask directly : reload_assets("rhb.json") (see lib.rs)
+/// - either way the path lands in a queue the game drains in update()
+///
+/// ELI5:
+/// ┌──────────────── Hot Reload Flow ──────────────────────────────┐
+/// │                                                               │
+/// │  webpack dev server          JS console                       │
+/// │  asset-version.json          reload_assets("rhb.png")         │
+/// │        │ poll                       │                         │
+/// │        ▼                            ▼                         │
+/// │  changed_paths(old, new) ──► PENDING queue (paths)            │
+/// │                                     │ take_pending()          │
+/// │                                     ▼                         │
+/// │                    Game::update re-fetches + swaps assets     │
+/// └───────────────────────────────────────────────────────────────┘
+use crate::browser;
+use crate::engine::asset::{self, AssetError, BrowserFetcher, LoadOptions};
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Asset path -> version, any change in version means "reload me"
+pub type AssetVersions = HashMap<String, u64>;
+
+thread_local! {
+    // wasm is single threaded, a thread_local is the one global queue that
+    // both the watcher task and the JS entry point can reach
+    static PENDING: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Queue `path` for reload, duplicates are dropped
+pub fn request_reload(path: &str) {
+    PENDING.with(|pending| {
+        let mut pending = pending.borrow_mut();
+        if !pending.iter().any(|queued| queued == path) {
+            pending.push(path.to_string());
+        }
+    });
+}
+
+/// Drain every path queued since the last call
+pub fn take_pending() -> Vec<String> {
+    PENDING.with(|pending| pending.take())
+}
+
+/// Paths that are new or whose version moved, sorted
+/// - removed paths are ignored, there's nothing to reload
+pub fn changed_paths(old: &AssetVersions, new: &AssetVersions) -> Vec<String> {
+    let mut changed: Vec<String> = new
+        .iter()
+        .filter(|(path, version)| old.get(*path) != Some(*version))
+        .map(|(path, _)| path.clone())
+        .collect();
+    changed.sort();
+    changed
+}
+
+/// Append a cache busting query, so the browser refetches a reloaded asset
+pub fn cache_busted(path: &str) -> String {
+    let stamp = browser::now().unwrap_or_default() as u64;
+    format!("{}?v={}", path, stamp)
+}
+
+/// Poll `version_path` every `poll_ms` and queue whatever changed
+/// - the first answer is the baseline, nothing is reloaded for it
+/// - stops for good when the endpoint doesn't exist (not a dev server)
+pub fn watch(version_path: &'static str, poll_ms: u32) {
+    // a poll must never stack up behind a slow one, fail fast instead
+    let options = LoadOptions {
+        timeout_ms: poll_ms,
+        retries: 0,
+        ..LoadOptions::default()
+    };
+    browser::spawn_local(async move {
+        let mut known: Option<AssetVersions> = None;
+        loop {
+            let path = cache_busted(version_path);
+            match asset::fetch_json::<AssetVersions>(&BrowserFetcher, &path, options).await {
+                Ok(versions) => {
+                    if let Some(old) = &known {
+                        for changed in changed_paths(old, &versions) {
+                            log!("[hot_reload] {} changed", changed);
+                            request_reload(&changed);
+                        }
+                    }
+                    known = Some(versions);
+                }
+                Err(AssetError::NotFound { .. }) => {
+                    log!("[hot_reload] no {}, asset watcher stopped", version_path);
+                    return;
+                }
+                // dev server restarting etc. just try again next poll
+                Err(_) => {}
+            }
+            if browser::sleep(poll_ms).await.is_err() {
+                return;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn versions(entries: &[(&str, u64)]) -> AssetVersions {
+        entries
+            .iter()
+            .map(|(path, version)| (path.to_string(), *version))
+            .collect()
+    }
+
+    #[test]
+    fn test_changed_paths_reports_new_and_modified() {
+        let old = versions(&[("rhb.json", 1), ("rhb.png", 1), ("BG.png", 1)]);
+        let new = versions(&[("rhb.json", 2), ("rhb.png", 1), ("Stone.png", 1)]);
+        assert_eq!(changed_paths(&old, &new), ["Stone.png", "rhb.json"]);
+        assert!(changed_paths(&new, &new).is_empty());
+    }
+
+    #[test]
+    fn test_pending_queue_drops_duplicates() {
+        request_reload("rhb.json");
+        request_reload("rhb.png");
+        request_reload("rhb.json");
+        assert_eq!(take_pending(), ["rhb.json", "rhb.png"]);
+        assert!(take_pending().is_empty());
+    }
+}
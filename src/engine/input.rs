@@ -0,0 +1,428 @@
+use crate::browser;
+use crate::engine::action::{self, ActionState, Bindings, Inputs};
+use crate::engine::gamepad::{BrowserGamepads, GamepadEvent, GamepadSource, GamepadState};
+use crate::engine::keycode::KeyCode;
+use crate::engine::pointer::{
+    self, ClientRect, GestureRecognizer, PointerPhase, PointerSample, PointerState,
+};
+use crate::engine::Point;
+use anyhow::{Context, Result};
+use futures::channel::mpsc::{unbounded, UnboundedReceiver};
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use web_sys::{KeyboardEvent, PointerEvent};
+
+#[derive(Debug)]
+/// Because we can't determine what kind of KeyboardEvent is returned :
+/// - this enum wraps the event's code as a key up or key down
+/// - effectively let's us manage one channel (as opposed to two+)
+/// - only the KeyCode crosses the channel, the JS event object is dropped
+///   in the listener
+enum KeyPress {
+    KeyUp(KeyCode),
+    KeyDown(KeyCode),
+}
+
+#[derive(Debug, Default)]
+/// Keys are KeyCodes, a generic physical keyboard (see engine::keycode)
+///
+/// Edges are per tick (one fixed update) :
+/// - just pressed / just released only hold for the tick that saw the event
+/// - a tap that starts and ends inside one tick is both, but never held
+///
+/// TABLE: Space tapped then held
+/// ┌─ tick ─┬─ events ──────┬─ just_pressed ─┬─ held ─┬─ held_ticks ─┬─ just_released ─┐
+/// │ 1      │ down          │ true           │ true   │ 1            │ false           │
+/// │ 2      │ (repeat down) │ false          │ true   │ 2            │ false           │
+/// │ 3      │ up            │ false          │ false  │ 0            │ true            │
+/// │ 4      │ down, up      │ true           │ false  │ 0            │ true            │
+/// └────────┴───────────────┴────────────────┴────────┴──────────────┴─────────────────┘
+pub struct KeyState {
+    // code -> ticks held, including the current one
+    held_ticks: HashMap<KeyCode, u32>,
+    just_pressed: HashSet<KeyCode>,
+    just_released: HashSet<KeyCode>,
+}
+
+impl KeyState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_pressed(&self, code: KeyCode) -> bool {
+        self.held_ticks.contains_key(&code)
+    }
+
+    /// Went down this tick, even if it already went back up
+    pub fn just_pressed(&self, code: KeyCode) -> bool {
+        self.just_pressed.contains(&code)
+    }
+
+    pub fn just_released(&self, code: KeyCode) -> bool {
+        self.just_released.contains(&code)
+    }
+
+    /// Ticks the key has been held, 0 when up
+    pub fn held_ticks(&self, code: KeyCode) -> u32 {
+        self.held_ticks.get(&code).copied().unwrap_or(0)
+    }
+
+    /// Start a new tick : the previous tick's edges no longer apply
+    pub(crate) fn begin_tick(&mut self) {
+        self.just_pressed.clear();
+        self.just_released.clear();
+    }
+
+    /// Close the tick : every key still down was held one tick longer
+    pub(crate) fn end_tick(&mut self) {
+        for ticks in self.held_ticks.values_mut() {
+            *ticks = ticks.saturating_add(1);
+        }
+    }
+
+    pub(crate) fn press(&mut self, code: KeyCode) {
+        // browsers repeat keydown while a key is held, only the first counts
+        if let Entry::Vacant(entry) = self.held_ticks.entry(code) {
+            entry.insert(0);
+            self.just_pressed.insert(code);
+        }
+    }
+
+    pub(crate) fn release(&mut self, code: KeyCode) {
+        if self.held_ticks.remove(&code).is_some() {
+            self.just_released.insert(code);
+        }
+    }
+}
+
+/// TABLE:
+/// ┌────────────── Input Processing Flow ──────────────────┐
+/// │                                                       │
+/// │ KeyboardEvent ──code()──► KeyCode                     │
+/// │     │                                                 │
+/// │     ▼                                                 │
+/// │ KeyPress(enum)        UnboundedReceiver               │
+/// │  ├─KeyUp ─────────────────────┐                       │
+/// │  └─KeyDown                    │                       │
+/// │     │                         │                       │
+/// │     ▼                         ▼                       │
+/// │ InputHandler ──────────► KeyState(KeyCode sets)       │
+/// │     │                    │                            │
+/// │     └──update()──────────┘                            │
+/// │                          │ Bindings::resolve()        │
+/// │                          ▼                            │
+/// │                     ActionState ──► Game::update()    │
+/// └───────────────────────────────────────────────────────┘
+///
+/// InputHandler encapsulates :
+/// - keystate: KeyState
+/// - receiver: UnboundedReceiver<KeyPress>
+/// - gamepads: GamepadState, polled from `gamepad_source` every update
+/// - gestures: GestureRecognizer, fed canvas pointer events every update
+/// - pointer: PointerState, fed the same events, for hit testing
+/// - bindings: Bindings, resolved into actions every update
+///
+/// Provides a cleaner interface and hides implemntation
+/// details of input processing
+pub struct InputHandler {
+    keystate: KeyState,
+    receiver: UnboundedReceiver<KeyPress>,
+    gamepads: GamepadState,
+    gamepad_source: Box<dyn GamepadSource>,
+    gestures: GestureRecognizer,
+    pointer: PointerState,
+    pointer_receiver: UnboundedReceiver<PointerSample>,
+    bindings: Bindings,
+    actions: ActionState,
+}
+
+impl InputHandler {
+    // a) Self (capital S) refers to the TYPE itself (InputHandler)
+    //  - Self in new() is good practice, easier to maintain because it
+    //  reduces change, like if the type name changes
+    // b) self (lowercase s) refers to an INSTANCE of the type
+    pub fn new(bindings: Bindings) -> Result<Self> {
+        let (keystate, receiver) = prepare_input()?;
+        let pointer_receiver = prepare_pointer_input()?;
+        action::publish_bindings(&bindings);
+        Ok(InputHandler {
+            keystate,
+            receiver,
+            gamepads: GamepadState::default(),
+            gamepad_source: Box::new(BrowserGamepads),
+            gestures: GestureRecognizer::default(),
+            pointer: PointerState::default(),
+            pointer_receiver,
+            bindings,
+            actions: ActionState::default(),
+        })
+    }
+
+    /// Advance input by one tick, call once per fixed update
+    /// - events queued since the last tick all land in this one
+    /// - with several fixed updates per frame only the first sees their edges
+    pub fn update(&mut self) {
+        self.apply_binding_changes();
+        process_input(&mut self.keystate, &mut self.receiver);
+        for event in self.gamepads.update(self.gamepad_source.as_mut()) {
+            match event {
+                GamepadEvent::Connected {
+                    index,
+                    id,
+                    standard,
+                } => {
+                    let note = if standard {
+                        ""
+                    } else {
+                        " (not standard mapping, ignored)"
+                    };
+                    log!("Gamepad {} connected : {}{}", index, id, note);
+                }
+                GamepadEvent::Disconnected { index, id } => {
+                    log!("Gamepad {} disconnected : {}", index, id);
+                }
+            }
+        }
+        process_pointer_input(
+            &mut self.gestures,
+            &mut self.pointer,
+            &mut self.pointer_receiver,
+        );
+        let inputs = Inputs {
+            keys: &self.keystate,
+            gamepads: &self.gamepads,
+            gestures: &self.gestures,
+        };
+        self.actions = self.bindings.resolve(&inputs, &self.actions);
+    }
+
+    pub fn get_keystate(&self) -> &KeyState {
+        &self.keystate
+    }
+
+    pub fn get_gamepads(&self) -> &GamepadState {
+        &self.gamepads
+    }
+
+    pub fn get_gestures(&self) -> &GestureRecognizer {
+        &self.gestures
+    }
+
+    pub fn get_pointer(&self) -> &PointerState {
+        &self.pointer
+    }
+
+    pub fn get_actions(&self) -> &ActionState {
+        &self.actions
+    }
+
+    pub fn bindings(&self) -> &Bindings {
+        &self.bindings
+    }
+
+    /// Apply rebinds queued from JS, saving the result for the next session
+    fn apply_binding_changes(&mut self) {
+        let changes = action::take_binding_changes();
+        if changes.is_empty() {
+            return;
+        }
+        for change in changes {
+            self.bindings.apply(change);
+        }
+        action::publish_bindings(&self.bindings);
+        self.bindings.save();
+    }
+}
+
+/// Prepare Input :
+/// - listens for key events (KeyPress)
+/// - puts key events into a channel
+fn prepare_input() -> Result<(KeyState, UnboundedReceiver<KeyPress>)> {
+    // unbounded() channels have no limits on it buffer size, used here:
+    // - we don't expect keyboard events to overflow memory
+    // - we process events quickly in each frame
+    // - avoiding backpressure handling simplifies the code
+    let (keydown_sender, keyevent_receiver) = unbounded();
+    let keydown_sender = Rc::new(RefCell::new(keydown_sender));
+    let keyup_sender = Rc::clone(&keydown_sender);
+
+    let onkeydown = browser::closure_wrap(Box::new(move |keycode: KeyboardEvent| {
+        log!("Key pressed: {}", keycode.key());
+        if let Some(code) = parse_code(&keycode) {
+            let _ = keydown_sender
+                .borrow_mut()
+                .start_send(KeyPress::KeyDown(code));
+        }
+    }) as Box<dyn FnMut(KeyboardEvent)>);
+    let onkeyup = browser::closure_wrap(Box::new(move |keycode: KeyboardEvent| {
+        log!("Key released: {}", keycode.key());
+        if let Some(code) = parse_code(&keycode) {
+            let _ = keyup_sender.borrow_mut().start_send(KeyPress::KeyUp(code));
+        }
+    }) as Box<dyn FnMut(KeyboardEvent)>);
+
+    let window = browser::window().context("Window element not found")?;
+
+    window.set_onkeydown(Some(onkeydown.as_ref().unchecked_ref()));
+    window.set_onkeyup(Some(onkeyup.as_ref().unchecked_ref()));
+
+    onkeydown.forget();
+    onkeyup.forget();
+
+    Ok((KeyState::new(), keyevent_receiver))
+}
+
+/// KeyboardEvent.code as a KeyCode, None (and logged) for keys we don't know
+fn parse_code(event: &KeyboardEvent) -> Option<KeyCode> {
+    match event.code().parse() {
+        Ok(code) => Some(code),
+        Err(err) => {
+            log!("Ignoring key : {}", err);
+            None
+        }
+    }
+}
+
+/// Prepare Pointer Input :
+/// - listens for pointer events on the canvas (mouse, touch and pen alike)
+/// - puts them into a channel as samples in canvas coordinates
+fn prepare_pointer_input() -> Result<UnboundedReceiver<PointerSample>> {
+    let (sender, receiver) = unbounded();
+    let canvas = browser::canvas()?;
+    let target = canvas.clone();
+
+    let onpointer = browser::closure_wrap(Box::new(move |event: PointerEvent| {
+        let phase = match event.type_().as_str() {
+            "pointerdown" => PointerPhase::Down,
+            "pointermove" => PointerPhase::Move,
+            "pointerup" => PointerPhase::Up,
+            "pointerleave" => PointerPhase::Leave,
+            _ => PointerPhase::Cancel,
+        };
+        if phase == PointerPhase::Down {
+            // keep getting this pointer's events after it slides off the canvas
+            let _ = target.set_pointer_capture(event.pointer_id());
+        }
+        let rect = target.get_bounding_client_rect();
+        let position = pointer::to_canvas(
+            f64::from(event.client_x()),
+            f64::from(event.client_y()),
+            ClientRect {
+                left: rect.left(),
+                top: rect.top(),
+                width: rect.width(),
+                height: rect.height(),
+            },
+            Point {
+                x: target.width() as i16,
+                y: target.height() as i16,
+            },
+        );
+        let _ = sender.unbounded_send(PointerSample {
+            id: event.pointer_id(),
+            phase,
+            position,
+            buttons: event.buttons() as u8,
+            time_ms: event.time_stamp(),
+        });
+    }) as Box<dyn FnMut(PointerEvent)>);
+
+    for kind in [
+        "pointerdown",
+        "pointermove",
+        "pointerup",
+        "pointercancel",
+        "pointerleave",
+    ] {
+        canvas
+            .add_event_listener_with_callback(kind, onpointer.as_ref().unchecked_ref())
+            .map_err(|err| anyhow::anyhow!("Could not listen for {} : {:#?}", kind, err))?;
+    }
+    onpointer.forget();
+
+    Ok(receiver)
+}
+
+/// Feed every queued pointer sample to the recognizer and the pointer
+/// state, as one tick
+fn process_pointer_input(
+    gestures: &mut GestureRecognizer,
+    pointer: &mut PointerState,
+    receiver: &mut UnboundedReceiver<PointerSample>,
+) {
+    gestures.begin_tick();
+    pointer.begin_tick();
+    while let Ok(Some(sample)) = receiver.try_next() {
+        gestures.feed(sample);
+        pointer.feed(sample);
+    }
+    // a finger held still sends nothing, long press needs the clock
+    if let Ok(now) = browser::now() {
+        gestures.update(now);
+    }
+}
+
+/// Process Input :
+/// - Grab all events from key press channel
+/// - Reduce them to KeyState, as one tick
+fn process_input(state: &mut KeyState, keyevent_receiver: &mut UnboundedReceiver<KeyPress>) {
+    state.begin_tick();
+    loop {
+        match keyevent_receiver.try_next() {
+            Ok(None) => break,
+            Err(_err) => break,
+            Ok(Some(e)) => match e {
+                KeyPress::KeyUp(code) => state.release(code),
+                KeyPress::KeyDown(code) => state.press(code),
+            },
+        };
+    }
+    state.end_tick();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edges_only_last_one_tick() {
+        let mut keys = KeyState::new();
+        keys.begin_tick();
+        keys.press(KeyCode::Space);
+        keys.end_tick();
+        assert!(keys.just_pressed(KeyCode::Space) && keys.is_pressed(KeyCode::Space));
+        assert_eq!(keys.held_ticks(KeyCode::Space), 1);
+
+        // a second fixed update in the same frame : no new events
+        keys.begin_tick();
+        keys.press(KeyCode::Space); // key repeat
+        keys.end_tick();
+        assert!(!keys.just_pressed(KeyCode::Space) && keys.is_pressed(KeyCode::Space));
+        assert_eq!(keys.held_ticks(KeyCode::Space), 2);
+
+        keys.begin_tick();
+        keys.release(KeyCode::Space);
+        keys.end_tick();
+        assert!(keys.just_released(KeyCode::Space) && !keys.is_pressed(KeyCode::Space));
+        assert_eq!(keys.held_ticks(KeyCode::Space), 0);
+
+        keys.begin_tick();
+        keys.end_tick();
+        assert!(!keys.just_released(KeyCode::Space));
+    }
+
+    #[test]
+    fn test_tap_within_one_tick_is_pressed_and_released() {
+        let mut keys = KeyState::new();
+        keys.begin_tick();
+        keys.press(KeyCode::Space);
+        keys.release(KeyCode::Space);
+        keys.end_tick();
+        assert!(keys.just_pressed(KeyCode::Space));
+        assert!(keys.just_released(KeyCode::Space));
+        assert!(!keys.is_pressed(KeyCode::Space));
+    }
+}
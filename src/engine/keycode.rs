@@ -0,0 +1,115 @@
+/// Physical keys, as named by KeyboardEvent.code
+/// - https://developer.mozilla.org/en-US/docs/Web/API/UI_Events/Keyboard_event_code_values
+/// - the variant name IS the browser string, "ArrowRight" <-> KeyCode::ArrowRight,
+///   so bindings JSON keeps the exact same { "key": "ArrowRight" } shape
+/// - a typo is now a compile error, or a parse error for strings from JS
+///
+/// Codes a browser sends that aren't listed here (media keys, vendor
+/// specific ones) don't parse, and are dropped before they reach KeyState
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Declares KeyCode along with its ALL table, so the two can't drift
+macro_rules! key_codes {
+    ($($code:ident),* $(,)?) => {
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+        pub enum KeyCode {
+            $($code,)*
+        }
+
+        impl KeyCode {
+            pub const ALL: &'static [KeyCode] = &[$(KeyCode::$code,)*];
+
+            /// The KeyboardEvent.code string
+            pub fn as_str(self) -> &'static str {
+                match self {
+                    $(KeyCode::$code => stringify!($code),)*
+                }
+            }
+        }
+    };
+}
+
+key_codes! {
+    // writing system keys
+    Backquote, Backslash, BracketLeft, BracketRight, Comma, Equal, Minus,
+    Period, Quote, Semicolon, Slash, IntlBackslash, IntlRo, IntlYen,
+    Digit0, Digit1, Digit2, Digit3, Digit4, Digit5, Digit6, Digit7, Digit8, Digit9,
+    KeyA, KeyB, KeyC, KeyD, KeyE, KeyF, KeyG, KeyH, KeyI, KeyJ, KeyK, KeyL, KeyM,
+    KeyN, KeyO, KeyP, KeyQ, KeyR, KeyS, KeyT, KeyU, KeyV, KeyW, KeyX, KeyY, KeyZ,
+    // functional keys
+    AltLeft, AltRight, Backspace, CapsLock, ContextMenu, ControlLeft, ControlRight,
+    Enter, MetaLeft, MetaRight, ShiftLeft, ShiftRight, Space, Tab,
+    // control pad and arrows
+    Delete, End, Help, Home, Insert, PageDown, PageUp,
+    ArrowDown, ArrowLeft, ArrowRight, ArrowUp,
+    // numpad
+    NumLock, Numpad0, Numpad1, Numpad2, Numpad3, Numpad4, Numpad5, Numpad6,
+    Numpad7, Numpad8, Numpad9, NumpadAdd, NumpadComma, NumpadDecimal, NumpadDivide,
+    NumpadEnter, NumpadEqual, NumpadMultiply, NumpadSubtract,
+    // function section
+    Escape, F1, F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+    Fn, PrintScreen, ScrollLock, Pause,
+}
+
+impl fmt::Display for KeyCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A KeyboardEvent.code KeyCode doesn't know
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownKeyCode(pub String);
+
+impl fmt::Display for UnknownKeyCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unknown key code {:?}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownKeyCode {}
+
+impl FromStr for KeyCode {
+    type Err = UnknownKeyCode;
+
+    /// Exact match only : "Arrowright" is an error, not ArrowRight
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        KeyCode::ALL
+            .iter()
+            .copied()
+            .find(|key| key.as_str() == code)
+            .ok_or_else(|| UnknownKeyCode(code.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_roundtrips_every_code() {
+        for key in KeyCode::ALL {
+            assert_eq!(key.as_str().parse::<KeyCode>(), Ok(*key));
+        }
+        assert_eq!("ArrowRight".parse(), Ok(KeyCode::ArrowRight));
+        assert_eq!(
+            "Arrowright".parse::<KeyCode>(),
+            Err(UnknownKeyCode("Arrowright".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_serde_uses_the_browser_string() {
+        assert_eq!(
+            serde_json::to_string(&KeyCode::Space).unwrap(),
+            r#""Space""#
+        );
+        assert_eq!(
+            serde_json::from_str::<KeyCode>(r#""KeyW""#).unwrap(),
+            KeyCode::KeyW
+        );
+        assert!(serde_json::from_str::<KeyCode>(r#""Arrowright""#).is_err());
+    }
+}
@@ -0,0 +1,429 @@
+/// Pointer (mouse / touch / pen) input on the canvas, and the gestures that
+/// make the game playable on a phone
+/// - browser pointer events become timestamped PointerSamples in canvas
+///   coordinates, everything after that is plain Rust
+/// - GestureRecognizer turns samples into Gestures, which Bindings map to
+///   actions like any key : { "gesture": "tap" }
+/// - PointerState keeps where the pointer is and which buttons are down,
+///   for hit testing what's under it (see engine::hit)
+///
+/// TABLE: one pointer, from down to up
+/// ┌─ Gesture ──┬─ duration ─────────┬─ movement ─────────────────────────┐
+/// │ Tap        │ <= tap_max_ms      │ <= tap_max_distance                │
+/// │ SwipeUp    │ <= swipe_max_ms    │ >= swipe_min_distance, mostly up   │
+/// │ SwipeDown  │ <= swipe_max_ms    │ >= swipe_min_distance, mostly down │
+/// │ LongPress  │ >= long_press_ms   │ <= tap_max_distance, held until up │
+/// └────────────┴────────────────────┴────────────────────────────────────┘
+use crate::engine::Point;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PointerPhase {
+    Down,
+    Move,
+    Up,
+    Cancel,
+    /// left the canvas, a mouse stops hovering and a lifted finger is gone
+    Leave,
+}
+
+/// One pointer event, already in canvas coordinates
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PointerSample {
+    /// PointerEvent.pointerId, tells fingers apart
+    pub id: i32,
+    pub phase: PointerPhase,
+    pub position: Point,
+    /// PointerEvent.buttons, a bit per PointerButton held after this event
+    pub buttons: u8,
+    /// ms, same clock as performance.now()
+    pub time_ms: f64,
+}
+
+/// Where the canvas sits on the page, from getBoundingClientRect()
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClientRect {
+    pub left: f64,
+    pub top: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Map a page (client) position onto the canvas' own pixel grid
+/// - CSS can scale the canvas, so one CSS pixel isn't one canvas pixel
+pub fn to_canvas(client_x: f64, client_y: f64, rect: ClientRect, canvas_size: Point) -> Point {
+    let scale = |offset: f64, css_size: f64, size: i16| {
+        if css_size <= 0.0 {
+            return 0;
+        }
+        (offset / css_size * f64::from(size)).round() as i16
+    };
+    Point {
+        x: scale(client_x - rect.left, rect.width, canvas_size.x),
+        y: scale(client_y - rect.top, rect.height, canvas_size.y),
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PointerButton {
+    /// left mouse button, a finger, a pen touching
+    Primary,
+    /// right mouse button, pen barrel button
+    Secondary,
+    Middle,
+}
+
+impl PointerButton {
+    /// Bit in PointerEvent.buttons
+    fn bit(self) -> u8 {
+        match self {
+            PointerButton::Primary => 1,
+            PointerButton::Secondary => 2,
+            PointerButton::Middle => 4,
+        }
+    }
+}
+
+/// Pointer position and buttons, with per tick edges like KeyState
+/// - position is the last known one, it stays put after a release so a
+///   click can still be placed, `is_over()` says whether it left
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct PointerState {
+    position: Option<Point>,
+    over: bool,
+    buttons: u8,
+    pressed: u8,
+    released: u8,
+}
+
+impl PointerState {
+    /// Start a new tick : the previous tick's edges no longer apply
+    pub fn begin_tick(&mut self) {
+        self.pressed = 0;
+        self.released = 0;
+    }
+
+    pub fn feed(&mut self, sample: PointerSample) {
+        self.position = Some(sample.position);
+        match sample.phase {
+            PointerPhase::Down | PointerPhase::Move | PointerPhase::Up => {
+                self.over = true;
+                self.pressed |= sample.buttons & !self.buttons;
+                self.released |= self.buttons & !sample.buttons;
+                self.buttons = sample.buttons;
+            }
+            // the browser took the pointer back, that's no click
+            PointerPhase::Cancel => {
+                self.over = false;
+                self.buttons = 0;
+            }
+            PointerPhase::Leave => self.over = false,
+        }
+    }
+
+    /// Last known position, in canvas coordinates
+    pub fn position(&self) -> Option<Point> {
+        self.position
+    }
+
+    /// Still on the canvas, hovering whatever is under `position()`
+    pub fn is_over(&self) -> bool {
+        self.over
+    }
+
+    pub fn is_down(&self, button: PointerButton) -> bool {
+        self.buttons & button.bit() != 0
+    }
+
+    pub fn just_pressed(&self, button: PointerButton) -> bool {
+        self.pressed & button.bit() != 0
+    }
+
+    pub fn just_released(&self, button: PointerButton) -> bool {
+        self.released & button.bit() != 0
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Gesture {
+    Tap,
+    SwipeUp,
+    SwipeDown,
+    LongPress,
+}
+
+impl Gesture {
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// Thresholds, distances are in canvas pixels
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct GestureConfig {
+    pub tap_max_ms: f64,
+    pub tap_max_distance: f64,
+    pub swipe_min_distance: f64,
+    pub swipe_max_ms: f64,
+    pub long_press_ms: f64,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            tap_max_ms: 250.0,
+            tap_max_distance: 12.0,
+            swipe_min_distance: 48.0,
+            swipe_max_ms: 500.0,
+            long_press_ms: 400.0,
+        }
+    }
+}
+
+/// The pointer being tracked, from its down sample
+#[derive(Debug, Copy, Clone)]
+struct Press {
+    id: i32,
+    start: Point,
+    start_ms: f64,
+    last: Point,
+    long: bool,
+}
+
+impl Press {
+    fn offset(&self) -> (f64, f64) {
+        (
+            f64::from(self.last.x) - f64::from(self.start.x),
+            f64::from(self.last.y) - f64::from(self.start.y),
+        )
+    }
+
+    fn distance(&self) -> f64 {
+        let (dx, dy) = self.offset();
+        dx.hypot(dy)
+    }
+}
+
+/// Recognizes gestures from the first pointer down, extra fingers are ignored
+/// - gestures fire for one tick, like a key's just pressed
+/// - LongPress also stays held until the pointer lifts
+#[derive(Debug, Default)]
+pub struct GestureRecognizer {
+    config: GestureConfig,
+    press: Option<Press>,
+    fired: u8,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// Start a new tick : the previous tick's gestures no longer apply
+    pub fn begin_tick(&mut self) {
+        self.fired = 0;
+    }
+
+    pub fn feed(&mut self, sample: PointerSample) {
+        match (sample.phase, self.press) {
+            (PointerPhase::Down, None) => {
+                self.press = Some(Press {
+                    id: sample.id,
+                    start: sample.position,
+                    start_ms: sample.time_ms,
+                    last: sample.position,
+                    long: false,
+                });
+            }
+            (PointerPhase::Move, Some(mut press)) if press.id == sample.id => {
+                press.last = sample.position;
+                self.press = Some(press);
+            }
+            (PointerPhase::Up, Some(mut press)) if press.id == sample.id => {
+                press.last = sample.position;
+                self.press = None;
+                if !press.long {
+                    if let Some(gesture) = self.classify(&press, sample.time_ms) {
+                        self.fired |= gesture.bit();
+                    }
+                }
+            }
+            (PointerPhase::Cancel, Some(press)) if press.id == sample.id => {
+                self.press = None;
+            }
+            _ => {}
+        }
+    }
+
+    /// Fire LongPress once the pointer has stayed down long enough
+    /// - called every tick, a still finger sends no events to react to
+    pub fn update(&mut self, now_ms: f64) {
+        if let Some(press) = &mut self.press {
+            let still = press.distance() <= self.config.tap_max_distance;
+            if !press.long && still && now_ms - press.start_ms >= self.config.long_press_ms {
+                press.long = true;
+                self.fired |= Gesture::LongPress.bit();
+            }
+        }
+    }
+
+    fn classify(&self, press: &Press, end_ms: f64) -> Option<Gesture> {
+        let config = &self.config;
+        let elapsed = end_ms - press.start_ms;
+        let (dx, dy) = press.offset();
+        if elapsed <= config.swipe_max_ms
+            && dy.abs() >= config.swipe_min_distance
+            && dy.abs() > dx.abs()
+        {
+            // canvas y grows downward
+            return Some(if dy < 0.0 {
+                Gesture::SwipeUp
+            } else {
+                Gesture::SwipeDown
+            });
+        }
+        if elapsed <= config.tap_max_ms && press.distance() <= config.tap_max_distance {
+            return Some(Gesture::Tap);
+        }
+        None
+    }
+
+    /// Recognized this tick
+    pub fn fired(&self, gesture: Gesture) -> bool {
+        self.fired & gesture.bit() != 0
+    }
+
+    /// Still going on, only a LongPress lasts past its tick
+    pub fn is_holding(&self, gesture: Gesture) -> bool {
+        gesture == Gesture::LongPress && self.press.is_some_and(|press| press.long)
+    }
+
+    /// Where the tracked pointer is, in canvas coordinates
+    pub fn position(&self) -> Option<Point> {
+        self.press.map(|press| press.last)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(id: i32, phase: PointerPhase, x: i16, y: i16, time_ms: f64) -> PointerSample {
+        PointerSample {
+            id,
+            phase,
+            position: Point { x, y },
+            buttons: u8::from(matches!(phase, PointerPhase::Down | PointerPhase::Move)),
+            time_ms,
+        }
+    }
+
+    /// Feed a down at t=0, then an up at `(x, y)` after `ms`
+    fn stroke(x: i16, y: i16, ms: f64) -> GestureRecognizer {
+        let mut gestures = GestureRecognizer::default();
+        gestures.begin_tick();
+        gestures.feed(sample(1, PointerPhase::Down, 100, 300, 0.0));
+        gestures.feed(sample(1, PointerPhase::Up, x, y, ms));
+        gestures
+    }
+
+    #[test]
+    fn test_to_canvas_undoes_css_scaling() {
+        let rect = ClientRect {
+            left: 10.0,
+            top: 20.0,
+            width: 300.0,
+            height: 300.0,
+        };
+        let canvas = Point { x: 600, y: 600 };
+        assert_eq!(to_canvas(160.0, 20.0, rect, canvas), Point { x: 300, y: 0 });
+        assert_eq!(
+            to_canvas(310.0, 320.0, rect, canvas),
+            Point { x: 600, y: 600 }
+        );
+    }
+
+    #[test]
+    fn test_tap_and_swipes() {
+        assert!(stroke(103, 302, 120.0).fired(Gesture::Tap));
+        assert!(stroke(110, 200, 200.0).fired(Gesture::SwipeUp));
+        assert!(stroke(90, 400, 200.0).fired(Gesture::SwipeDown));
+
+        // too slow for either, and mostly sideways is not a swipe
+        let slow = stroke(100, 200, 900.0);
+        let sideways = stroke(250, 250, 200.0);
+        for gesture in [Gesture::Tap, Gesture::SwipeUp, Gesture::SwipeDown] {
+            assert!(!slow.fired(gesture));
+            assert!(!sideways.fired(gesture));
+        }
+    }
+
+    #[test]
+    fn test_long_press_holds_until_up() {
+        let mut gestures = GestureRecognizer::default();
+        gestures.begin_tick();
+        gestures.feed(sample(1, PointerPhase::Down, 100, 300, 0.0));
+        gestures.update(200.0);
+        assert!(!gestures.fired(Gesture::LongPress));
+
+        gestures.update(450.0);
+        assert!(gestures.fired(Gesture::LongPress));
+        assert!(gestures.is_holding(Gesture::LongPress));
+
+        // fires once, then it's only held
+        gestures.begin_tick();
+        gestures.update(600.0);
+        assert!(!gestures.fired(Gesture::LongPress));
+        assert!(gestures.is_holding(Gesture::LongPress));
+
+        // lifting ends it without a Tap
+        gestures.begin_tick();
+        gestures.feed(sample(1, PointerPhase::Up, 100, 300, 700.0));
+        assert!(!gestures.is_holding(Gesture::LongPress));
+        assert!(!gestures.fired(Gesture::Tap));
+    }
+
+    #[test]
+    fn test_second_finger_is_ignored() {
+        let mut gestures = GestureRecognizer::default();
+        gestures.begin_tick();
+        gestures.feed(sample(1, PointerPhase::Down, 100, 300, 0.0));
+        gestures.feed(sample(2, PointerPhase::Down, 400, 300, 10.0));
+        gestures.feed(sample(2, PointerPhase::Up, 400, 100, 50.0));
+        assert!(!gestures.fired(Gesture::SwipeUp));
+        assert_eq!(gestures.position(), Some(Point { x: 100, y: 300 }));
+
+        gestures.feed(sample(1, PointerPhase::Cancel, 100, 300, 60.0));
+        assert_eq!(gestures.position(), None);
+    }
+
+    #[test]
+    fn test_pointer_state_edges_and_leave() {
+        let mut pointer = PointerState::default();
+        pointer.begin_tick();
+        pointer.feed(sample(1, PointerPhase::Down, 10, 20, 0.0));
+        assert!(pointer.just_pressed(PointerButton::Primary));
+        assert!(pointer.is_down(PointerButton::Primary));
+        assert!(!pointer.is_down(PointerButton::Secondary));
+
+        pointer.begin_tick();
+        pointer.feed(sample(1, PointerPhase::Up, 12, 20, 50.0));
+        pointer.feed(sample(1, PointerPhase::Leave, 12, 20, 51.0));
+        assert!(pointer.just_released(PointerButton::Primary));
+        assert!(!pointer.is_over());
+        // the release still has a place
+        assert_eq!(pointer.position(), Some(Point { x: 12, y: 20 }));
+
+        // cancel drops the button without a release
+        pointer.begin_tick();
+        pointer.feed(sample(1, PointerPhase::Down, 10, 20, 100.0));
+        pointer.begin_tick();
+        pointer.feed(sample(1, PointerPhase::Cancel, 10, 20, 150.0));
+        assert!(!pointer.is_down(PointerButton::Primary));
+        assert!(!pointer.just_released(PointerButton::Primary));
+    }
+}
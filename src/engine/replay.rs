@@ -0,0 +1,234 @@
+/// Input recording and deterministic replay
+/// - live play records the ActionState every fixed update receives
+/// - replay mode feeds those ticks back instead of InputHandler, so the
+///   game sees the exact same input on the exact same update
+/// - play back with ?replay=<file> in the page URL, export the current
+///   recording from JS with export_replay() (see lib.rs)
+///
+/// Deterministic because Game::update only ever gets a tick's ActionState :
+/// no delta time, no clock, no randomness (see the Game trait)
+///
+/// TABLE: (all integers little endian)
+/// ┌──────────────── replay layout ──────────────────────────────┐
+/// │ magic      4 bytes   b"WTDR"                                │
+/// │ version    u16       REPLAY_VERSION                         │
+/// │ ticks      u32       number of fixed updates recorded       │
+/// │ runs       u32       number of runs that follow             │
+/// ├─────────────────────────────────────────────────────────────┤
+/// │ run        runs x :                                         │
+/// │   len      u16       identical ticks in a row               │
+/// │   held     u8        ActionState bits, one per Action       │
+/// │   pressed  u8                                               │
+/// │   released u8                                               │
+/// └─────────────────────────────────────────────────────────────┘
+/// Idle stretches collapse into one run, a minute of play is a few hundred bytes
+use crate::engine::action::ActionState;
+use crate::engine::asset::AssetError;
+use crate::engine::bundle::Reader;
+use std::cell::RefCell;
+
+pub const REPLAY_MAGIC: &[u8; 4] = b"WTDR";
+pub const REPLAY_VERSION: u16 = 1;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Run {
+    len: u16,
+    bits: [u8; 3],
+}
+
+/// Ticks recorded so far, run length encoded
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    ticks: u32,
+    runs: Vec<Run>,
+}
+
+impl Recording {
+    pub fn record(&mut self, actions: &ActionState) {
+        let bits = actions.to_bits();
+        match self.runs.last_mut() {
+            Some(run) if run.bits == bits && run.len < u16::MAX => run.len += 1,
+            _ => self.runs.push(Run { len: 1, bits }),
+        }
+        self.ticks = self.ticks.saturating_add(1);
+    }
+
+    pub fn ticks(&self) -> u32 {
+        self.ticks
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(14 + self.runs.len() * 5);
+        bytes.extend_from_slice(REPLAY_MAGIC);
+        bytes.extend_from_slice(&REPLAY_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&self.ticks.to_le_bytes());
+        bytes.extend_from_slice(&(self.runs.len() as u32).to_le_bytes());
+        for run in &self.runs {
+            bytes.extend_from_slice(&run.len.to_le_bytes());
+            bytes.extend_from_slice(&run.bits);
+        }
+        bytes
+    }
+
+    /// Parse a replay file, `path` is only used to label errors
+    pub fn parse(path: &str, bytes: &[u8]) -> Result<Self, AssetError> {
+        let malformed = |reason: &str| AssetError::Decode {
+            path: path.to_string(),
+            reason: format!("malformed replay, {}", reason),
+        };
+        let mut reader = Reader::new(bytes);
+
+        if reader.take(4) != Some(REPLAY_MAGIC.as_slice()) {
+            return Err(malformed("bad magic"));
+        }
+        let version = reader.u16().ok_or_else(|| malformed("missing version"))?;
+        if version != REPLAY_VERSION {
+            return Err(malformed(&format!("unsupported version {}", version)));
+        }
+        let ticks = reader
+            .u32()
+            .ok_or_else(|| malformed("missing tick count"))?;
+        let count = reader.u32().ok_or_else(|| malformed("missing run count"))?;
+
+        let mut runs = Vec::new();
+        let mut total: u64 = 0;
+        for _ in 0..count {
+            let len = reader.u16().ok_or_else(|| malformed("truncated run"))?;
+            let bits = reader
+                .take(3)
+                .and_then(|bits| bits.try_into().ok())
+                .ok_or_else(|| malformed("truncated run"))?;
+            if len == 0 {
+                return Err(malformed("empty run"));
+            }
+            total += u64::from(len);
+            runs.push(Run { len, bits });
+        }
+        if total != u64::from(ticks) {
+            return Err(malformed(&format!(
+                "runs add up to {} ticks, header says {}",
+                total, ticks
+            )));
+        }
+        if !reader.is_empty() {
+            return Err(malformed("trailing bytes"));
+        }
+        Ok(Self { ticks, runs })
+    }
+
+    pub fn into_playback(self) -> Playback {
+        Playback {
+            runs: self.runs,
+            run: 0,
+            used: 0,
+            previous: ActionState::default(),
+        }
+    }
+}
+
+/// Recorded ticks, in order, one per fixed update
+#[derive(Debug)]
+pub struct Playback {
+    runs: Vec<Run>,
+    run: usize,
+    used: u16,
+    previous: ActionState,
+}
+
+impl Iterator for Playback {
+    type Item = ActionState;
+
+    fn next(&mut self) -> Option<ActionState> {
+        let run = self.runs.get(self.run)?;
+        let state = ActionState::from_bits(run.bits, &self.previous);
+        self.used += 1;
+        if self.used == run.len {
+            self.run += 1;
+            self.used = 0;
+        }
+        self.previous = state;
+        Some(state)
+    }
+}
+
+thread_local! {
+    // the live session's recording, reachable from JS through export_replay()
+    static RECORDING: RefCell<Recording> = RefCell::new(Recording::default());
+}
+
+/// Append one live tick to the session recording
+pub fn record_tick(actions: &ActionState) {
+    RECORDING.with(|recording| recording.borrow_mut().record(actions));
+}
+
+/// The session recording so far, in the replay file format
+pub fn recorded_bytes() -> Vec<u8> {
+    RECORDING.with(|recording| recording.borrow().to_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::action::Action;
+
+    /// Ticks of only `held` bits, edges left out to keep it short
+    fn ticks(held: &[u8]) -> Vec<ActionState> {
+        let mut previous = ActionState::default();
+        held.iter()
+            .map(|held| {
+                previous = ActionState::from_bits([*held, 0, 0], &previous);
+                previous
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_record_then_playback_roundtrip() {
+        let recorded = ticks(&[0, 0, 0, 0b100, 0b100, 0b101, 0, 0]);
+        let mut recording = Recording::default();
+        recorded.iter().for_each(|tick| recording.record(tick));
+
+        let bytes = recording.to_bytes();
+        // 14 byte header + 4 runs of 5 bytes
+        assert_eq!(bytes.len(), 14 + 4 * 5);
+
+        let played: Vec<ActionState> = Recording::parse("bug.wtdr", &bytes)
+            .unwrap()
+            .into_playback()
+            .collect();
+        assert_eq!(played, recorded);
+        assert_eq!(played[5].held_ticks(Action::Jump), 3);
+        assert_eq!(played[5].held_ticks(Action::Run), 1);
+    }
+
+    #[test]
+    fn test_long_idle_collapses_into_runs() {
+        let mut recording = Recording::default();
+        for _ in 0..70_000 {
+            recording.record(&ActionState::default());
+        }
+        assert_eq!(recording.ticks(), 70_000);
+        assert_eq!(recording.to_bytes().len(), 14 + 2 * 5);
+    }
+
+    #[test]
+    fn test_malformed_replays_are_decode_errors() {
+        let mut recording = Recording::default();
+        recording.record(&ActionState::default());
+        let bytes = recording.to_bytes();
+
+        let mut wrong_count = bytes.clone();
+        wrong_count[6] = 2;
+        for bad in [
+            &b"WTDB"[..],
+            &bytes[..bytes.len() - 1],
+            &wrong_count,
+            &[bytes.as_slice(), &[0]].concat(),
+        ] {
+            assert!(matches!(
+                Recording::parse("bug.wtdr", bad),
+                Err(AssetError::Decode { .. })
+            ));
+        }
+    }
+}
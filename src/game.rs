@@ -1,13 +1,23 @@
+#[cfg(debug_assertions)]
 use crate::browser;
-use crate::engine;
-use crate::engine::input::*;
-use crate::engine::Sheet;
+use crate::engine::action::{Action, ActionState};
+use crate::engine::asset::{self, AssetError, BrowserFetcher, LoadOptions};
+use crate::engine::buffer::{Fire, InputBuffer, IntentWindows};
+use crate::engine::bundle::{self, Bundle};
+use crate::engine::hit::{HitTester, Hitbox, Space};
 #[cfg(debug_assertions)]
-use crate::engine::{Game, Image, Point, Rect, Renderer, Size};
-use crate::sprite::RedHatBoy;
+use crate::engine::hot_reload;
+use crate::engine::pointer::PointerState;
+use crate::engine::Sheet;
+use crate::engine::{Camera, Game, Image, Point, Rect, Renderer, Size};
+use crate::sprite::{
+    self, AnimationEvent, AnimationOverrides, AnimationSet, Facing, RedHatBoy, RedHatBoySimulation,
+};
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
 use futures::join;
+#[cfg(debug_assertions)]
+use std::{cell::RefCell, rc::Rc};
 use web_sys::HtmlImageElement;
 
 /// TABLE
@@ -22,9 +32,9 @@ use web_sys::HtmlImageElement;
 /// │    └─────────────┘          └──────┬──────┘          └──────┬──────┘    │
 /// │                                    │                        │           │
 /// │                              ┌─────┴──────┐            ┌────┴─────┐     │
-/// │                              │  KeyState  │            │  Update  │     │
+/// │                              │ActionState │            │  Update  │     │
 /// │                              │  Keyboard  ├────────────► Game     │     │
-/// │                              │  Input     │            │ State    │     │
+/// │                              │  Bindings  │            │ State    │     │
 /// │                              └────────────┘            └──────────┘     │
 /// │                                                                         │
 /// ├──────────────────────── Call Sequence ──────────────────────────────────┤
@@ -33,11 +43,11 @@ use web_sys::HtmlImageElement;
 /// │     └─► GameLoop.update() initiates frame processing                    │
 /// │                                                                         │
 /// │  2. Input Processing                                                    │
-/// │     └─► engine.update() captures and processes KeyState                 │
+/// │     └─► engine.update() maps KeyState to actions via Bindings           │
 /// │                                                                         │
 /// │  3. Game State Update                                                   │
 /// │     └─► WalkTheDog.update() manages:                                    │
-/// │         ├─► Input Processing: Handle keyboard events                    │
+/// │         ├─► Input Processing: Handle Run / Slide / Jump / Pause         │
 /// │         ├─► Character States: Update animations and positions           │
 /// │         ├─► World Updates: Modify game environment                      │
 /// │         └─► Collision Detection: Check for object interactions          │
@@ -46,10 +56,12 @@ use web_sys::HtmlImageElement;
 pub enum WalkTheDog {
     /// Initialize state while resources are being loaded
     /// Transition to `Loaded` once initialization is complete
-    Loading,
+    /// - replay: playing back a recording, see WalkTheDog::replaying()
+    Loading { replay: bool },
 
     /// Active game state with initialized RedHatBoy assets
-    Loaded(Walk),
+    /// - boxed, Walk is far bigger than Loading
+    Loaded(Box<Walk>),
 }
 
 impl WalkTheDog {
@@ -59,24 +71,168 @@ impl WalkTheDog {
     // read-only memory
     const SHEET_PATH: &'static str = "rhb.json";
     const IMAGE_PATH: &'static str = "rhb.png";
+    const ANIMATIONS_PATH: &'static str = "rhb_animations.json";
+    const BACKGROUND_PATH: &'static str = "BG.png";
+    const STONE_PATH: &'static str = "Stone.png";
+    // built by `cargo run --bin pack_assets`, optional : without it every
+    // asset above is fetched on its own
+    const BUNDLE_PATH: &'static str = "assets.pack";
+    // served by the webpack dev server, see www/webpack.config.js
+    #[cfg(debug_assertions)]
+    const VERSION_PATH: &'static str = "asset-version.json";
+    #[cfg(debug_assertions)]
+    const HOT_RELOAD_POLL_MS: u32 = 1_000;
+    // jumps in the air before landing, 1 = double jump
+    const AIR_JUMPS: u8 = 1;
+    // animation events the game reacts to, placed on frames in
+    // rhb_animations.json
+    const ANIMATION_EVENTS: [&'static str; 2] = ["footstep", "dust"];
 
     pub fn new() -> Self {
-        WalkTheDog::Loading
+        WalkTheDog::Loading { replay: false }
+    }
+
+    /// A game for playing back a recording
+    /// - asset hot reload stays off, every update has to run on the assets
+    ///   the recording was made with or the replay drifts
+    pub fn replaying() -> Self {
+        WalkTheDog::Loading { replay: true }
     }
     async fn load_sprite_sheet() -> Result<Sheet> {
-        browser::fetch_json::<Sheet>(Self::SHEET_PATH)
+        asset::fetch_json::<Sheet>(&BrowserFetcher, Self::SHEET_PATH, LoadOptions::default())
             .await
             .with_context(|| format!("Failed to load sprite sheet from : {}", Self::SHEET_PATH))
     }
 
-    async fn load_sprite_image() -> Result<HtmlImageElement> {
-        engine::load_image(Self::IMAGE_PATH).await.with_context(|| {
-            format!(
-                "Failed to load sprite image resource from : {}",
-                Self::IMAGE_PATH
-            )
+    /// Override file is optional, animations still load from the sheet alone
+    async fn load_animation_overrides() -> AnimationOverrides {
+        let overrides = asset::fetch_json::<AnimationOverrides>(
+            &BrowserFetcher,
+            Self::ANIMATIONS_PATH,
+            LoadOptions::default(),
+        )
+        .await;
+        Self::overrides_or_default(overrides)
+    }
+
+    fn overrides_or_default(
+        overrides: Result<AnimationOverrides, AssetError>,
+    ) -> AnimationOverrides {
+        overrides.unwrap_or_else(|err| {
+            log!("No animation overrides loaded : {}", err);
+            AnimationOverrides::default()
         })
     }
+
+    /// Every asset in one request when a bundle was packed, else one request
+    /// per file
+    async fn load_assets() -> Result<Assets> {
+        match bundle::load_bundle(&BrowserFetcher, Self::BUNDLE_PATH, LoadOptions::default()).await
+        {
+            Ok(bundle) => Self::load_from_bundle(&bundle).await,
+            Err(AssetError::NotFound { .. }) => Self::load_from_files().await,
+            Err(err) => Err(err).context("Failed to load asset bundle"),
+        }
+    }
+
+    async fn load_from_bundle(bundle: &Bundle) -> Result<Assets> {
+        let sheet = bundle.json::<Sheet>(Self::SHEET_PATH)?;
+        let overrides = Self::overrides_or_default(bundle.json(Self::ANIMATIONS_PATH));
+        let (image, background, stone) = join!(
+            bundle.image(Self::IMAGE_PATH),
+            bundle.image(Self::BACKGROUND_PATH),
+            bundle.image(Self::STONE_PATH),
+        );
+        Ok(Assets {
+            sheet,
+            image: image?,
+            overrides,
+            background: background?,
+            stone: stone?,
+        })
+    }
+
+    /// Compile the sheet into animations, failing with every sheet problem
+    /// listed rather than panicking on the first missing frame mid-game
+    fn build_animations(
+        sheet: &Sheet,
+        overrides: &AnimationOverrides,
+        image: &HtmlImageElement,
+    ) -> Result<AnimationSet> {
+        let animations = AnimationSet::from_sheet(sheet, overrides);
+        let report = sprite::validate_sheet(
+            sheet,
+            &animations,
+            &RedHatBoySimulation::required_animations(),
+            Size {
+                width: image.width() as i16,
+                height: image.height() as i16,
+            },
+        );
+        if !report.is_ok() {
+            return Err(anyhow::Error::new(report))
+                .with_context(|| format!("Invalid sprite sheet : {}", Self::SHEET_PATH));
+        }
+        Ok(animations)
+    }
+
+    /// Re-fetch the RedHatBoy sheet, image and overrides, bypassing the cache
+    #[cfg(debug_assertions)]
+    async fn reload_sprite() -> Result<ReloadedSprite> {
+        let options = LoadOptions::default();
+        let sheet_path = hot_reload::cache_busted(Self::SHEET_PATH);
+        let image_path = hot_reload::cache_busted(Self::IMAGE_PATH);
+        let overrides_path = hot_reload::cache_busted(Self::ANIMATIONS_PATH);
+        let (sheet, image, overrides) = join!(
+            asset::fetch_json::<Sheet>(&BrowserFetcher, &sheet_path, options),
+            asset::load_image(&image_path, options),
+            asset::fetch_json::<AnimationOverrides>(&BrowserFetcher, &overrides_path, options),
+        );
+        let (sheet, image) = (sheet?, image?);
+        let overrides = Self::overrides_or_default(overrides);
+        let animations = Self::build_animations(&sheet, &overrides, &image)?;
+        Ok((animations, image))
+    }
+
+    async fn load_from_files() -> Result<Assets> {
+        // ELI5:
+        // +------------+----------------------------+----------------+
+        // |   Method   |       Resource Time        |   Total Time   |
+        // +------------+----------------------------+----------------+
+        // |            | Image: 300ms, JSON: 200ms  |                |
+        // +------------+----------------------------+----------------+
+        // |  Serial    | Image → JSON               | 500ms          |
+        // |  Loading   | (One after another)        | (300ms + 200ms)|
+        // +------------+----------------------------+----------------+
+        // |  Parallel  | Image || JSON              | 300ms          |
+        // |  Loading   | (Simultaneous loading)     | (max time wins)|
+        // +------------+----------------------------+----------------+
+        let (sheet_result, image_result, overrides) = join!(
+            Self::load_sprite_sheet(),
+            Self::load_sprite_image(),
+            Self::load_animation_overrides(),
+        );
+        let background = asset::load_image(Self::BACKGROUND_PATH, LoadOptions::default()).await?;
+        let stone = asset::load_image(Self::STONE_PATH, LoadOptions::default()).await?;
+        Ok(Assets {
+            sheet: sheet_result?,
+            image: image_result?,
+            overrides,
+            background,
+            stone,
+        })
+    }
+
+    async fn load_sprite_image() -> Result<HtmlImageElement> {
+        asset::load_image(Self::IMAGE_PATH, LoadOptions::default())
+            .await
+            .with_context(|| {
+                format!(
+                    "Failed to load sprite image resource from : {}",
+                    Self::IMAGE_PATH
+                )
+            })
+    }
 }
 
 #[async_trait(?Send)]
@@ -89,50 +245,122 @@ impl Game for WalkTheDog {
             // │ ✓ Independent resources load simultaneously    │
             // │ ✓ Total time determined by slowest resource    │
             // └────────────────────────────────────────────────┘
-            WalkTheDog::Loading => {
-                // ELI5:
-                // +------------+----------------------------+----------------+
-                // |   Method   |       Resource Time        |   Total Time   |
-                // +------------+----------------------------+----------------+
-                // |            | Image: 300ms, JSON: 200ms  |                |
-                // +------------+----------------------------+----------------+
-                // |  Serial    | Image → JSON               | 500ms          |
-                // |  Loading   | (One after another)        | (300ms + 200ms)|
-                // +------------+----------------------------+----------------+
-                // |  Parallel  | Image || JSON              | 300ms          |
-                // |  Loading   | (Simultaneous loading)     | (max time wins)|
-                // +------------+----------------------------+----------------+
-                let (sheet_result, image_result) =
-                    join!(Self::load_sprite_sheet(), Self::load_sprite_image(),);
-                let sheet = sheet_result?;
-                let image = image_result?;
-                let background = engine::load_image("BG.png").await?;
-                let stone = engine::load_image("Stone.png").await?;
-                let rhb = RedHatBoy::new(sheet, image);
-                let walk = Walk {
-                    boy: rhb,
-                    background: Image::new(background, Point { x: 0, y: 0 }),
-                    stone: Image::new(stone, Point { x: 150, y: 546 }),
-                };
-                Ok(Box::new(WalkTheDog::Loaded(walk)))
+            WalkTheDog::Loading { replay } => {
+                let Assets {
+                    sheet,
+                    image,
+                    overrides,
+                    background,
+                    stone,
+                } = Self::load_assets().await?;
+                let animations = Self::build_animations(&sheet, &overrides, &image)?;
+                let simulation = RedHatBoySimulation::new(animations)
+                    .with_air_jumps(Self::AIR_JUMPS)
+                    .subscribe(&Self::ANIMATION_EVENTS);
+                let rhb = RedHatBoy::new(simulation, image);
+                let walk = Walk::new(
+                    rhb,
+                    Image::new(background, Point { x: 0, y: 0 }),
+                    Image::new(stone, Point { x: 150, y: 546 }),
+                    *replay,
+                );
+                #[cfg(debug_assertions)]
+                if !replay {
+                    hot_reload::watch(Self::VERSION_PATH, Self::HOT_RELOAD_POLL_MS);
+                }
+                Ok(Box::new(WalkTheDog::Loaded(Box::new(walk))))
             }
             WalkTheDog::Loaded(_) => Err(anyhow!("Game is already initialized")),
         }
     }
 
-    fn update(&mut self, keystate: &KeyState) {
+    fn update(&mut self, actions: &ActionState) {
         if let WalkTheDog::Loaded(walk) = self {
+            #[cfg(debug_assertions)]
+            if walk.hot_reload {
+                walk.hot_reload();
+            }
+            // the boy plays out his fall, but takes no more input
+            if walk.game_over {
+                walk.boy.simulation_mut().update();
+                walk.puffs.retain_mut(Puff::fade);
+                return;
+            }
+            if actions.just_pressed(Action::Pause) {
+                walk.paused = !walk.paused;
+            }
+            if walk.paused {
+                return;
+            }
             // process input and trigger state changes
-            if keystate.is_pressed("ArrowRight") {
-                walk.boy.run_right();
+            // - jump / slide go through the buffer, so a press a few ticks
+            // early (mid-air, mid-slide) or late (coyote) isn't dropped
+            walk.buffer.update(actions);
+            // both run directions held cancel out, and brake like neither
+            match (
+                actions.is_active(Action::Run),
+                actions.is_active(Action::RunLeft),
+            ) {
+                (true, false) => walk.boy.simulation_mut().run(Facing::Right),
+                (false, true) => walk.boy.simulation_mut().run(Facing::Left),
+                _ => walk.boy.simulation_mut().brake(),
+            }
+            if walk
+                .buffer
+                .poll(Action::Slide, walk.boy.simulation().can_slide())
+                .is_some()
+            {
+                walk.boy.simulation_mut().slide();
+            }
+            // gestures (tap, swipe up) go down and up inside one tick, they're
+            // never held and have no release to time : those jump full height
+            if actions.just_pressed(Action::Jump) {
+                walk.jump_held = actions.is_active(Action::Jump);
             }
-            if keystate.is_pressed("ArrowDown") {
-                walk.boy.slide();
+            // a late press only spends the coyote window on a boy who can
+            // still take it, say not while he's hurt
+            let boy = walk.boy.simulation();
+            let (ready, grace) = (boy.can_jump(), boy.can_coyote_jump());
+            match walk.buffer.poll_with_grace(Action::Jump, ready, grace) {
+                Some(Fire::Now) => walk.boy.simulation_mut().jump(),
+                Some(Fire::Grace) => walk.boy.simulation_mut().coyote_jump(),
+                None => {}
             }
-            if keystate.is_pressed("Space") {
-                walk.boy.jump();
+            // after the jump, so a buffered tap that fires once the button
+            // is already up is a short hop too
+            if walk.jump_held && !actions.is_active(Action::Jump) {
+                walk.boy.simulation_mut().release_jump();
+            }
+            walk.boy.simulation_mut().update();
+            walk.puffs.retain_mut(Puff::fade);
+            for event in walk.boy.simulation_mut().take_events() {
+                walk.on_animation_event(&event);
+            }
+            // the stone hurts, invulnerability frames keep one touch from
+            // counting every tick
+            if walk
+                .boy
+                .simulation()
+                .hitbox()
+                .intersects(&walk.stone.bounding_box())
+            {
+                walk.boy.simulation_mut().take_damage();
+            }
+            // the boy hit the stone one time too many
+            if walk.boy.simulation_mut().take_died() {
+                walk.game_over = true;
             }
-            walk.boy.update();
+        }
+    }
+
+    fn pointer(&mut self, pointer: &PointerState) {
+        if let WalkTheDog::Loaded(walk) = self {
+            let hitboxes = walk.hitboxes();
+            let hits = walk.hit_tester.update(pointer, &walk.camera, &hitboxes);
+            if let Some(target) = hits.clicked {
+                walk.select(target);
+            }
+            walk.hovered = hits.hovered;
         }
     }
 
@@ -148,13 +376,500 @@ impl Game for WalkTheDog {
             // Draw order matters : background -> foreground
             walk.background.draw(renderer);
             walk.boy.draw(renderer);
+            for puff in &walk.puffs {
+                puff.draw(renderer);
+            }
             walk.stone.draw(renderer);
+            if walk.game_over {
+                renderer.draw_text(
+                    "Game Over",
+                    "bold 48px sans-serif",
+                    "#000000",
+                    &Point { x: 180, y: 300 },
+                );
+            }
+            if let Some(selected) = walk.hitbox(walk.selected) {
+                renderer.draw_bounding_box(&selected.rect, "#00bfff");
+            }
+            #[cfg(debug_assertions)]
+            if let Some(hovered) = walk.hovered {
+                if let Some(hitbox) = walk.hitbox(Some(hovered)) {
+                    renderer.draw_bounding_box(&hitbox.rect, "#ffff00");
+                }
+            }
         }
     }
 }
 
+/// Everything initialize() needs, wherever it was loaded from
+struct Assets {
+    sheet: Sheet,
+    image: HtmlImageElement,
+    overrides: AnimationOverrides,
+    background: HtmlImageElement,
+    stone: HtmlImageElement,
+}
+
+/// Sprite data re-fetched by hot reload, waiting to be swapped in
+#[cfg(debug_assertions)]
+type ReloadedSprite = (AnimationSet, HtmlImageElement);
+
 pub struct Walk {
     boy: RedHatBoy,
     background: Image,
     stone: Image,
+    paused: bool,
+    // the boy died, input is ignored from then on
+    game_over: bool,
+    buffer: InputBuffer,
+    // the last jump press was held down, so letting go of it cuts the jump
+    jump_held: bool,
+    // kicked up by animation events, see Walk::on_animation_event
+    puffs: Vec<Puff>,
+    camera: Camera,
+    hit_tester: HitTester<Target>,
+    // under the pointer as of the last tick, outlined in debug builds
+    hovered: Option<Target>,
+    // picked by clicking it, outlined until it's clicked again
+    selected: Option<Target>,
+    // off while replaying, see WalkTheDog::replaying()
+    #[cfg(debug_assertions)]
+    hot_reload: bool,
+    // filled by an async reload task, drained on the next update()
+    #[cfg(debug_assertions)]
+    reloaded: Rc<RefCell<Option<ReloadedSprite>>>,
+}
+
+/// What can be pointed at on the canvas
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Target {
+    Boy,
+    Stone,
+}
+
+impl Walk {
+    fn new(boy: RedHatBoy, background: Image, stone: Image, replay: bool) -> Self {
+        // only hot reload cares about replays
+        #[cfg(not(debug_assertions))]
+        let _ = replay;
+        Walk {
+            boy,
+            background,
+            stone,
+            paused: false,
+            game_over: false,
+            buffer: InputBuffer::new(IntentWindows::default()),
+            jump_held: false,
+            puffs: Vec::new(),
+            camera: Camera::default(),
+            hit_tester: HitTester::new(),
+            hovered: None,
+            selected: None,
+            #[cfg(debug_assertions)]
+            hot_reload: !replay,
+            #[cfg(debug_assertions)]
+            reloaded: Rc::new(RefCell::new(None)),
+        }
+    }
+
+    /// Clickable things, z follows draw order : the stone is drawn over the boy
+    fn hitboxes(&self) -> [Hitbox<Target>; 2] {
+        [
+            Hitbox {
+                id: Target::Boy,
+                rect: self.boy.simulation().hitbox(),
+                z: 0,
+                space: Space::World,
+            },
+            Hitbox {
+                id: Target::Stone,
+                rect: self.stone.bounding_box(),
+                z: 1,
+                space: Space::World,
+            },
+        ]
+    }
+
+    fn hitbox(&self, target: Option<Target>) -> Option<Hitbox<Target>> {
+        let target = target?;
+        self.hitboxes().into_iter().find(|hit| hit.id == target)
+    }
+
+    /// A click selects its target, clicking the selection again drops it
+    fn select(&mut self, target: Target) {
+        self.selected = if self.selected == Some(target) {
+            None
+        } else {
+            Some(target)
+        };
+    }
+
+    /// Subscribed animation events, see WalkTheDog::ANIMATION_EVENTS
+    /// - no audio yet, a footstep kicks up a little dust at the boy's feet
+    fn on_animation_event(&mut self, event: &AnimationEvent) {
+        let radius = match event.name.as_str() {
+            "footstep" => 4,
+            "dust" => 10,
+            _ => return,
+        };
+        let feet = self.boy.simulation().bounding_box();
+        self.puffs.push(Puff {
+            center: Point {
+                x: feet.position.x + feet.size.width / 2,
+                y: feet.position.y + feet.size.height,
+            },
+            radius,
+            ticks_left: Puff::TICKS,
+        });
+    }
+}
+
+/// A puff of dust, fading out over Puff::TICKS updates
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+struct Puff {
+    center: Point,
+    radius: i16,
+    ticks_left: u8,
+}
+
+impl Puff {
+    const TICKS: u8 = 20;
+
+    /// One update older, false once it's gone
+    fn fade(&mut self) -> bool {
+        self.ticks_left = self.ticks_left.saturating_sub(1);
+        self.ticks_left > 0
+    }
+
+    fn draw(&self, renderer: &Renderer) {
+        let alpha = f32::from(self.ticks_left) / f32::from(Self::TICKS);
+        let color = format!("rgba(150, 130, 110, {:.2})", alpha);
+        renderer.fill_circle(&self.center, self.radius, &color);
+    }
+}
+
+#[cfg(debug_assertions)]
+impl Walk {
+    /// Kick off a reload when a RedHatBoy asset changed, and swap in any
+    /// reload that finished since the last update
+    fn hot_reload(&mut self) {
+        let sprite_paths = [
+            WalkTheDog::SHEET_PATH,
+            WalkTheDog::IMAGE_PATH,
+            WalkTheDog::ANIMATIONS_PATH,
+        ];
+        let changed = hot_reload::take_pending();
+        if changed
+            .iter()
+            .any(|path| sprite_paths.contains(&path.as_str()))
+        {
+            let slot = Rc::clone(&self.reloaded);
+            browser::spawn_local(async move {
+                match WalkTheDog::reload_sprite().await {
+                    Ok(sprite) => *slot.borrow_mut() = Some(sprite),
+                    Err(err) => {
+                        log!("[hot_reload] keeping the old sprite : {:#}", err);
+                    }
+                }
+            });
+        }
+
+        if let Some((animations, image)) = self.reloaded.borrow_mut().take() {
+            log!("[hot_reload] swapped in new RedHatBoy sprite");
+            self.boy.reload(animations, image);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::pointer::{PointerPhase, PointerSample};
+    use crate::engine::replay::Recording;
+    use crate::engine::TickInput;
+    use wasm_bindgen::{JsCast, JsValue};
+
+    const RUN: u8 = 0b001;
+    const SLIDE: u8 = 0b010;
+    const JUMP: u8 = 0b100;
+
+    /// Stand in for a loaded image, update() never touches the element
+    /// - null is one of wasm-bindgen's reserved values, holding and dropping
+    ///   it needs no JS, so this works natively
+    fn no_image() -> HtmlImageElement {
+        JsValue::NULL.unchecked_into()
+    }
+
+    /// A loaded game on fixed assets : 100x120 frames, 4 per animation, and
+    /// the stone out of the boy's way
+    fn game(replay: bool) -> WalkTheDog {
+        game_with(replay, &AnimationOverrides::default())
+    }
+
+    fn game_with(replay: bool, overrides: &AnimationOverrides) -> WalkTheDog {
+        let animations =
+            sprite::test_animations(&RedHatBoySimulation::required_animations(), overrides);
+        let simulation = RedHatBoySimulation::new(animations)
+            .with_air_jumps(WalkTheDog::AIR_JUMPS)
+            .subscribe(&WalkTheDog::ANIMATION_EVENTS);
+        let walk = Walk::new(
+            RedHatBoy::new(simulation, no_image()),
+            Image::with_size(
+                no_image(),
+                Point { x: 0, y: 0 },
+                Size {
+                    width: 600,
+                    height: 600,
+                },
+            ),
+            Image::with_size(
+                no_image(),
+                Point { x: 150, y: 0 },
+                Size {
+                    width: 50,
+                    height: 50,
+                },
+            ),
+            replay,
+        );
+        WalkTheDog::Loaded(Box::new(walk))
+    }
+
+    fn walk(game: &WalkTheDog) -> &Walk {
+        match game {
+            WalkTheDog::Loaded(walk) => walk,
+            WalkTheDog::Loading { .. } => panic!("the fixture is loaded"),
+        }
+    }
+
+    /// Everything an update can change about the boy
+    fn snapshot(game: &WalkTheDog) -> (&'static str, Point, Facing, u8, Size) {
+        let boy = walk(game).boy.simulation();
+        (
+            boy.state_name(),
+            boy.position(),
+            boy.facing(),
+            boy.health(),
+            boy.bounding_box_size(),
+        )
+    }
+
+    /// [held, pressed, released] bits per tick into ActionStates
+    fn ticks(script: &[[u8; 3]]) -> Vec<ActionState> {
+        let mut previous = ActionState::default();
+        script
+            .iter()
+            .map(|bits| {
+                previous = ActionState::from_bits(*bits, &previous);
+                previous
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_replayed_input_reproduces_every_update() {
+        let repeat = |bits: [u8; 3], count: usize| std::iter::repeat_n(bits, count);
+        let script: Vec<[u8; 3]> = repeat([0, 0, 0], 10)
+            .chain(repeat([RUN, RUN, 0], 1))
+            .chain(repeat([RUN, 0, 0], 20))
+            .chain(repeat([RUN | JUMP, JUMP, 0], 1))
+            .chain(repeat([RUN, 0, JUMP], 1))
+            .chain(repeat([RUN, 0, 0], 60))
+            .chain(repeat([RUN | SLIDE, SLIDE, 0], 1))
+            .chain(repeat([RUN, 0, SLIDE], 1))
+            .chain(repeat([RUN, 0, 0], 40))
+            .collect();
+
+        // live : updates straight from the player's ticks, recorded as we go
+        let mut live = game(false);
+        let mut recording = Recording::default();
+        let mut played = Vec::new();
+        for tick in ticks(&script) {
+            recording.record(&tick);
+            live.update(&tick);
+            played.push(snapshot(&live));
+        }
+
+        // replay : the recording's bytes, fed through the game loop's input
+        #[cfg(debug_assertions)]
+        hot_reload::request_reload(WalkTheDog::SHEET_PATH);
+        let playback = Recording::parse("run.wtdr", &recording.to_bytes())
+            .unwrap()
+            .into_playback();
+        let mut input = TickInput::Replay(playback);
+        let mut replayed = game(true);
+        for expected in &played {
+            input.feed(&mut replayed);
+            assert_eq!(&snapshot(&replayed), expected);
+        }
+        assert!(
+            played.iter().any(|(_, position, ..)| position.x > 0),
+            "the script should actually drive the boy"
+        );
+        #[cfg(debug_assertions)]
+        assert_eq!(
+            hot_reload::take_pending(),
+            [WalkTheDog::SHEET_PATH],
+            "a replay leaves reload requests alone"
+        );
+    }
+
+    #[test]
+    fn test_footsteps_kick_up_dust_that_fades() {
+        let overrides = serde_json::from_str(r#"{ "Run": { "events": { "3": ["footstep"] } } }"#);
+        let mut game = game_with(false, &overrides.unwrap());
+        let run = ticks(&[[RUN, RUN, 0], [RUN, 0, 0]]);
+        game.update(&run[0]);
+        let mut updates = 0;
+        while walk(&game).puffs.is_empty() {
+            game.update(&run[1]);
+            updates += 1;
+            assert!(updates < 60, "Run (3) should come round");
+        }
+        let boy = walk(&game).boy.simulation().bounding_box();
+        let puff = walk(&game).puffs[0];
+        assert_eq!(puff.center.y, boy.position.y + boy.size.height);
+
+        // standing still : no new steps, the last one fades out
+        let idle = ticks(&[[0, 0, RUN], [0, 0, 0]]);
+        game.update(&idle[0]);
+        for _ in 0..Puff::TICKS {
+            game.update(&idle[1]);
+        }
+        assert!(walk(&game).puffs.is_empty());
+    }
+
+    #[test]
+    fn test_dying_ends_the_game() {
+        let mut game = game(false);
+        let run = ticks(&[[RUN, RUN, 0], [RUN, 0, 0]]);
+        game.update(&run[0]);
+        assert!(!walk(&game).game_over);
+
+        if let WalkTheDog::Loaded(walk) = &mut game {
+            walk.boy.simulation_mut().knock_out();
+            walk.puffs.push(Puff {
+                center: Point { x: 0, y: 0 },
+                radius: 4,
+                ticks_left: Puff::TICKS,
+            });
+        }
+        game.update(&run[1]);
+        assert!(walk(&game).game_over);
+        for _ in 0..60 {
+            game.update(&run[1]);
+        }
+        assert_eq!(walk(&game).boy.simulation().state_name(), "Dead");
+        assert!(walk(&game).game_over, "nothing brings him back");
+        assert!(walk(&game).puffs.is_empty(), "the dust still settles");
+    }
+
+    #[test]
+    fn test_clicking_a_target_selects_it() {
+        let mut game = game(false);
+        let mut pointer = PointerState::default();
+        let mut click = |game: &mut WalkTheDog, x: i16, y: i16| {
+            for phase in [PointerPhase::Down, PointerPhase::Up] {
+                pointer.begin_tick();
+                pointer.feed(PointerSample {
+                    id: 1,
+                    phase,
+                    position: Point { x, y },
+                    buttons: u8::from(phase == PointerPhase::Down),
+                    time_ms: 0.0,
+                });
+                game.pointer(&pointer);
+            }
+        };
+        click(&mut game, 175, 25);
+        assert_eq!(walk(&game).selected, Some(Target::Stone));
+        // nothing there, the selection stays
+        click(&mut game, 400, 25);
+        assert_eq!(walk(&game).selected, Some(Target::Stone));
+        click(&mut game, 175, 25);
+        assert_eq!(walk(&game).selected, None);
+    }
+
+    /// Highest the running boy gets on the Jump bits of `script`
+    fn jump_height(script: &[[u8; 3]]) -> i16 {
+        let mut game = game(false);
+        game.update(&ticks(&[[RUN, RUN, 0]])[0]);
+        let floor = walk(&game).boy.simulation().position().y;
+        let mut top = floor;
+        let rest = std::iter::repeat_n([0, 0, 0], 120);
+        let script: Vec<[u8; 3]> = script
+            .iter()
+            .copied()
+            .chain(rest)
+            .map(|[held, pressed, released]| [held | RUN, pressed, released])
+            .collect();
+        for tick in ticks(&script) {
+            game.update(&tick);
+            top = top.min(walk(&game).boy.simulation().position().y);
+        }
+        floor - top
+    }
+
+    #[test]
+    fn test_jump_just_after_braking_to_a_stop() {
+        let mut game = game(false);
+        let run = ticks(&[[RUN, RUN, 0], [RUN, 0, 0], [0, 0, RUN], [0, 0, 0]]);
+        for tick in &run[..2] {
+            game.update(tick);
+        }
+        game.update(&run[2]);
+        let mut updates = 0;
+        while walk(&game).boy.simulation().state_name() != "Idle" {
+            game.update(&run[3]);
+            updates += 1;
+            assert!(updates < 60, "braking should come to a stop");
+        }
+        // a tick late for a jump, inside the coyote window
+        game.update(&ticks(&[[JUMP, JUMP, 0]])[0]);
+        assert_eq!(walk(&game).boy.simulation().state_name(), "Jumping");
+    }
+
+    #[test]
+    fn test_tap_gesture_jumps_full_height() {
+        let mut hold = vec![[JUMP, 0, 0]; 40];
+        hold[0] = [JUMP, JUMP, 0];
+        let held = jump_height(&hold);
+        // a key let go after a few ticks : cut short
+        let hop = jump_height(&[[JUMP, JUMP, 0], [JUMP, 0, 0], [0, 0, JUMP]]);
+        // a tap or swipe up : down and up in one tick, never held
+        let tap = jump_height(&[[0, JUMP, JUMP]]);
+        assert!(hop < held, "hop {} vs held {}", hop, held);
+        assert_eq!(tap, held);
+    }
+
+    #[test]
+    fn test_second_press_in_the_air_double_jumps() {
+        let mut game = game(false);
+        let script = [[RUN | JUMP, JUMP, 0]]
+            .into_iter()
+            .chain(std::iter::repeat_n([RUN | JUMP, 0, 0], 10))
+            .chain([[RUN, 0, JUMP]])
+            .collect::<Vec<_>>();
+        let mut previous = ActionState::default();
+        let mut tick = |game: &mut WalkTheDog, bits: [u8; 3]| {
+            previous = ActionState::from_bits(bits, &previous);
+            game.update(&previous);
+        };
+        for bits in script {
+            tick(&mut game, bits);
+        }
+        assert_eq!(walk(&game).boy.simulation().state_name(), "Jumping");
+        assert!(walk(&game).boy.simulation().can_jump(), "one air jump left");
+
+        tick(&mut game, [RUN | JUMP, JUMP, 0]);
+        assert_eq!(walk(&game).boy.simulation().state_name(), "DoubleJumping");
+        assert!(
+            !walk(&game).boy.simulation().can_jump(),
+            "air jumps used up"
+        );
+        // a third press waits in the buffer for the landing
+        tick(&mut game, [RUN, 0, JUMP]);
+        tick(&mut game, [RUN | JUMP, JUMP, 0]);
+        assert_eq!(walk(&game).boy.simulation().state_name(), "DoubleJumping");
+    }
 }
@@ -1,4 +1,10 @@
 // ==================== Imports ====================
+use crate::engine::action::{
+    live_bindings, request_binding_change, Action, Binding, BindingCommand, Bindings,
+};
+use crate::engine::asset::{self, AssetError, BrowserFetcher, LoadOptions};
+use crate::engine::keycode::KeyCode;
+use crate::engine::replay::{self, Playback, Recording};
 use crate::engine::GameLoop;
 use crate::game::WalkTheDog;
 use wasm_bindgen::prelude::*;
@@ -25,9 +31,10 @@ use wasm_bindgen::JsValue;
 
 #[macro_use]
 mod browser;
-mod engine;
+// engine + sprite are public so native benches and tools can link them
+pub mod engine;
 mod game;
-mod sprite;
+pub mod sprite;
 
 // ==================== Main Functions ====================
 /// Main entry for Webassembly module
@@ -40,11 +47,71 @@ pub fn main_js() -> Result<(), JsValue> {
     console_error_panic_hook::set_once();
 
     browser::spawn_local(async move {
-        let game = WalkTheDog::new();
-        GameLoop::start(game)
-            .await
-            .expect("[lib.rs::main_js] Could not start game loop");
+        let started = match browser::query_param("replay") {
+            Some(path) => match load_replay(&path).await {
+                Ok(playback) => GameLoop::start_replay(WalkTheDog::replaying(), playback).await,
+                Err(err) => Err(err.into()),
+            },
+            None => GameLoop::start(WalkTheDog::new()).await,
+        };
+        started.expect("[lib.rs::main_js] Could not start game loop");
     });
 
     Ok(())
 }
+
+/// Fetch a replay recorded by export_replay()
+async fn load_replay(path: &str) -> Result<Playback, AssetError> {
+    let bytes = asset::fetch_bytes(&BrowserFetcher, path, LoadOptions::default()).await?;
+    let recording = Recording::parse(path, &bytes)?;
+    log!("Replaying {} ({} ticks)", path, recording.ticks());
+    Ok(recording.into_playback())
+}
+
+/// Ask the running game to re-fetch an asset, e.g. from the dev console :
+/// - reload_assets("rhb.json")
+/// - development builds only, release builds ignore it
+#[wasm_bindgen]
+pub fn reload_assets(path: &str) {
+    #[cfg(debug_assertions)]
+    engine::hot_reload::request_reload(path);
+    #[cfg(not(debug_assertions))]
+    log!("reload_assets({}) ignored : hot reload is dev only", path);
+}
+
+/// Rebind an action at runtime, e.g. rebind_action("jump", "KeyW")
+/// - `code` is a KeyboardEvent.code, the change is saved for the next session
+#[wasm_bindgen]
+pub fn rebind_action(action: &str, code: &str) -> Result<(), JsValue> {
+    let action = Action::from_name(action)
+        .ok_or_else(|| JsValue::from_str(&format!("Unknown action : {}", action)))?;
+    let code: KeyCode = code
+        .parse()
+        .map_err(|err| JsValue::from_str(&format!("{}", err)))?;
+    request_binding_change(BindingCommand::Rebind(action, Binding::Key(code)));
+    Ok(())
+}
+
+/// Replace every binding with a JSON document, as produced by export_bindings()
+#[wasm_bindgen]
+pub fn import_bindings(json: &str) -> Result<(), JsValue> {
+    let bindings = Bindings::from_json(json)
+        .map_err(|err| JsValue::from_str(&format!("Invalid bindings : {}", err)))?;
+    request_binding_change(BindingCommand::Replace(bindings));
+    Ok(())
+}
+
+/// Current bindings as JSON
+/// - the ones the game is using, including rebinds not applied yet, even
+///   when saving them to localStorage failed
+#[wasm_bindgen]
+pub fn export_bindings() -> String {
+    live_bindings().unwrap_or_else(Bindings::load).to_json()
+}
+
+/// Everything played so far as a replay file, save it and reload the page
+/// with ?replay=<file> to watch it again
+#[wasm_bindgen]
+pub fn export_replay() -> Vec<u8> {
+    replay::recorded_bytes()
+}
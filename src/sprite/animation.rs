@@ -0,0 +1,533 @@
+/// Animations are DISCOVERED from the loaded Sheet instead of hard-coded :
+/// - frame keys are grouped on their prefix   ("Run (3).png" -> "Run")
+/// - frames are ordered on their numeric suffix ("Run (3).png" -> 3)
+/// - frame count is however many frames the sheet actually ships
+///
+/// An optional override file tunes playback per animation (speed, per-frame
+/// durations, speed multiplier, loop mode), names events fired on frames and
+/// sets hitboxes,
+/// so a re-export of the sheet with a different frame count just works.
+///
+/// ELI5:
+/// ┌─────────────── Sheet Keys → AnimationSet ─────────────────────┐
+/// │                                                               │
+/// │  "Run (1).png" ─┐                                             │
+/// │  "Run (2).png" ─┼──► "Run"  : frames [1..8], size of frame 1  │
+/// │  "Run (8).png" ─┘                                             │
+/// │                                                               │
+/// │  "Idle (1).png"─┬──► "Idle" : frames [1..10]                  │
+/// │  "Idle (10).png"┘                                             │
+/// │                                                               │
+/// │  rhb_animations.json ──► { "Run": { speed, durations,        │
+/// │                             multiplier, mode, events,        │
+/// │                             hitbox, frame_hitboxes } }  (opt) │
+/// └───────────────────────────────────────────────────────────────┘
+///
+/// The set is COMPILED once at load time into an indexed table, so drawing
+/// never formats a key String or hashes into Sheet.frames :
+/// ┌─────────────── Indexed Lookup ────────────────────────────────┐
+/// │  load : "Run" ──(hash once)──► AnimationId(2)                 │
+/// │  draw : AnimationId(2) + frame ─► animations[2].rects[i]      │
+/// │                                    (Vec index, no allocation) │
+/// └───────────────────────────────────────────────────────────────┘
+use crate::engine::{Cell, Point, Rect, Sheet, SheetRect, Size};
+use crate::sprite::{DEFAULT_SPRITE_SIZE, FRAME_TICK_RATE};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How an animation plays, and what happens once the last frame has been
+/// shown, see sprite::player for the frame order of each
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoopMode {
+    /// wrap back to the first frame
+    #[default]
+    Loop,
+    /// play through once, then rest on the first frame
+    Once,
+    /// play through once, then freeze on the last frame
+    HoldLast,
+    /// Loop, last frame to first
+    Reverse,
+    /// first to last and back again, forever
+    PingPong,
+}
+
+/// Per animation playback settings, every field is optional so the override
+/// file only needs to list what differs from the defaults
+/// - without a mode, every state plays in the one it declares
+///   (SpriteState::mode)
+/// - durations are milliseconds per frame, in playback order : one for every
+///   frame, or none and every frame plays for speed (validate_sheet reports
+///   lists in between)
+/// - events are keyed on the sheet's frame number, the 3 of "Run (3).png",
+///   and fire when that frame comes on screen
+/// - hitbox is the collision box, relative to the frame's top left, for
+///   every frame but those frame_hitboxes lists (same keys as events)
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+pub struct AnimationOverride {
+    pub speed: Option<u8>,
+    pub durations: Option<Vec<u16>>,
+    pub multiplier: Option<f32>,
+    pub mode: Option<LoopMode>,
+    pub events: Option<HashMap<u16, Vec<String>>>,
+    pub hitbox: Option<SheetRect>,
+    pub frame_hitboxes: Option<HashMap<u16, SheetRect>>,
+}
+
+/// Override file contents, keyed on animation name :
+/// - { "Run": { "speed": 2 }, "Slide": { "mode": "once" } }
+/// - { "Slide": { "durations": [50, 50, 50, 50, 200], "multiplier": 1.5 } }
+/// - { "Run": { "events": { "3": ["footstep"], "7": ["footstep"] } } }
+/// - { "Slide": { "hitbox": { "x": 20, "y": 70, "w": 120, "h": 60 } } }
+#[derive(Debug, Default, Clone, Deserialize, Serialize)]
+#[serde(transparent)]
+pub struct AnimationOverrides {
+    pub animations: HashMap<String, AnimationOverride>,
+}
+
+/// An event named in the override file, fired when its frame came on screen
+/// - queued by the sprite's update(), see RedHatBoy::take_events()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnimationEvent {
+    pub name: String,
+    pub animation: &'static str,
+    /// index into the animation's frames, NOT the sheet's frame number
+    pub frame: u8,
+}
+
+/// Index of an animation in its AnimationSet, resolve once with
+/// AnimationSet::id() and keep it around instead of the name
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct AnimationId(u16);
+
+/// A trimmed frame's place in the image it was cropped from, from the
+/// sheet's spriteSourceSize / sourceSize
+/// - untrimmed frames sit at (0, 0) and are their own size
+/// - hitboxes are relative to the untrimmed frame, so trimming a sheet
+///   doesn't move them
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct FrameTrim {
+    /// top left of the source rect inside the untrimmed frame
+    pub offset: Point,
+    /// size of the untrimmed frame
+    pub size: Size,
+}
+
+impl FrameTrim {
+    fn of(cell: &Cell) -> Self {
+        let frame = &cell.frame;
+        FrameTrim {
+            offset: cell
+                .sprite_source_size
+                .map_or(Point { x: 0, y: 0 }, |trimmed| Point {
+                    x: trimmed.x,
+                    y: trimmed.y,
+                }),
+            size: cell.source_size.map_or(
+                Size {
+                    width: frame.w,
+                    height: frame.h,
+                },
+                |source| Size {
+                    width: source.w,
+                    height: source.h,
+                },
+            ),
+        }
+    }
+}
+
+/// A single animation discovered from the sheet
+#[derive(Debug, Clone)]
+pub struct AnimationDef {
+    pub name: String,
+    /// sheet frame keys in playback order
+    pub frames: Vec<String>,
+    /// source rect of each frame in the sheet image, same order as frames
+    pub rects: Vec<Rect>,
+    /// where each rect sits in its untrimmed frame, same order as frames
+    pub trims: Vec<FrameTrim>,
+    /// untrimmed size of the first frame, used as the state's bounding box
+    pub size: Size,
+    /// ticks each frame stays on screen, unless durations lists it
+    pub speed: u8,
+    /// milliseconds per frame as listed in the override file, may be empty
+    pub durations: Vec<u16>,
+    /// scales playback speed, 1.0 unless overridden
+    pub speed_multiplier: f32,
+    /// from the override file, None leaves it to the state
+    pub mode: Option<LoopMode>,
+    /// event names per frame, same order as frames
+    pub events: Vec<Vec<String>>,
+    /// collision box relative to the frame, None collides with the frame
+    pub hitbox: Option<Rect>,
+    /// per frame collision boxes, same order as frames, over hitbox
+    pub frame_hitboxes: Vec<Option<Rect>>,
+    /// frame numbers the override file has events or hitboxes on, but the
+    /// sheet doesn't ship, see validate
+    pub unknown_frames: Vec<u16>,
+}
+
+impl AnimationDef {
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AnimationSet {
+    animations: Vec<AnimationDef>,
+    // only consulted at load time, to resolve a name into an AnimationId
+    ids: HashMap<String, AnimationId>,
+}
+
+impl AnimationSet {
+    /// Group every frame key in the sheet on its prefix, then apply overrides
+    /// - keys that don't end in a number are ignored (not part of a sequence)
+    pub fn from_sheet(sheet: &Sheet, overrides: &AnimationOverrides) -> Self {
+        let mut grouped: HashMap<&str, Vec<(u16, &str)>> = HashMap::new();
+        for key in sheet.frames.keys() {
+            if let Some((name, number)) = parse_frame_key(key) {
+                grouped.entry(name).or_default().push((number, key));
+            }
+        }
+
+        let animations = grouped
+            .into_iter()
+            .map(|(name, mut frames)| {
+                // keys break ties, so duplicate numbers play in the same order every load
+                frames.sort_unstable();
+                let rects: Vec<Rect> = frames
+                    .iter()
+                    .map(|(_, key)| to_rect(&sheet.frames[*key].frame))
+                    .collect();
+                let trims: Vec<FrameTrim> = frames
+                    .iter()
+                    .map(|(_, key)| FrameTrim::of(&sheet.frames[*key]))
+                    .collect();
+                let size = trims.first().map_or(DEFAULT_SPRITE_SIZE, |trim| trim.size);
+                let settings = overrides.animations.get(name);
+                let mut unknown_frames = Vec::new();
+                let mut index_of = |number: &u16| {
+                    let index = frames.iter().position(|(frame, _)| frame == number);
+                    if index.is_none() {
+                        unknown_frames.push(*number);
+                    }
+                    index
+                };
+                let mut events = vec![Vec::new(); frames.len()];
+                for (number, names) in settings
+                    .and_then(|s| s.events.as_ref())
+                    .into_iter()
+                    .flatten()
+                {
+                    if let Some(index) = index_of(number) {
+                        events[index].extend(names.iter().cloned());
+                    }
+                }
+                let mut frame_hitboxes = vec![None; frames.len()];
+                for (number, hitbox) in settings
+                    .and_then(|s| s.frame_hitboxes.as_ref())
+                    .into_iter()
+                    .flatten()
+                {
+                    if let Some(index) = index_of(number) {
+                        frame_hitboxes[index] = Some(to_rect(hitbox));
+                    }
+                }
+                // HashMap order, sorted so the report reads the same every load
+                unknown_frames.sort_unstable();
+                unknown_frames.dedup();
+                AnimationDef {
+                    name: name.to_string(),
+                    frames: frames.into_iter().map(|(_, key)| key.to_string()).collect(),
+                    rects,
+                    trims,
+                    size,
+                    // a speed of 0 would never advance, treat it as unset
+                    speed: settings
+                        .and_then(|s| s.speed)
+                        .filter(|speed| *speed > 0)
+                        .unwrap_or(FRAME_TICK_RATE),
+                    durations: settings
+                        .and_then(|s| s.durations.clone())
+                        .unwrap_or_default(),
+                    // 0 would never finish a Once animation (and strand the
+                    // states waiting on it), negative or NaN would run time
+                    // backwards : treat them as unset, like a speed of 0
+                    speed_multiplier: settings
+                        .and_then(|s| s.multiplier)
+                        .filter(|multiplier| *multiplier > 0.0)
+                        .unwrap_or(1.0),
+                    mode: settings.and_then(|s| s.mode),
+                    events,
+                    hitbox: settings.and_then(|s| s.hitbox.as_ref()).map(to_rect),
+                    frame_hitboxes,
+                    unknown_frames,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        Self::compile(animations)
+    }
+
+    /// Assign every animation an id, sorted by name so ids are stable
+    /// between loads of the same sheet
+    fn compile(mut animations: Vec<AnimationDef>) -> Self {
+        animations.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+        let ids = animations
+            .iter()
+            .enumerate()
+            .map(|(index, def)| {
+                let id = u16::try_from(index).expect("more than u16::MAX animations");
+                (def.name.clone(), AnimationId(id))
+            })
+            .collect();
+        Self { animations, ids }
+    }
+
+    /// Resolve a name to an id, do this at load time, NOT per frame
+    pub fn id(&self, name: &str) -> Option<AnimationId> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&AnimationDef> {
+        self.id(name).map(|id| self.by_id(id))
+    }
+
+    pub fn by_id(&self, id: AnimationId) -> &AnimationDef {
+        &self.animations[usize::from(id.0)]
+    }
+
+    /// Source rect of frame `index`, see player::Animation::frame()
+    /// - plain Vec indexing : no String formatting, no hashing
+    pub fn frame_at(&self, id: AnimationId, index: u8) -> Option<&Rect> {
+        self.by_id(id).rects.get(usize::from(index))
+    }
+
+    /// Where frame `index`'s source rect sits in the untrimmed frame
+    pub fn trim_at(&self, id: AnimationId, index: u8) -> Option<FrameTrim> {
+        self.by_id(id).trims.get(usize::from(index)).copied()
+    }
+
+    /// Event names on frame `index`, empty for most frames
+    pub fn events_at(&self, id: AnimationId, index: u8) -> &[String] {
+        self.by_id(id)
+            .events
+            .get(usize::from(index))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Collision box of frame `index`, relative to the frame's top left
+    /// - its own from frame_hitboxes, else the animation's hitbox
+    /// - None when neither is set, collide with the whole frame then
+    pub fn hitbox_at(&self, id: AnimationId, index: u8) -> Option<Rect> {
+        let animation = self.by_id(id);
+        animation
+            .frame_hitboxes
+            .get(usize::from(index))
+            .copied()
+            .flatten()
+            .or(animation.hitbox)
+    }
+
+    /// Sheet key of the frame `frame_at` returns, for debugging
+    pub fn frame_name_at(&self, id: AnimationId, index: u8) -> Option<&str> {
+        self.by_id(id)
+            .frames
+            .get(usize::from(index))
+            .map(String::as_str)
+    }
+}
+
+fn to_rect(frame: &SheetRect) -> Rect {
+    Rect::new(
+        Point {
+            x: frame.x,
+            y: frame.y,
+        },
+        Size {
+            width: frame.w,
+            height: frame.h,
+        },
+    )
+}
+
+/// Split a frame key into (animation name, frame number)
+/// - "Run (3).png" -> ("Run", 3)
+/// - "run_03.png"  -> ("run", 3)
+/// - "Run.png"     -> None
+pub fn parse_frame_key(key: &str) -> Option<(&str, u16)> {
+    // drop the file extension, if any
+    let stem = key.rsplit_once('.').map_or(key, |(stem, _)| stem);
+    // drop a closing paren so "Run (3)" and "Run 3" parse the same
+    let stem = stem.strip_suffix(')').unwrap_or(stem);
+    let digits_at = stem.trim_end_matches(|c: char| c.is_ascii_digit()).len();
+    let (prefix, digits) = stem.split_at(digits_at);
+    let number = digits.parse().ok()?;
+    let name = prefix.trim_end_matches(['(', '_', '-', ' ']);
+    if name.is_empty() {
+        return None;
+    }
+    Some((name, number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Cell, SheetRect};
+
+    fn sheet(keys: &[&str]) -> Sheet {
+        Sheet {
+            frames: keys
+                .iter()
+                .enumerate()
+                .map(|(i, key)| {
+                    let cell = Cell::new(SheetRect {
+                        x: 0,
+                        y: 0,
+                        w: 10 + i as i16,
+                        h: 20,
+                    });
+                    (key.to_string(), cell)
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_parse_frame_key() {
+        assert_eq!(parse_frame_key("Run (3).png"), Some(("Run", 3)));
+        assert_eq!(parse_frame_key("Idle (10).png"), Some(("Idle", 10)));
+        assert_eq!(parse_frame_key("run_03.png"), Some(("run", 3)));
+        assert_eq!(parse_frame_key("Jump 7"), Some(("Jump", 7)));
+        assert_eq!(parse_frame_key("Run.png"), None);
+        assert_eq!(parse_frame_key("(3).png"), None);
+    }
+
+    #[test]
+    fn test_frames_grouped_and_ordered_numerically() {
+        let sheet = sheet(&["Run (10).png", "Run (2).png", "Run (1).png", "Idle (1).png"]);
+        let set = AnimationSet::from_sheet(&sheet, &AnimationOverrides::default());
+
+        let run = set.get("Run").unwrap();
+        assert_eq!(run.frames, ["Run (1).png", "Run (2).png", "Run (10).png"]);
+        assert_eq!(run.frame_count(), 3);
+        assert_eq!(run.speed, FRAME_TICK_RATE);
+        assert_eq!(run.mode, None);
+        assert!(run.durations.is_empty());
+        assert_eq!(run.speed_multiplier, 1.0);
+        assert_eq!(set.get("Idle").unwrap().frame_count(), 1);
+        assert!(set.get("Slide").is_none());
+    }
+
+    #[test]
+    fn test_frame_at_indexes_compiled_rects() {
+        let sheet = sheet(&["Run (1).png", "Run (2).png"]);
+        let set = AnimationSet::from_sheet(&sheet, &AnimationOverrides::default());
+        let run = set.id("Run").unwrap();
+
+        let first = sheet.frames["Run (1).png"].frame.w;
+        let second = sheet.frames["Run (2).png"].frame.w;
+        assert_eq!(set.frame_at(run, 0).unwrap().size.width, first);
+        assert_eq!(set.frame_at(run, 1).unwrap().size.width, second);
+        assert_eq!(set.frame_name_at(run, 1), Some("Run (2).png"));
+        assert!(set.frame_at(run, 2).is_none());
+    }
+
+    #[test]
+    fn test_overrides_applied() {
+        let sheet = sheet(&["Slide (1).png", "Slide (2).png"]);
+        let mut overrides = AnimationOverrides::default();
+        overrides.animations.insert(
+            "Slide".to_string(),
+            AnimationOverride {
+                speed: Some(5),
+                durations: Some(vec![40, 120]),
+                multiplier: Some(1.5),
+                mode: Some(LoopMode::Once),
+                events: None,
+                ..AnimationOverride::default()
+            },
+        );
+        let set = AnimationSet::from_sheet(&sheet, &overrides);
+
+        let slide = set.get("Slide").unwrap();
+        assert_eq!(slide.speed, 5);
+        assert_eq!(slide.durations, [40, 120]);
+        assert_eq!(slide.speed_multiplier, 1.5);
+        assert_eq!(slide.mode, Some(LoopMode::Once));
+    }
+
+    #[test]
+    fn test_multiplier_must_be_positive() {
+        let sheet = sheet(&["Slide (1).png", "Hurt (1).png", "Run (1).png"]);
+        let overrides: AnimationOverrides = serde_json::from_str(
+            r#"{ "Slide": { "multiplier": 0.0 }, "Hurt": { "multiplier": -2.0 },
+                 "Run": { "multiplier": 0.5 } }"#,
+        )
+        .unwrap();
+        let set = AnimationSet::from_sheet(&sheet, &overrides);
+        let multiplier = |name| set.get(name).unwrap().speed_multiplier;
+        assert_eq!(multiplier("Slide"), 1.0);
+        assert_eq!(multiplier("Hurt"), 1.0);
+        assert_eq!(multiplier("Run"), 0.5);
+    }
+
+    #[test]
+    fn test_once_and_hold_last_are_distinct_modes() {
+        let overrides: AnimationOverrides = serde_json::from_str(
+            r#"{ "Dead": { "mode": "hold_last" }, "Slide": { "mode": "once" } }"#,
+        )
+        .unwrap();
+        let mode = |name: &str| overrides.animations[name].mode;
+        assert_eq!(mode("Dead"), Some(LoopMode::HoldLast));
+        assert_eq!(mode("Slide"), Some(LoopMode::Once));
+    }
+
+    #[test]
+    fn test_events_land_on_their_numbered_frame() {
+        // numbered 1, 3, 7 : the 7th sheet frame is the 3rd played
+        let sheet = sheet(&["Run (1).png", "Run (3).png", "Run (7).png"]);
+        let overrides: AnimationOverrides = serde_json::from_str(
+            r#"{ "Run": { "events": { "3": ["footstep"], "7": ["footstep", "dust"], "9": ["x"] } } }"#,
+        )
+        .unwrap();
+        let set = AnimationSet::from_sheet(&sheet, &overrides);
+        let run = set.id("Run").unwrap();
+
+        assert!(set.events_at(run, 0).is_empty());
+        assert_eq!(set.events_at(run, 1), ["footstep"]);
+        assert_eq!(set.events_at(run, 2), ["footstep", "dust"]);
+        assert!(set.events_at(run, 3).is_empty(), "past the last frame");
+        assert_eq!(set.by_id(run).unknown_frames, [9]);
+    }
+
+    #[test]
+    fn test_frame_hitboxes_over_the_animation_hitbox() {
+        let sheet = sheet(&["Dead (1).png", "Dead (2).png", "Dead (3).png"]);
+        let overrides: AnimationOverrides = serde_json::from_str(
+            r#"{ "Dead": {
+                "hitbox": { "x": 20, "y": 80, "w": 120, "h": 50 },
+                "frame_hitboxes": { "1": { "x": 40, "y": 20, "w": 80, "h": 110 },
+                                    "4": { "x": 0, "y": 0, "w": 1, "h": 1 } }
+            } }"#,
+        )
+        .unwrap();
+        let set = AnimationSet::from_sheet(&sheet, &overrides);
+        let dead = set.id("Dead").unwrap();
+
+        let hitbox = |index| {
+            set.hitbox_at(dead, index)
+                .map(|rect| (rect.position.y, rect.size.height))
+        };
+        assert_eq!(hitbox(0), Some((20, 110)), "its own");
+        assert_eq!(hitbox(1), Some((80, 50)), "the animation's");
+        assert_eq!(hitbox(2), Some((80, 50)));
+        assert_eq!(set.by_id(dead).unknown_frames, [4]);
+
+        let set = AnimationSet::from_sheet(&sheet, &AnimationOverrides::default());
+        assert!(set.hitbox_at(set.id("Dead").unwrap(), 0).is_none());
+    }
+}
@@ -0,0 +1,422 @@
+/// state_machine! : a character's typestate machine from a transition table
+/// - one line per state, one line per transition, the macro writes the rest
+/// - typestate safety is untouched : every transition body calls methods on
+///   the typed state (RedHatBoyState<Running>::jump ...), a transition the
+///   state doesn't have still fails to compile
+///
+/// TABLE: what one `states` line generates
+/// ┌─ table line ────────────────┬─ generated ─────────────────────────────┐
+/// │ Running(sprite::Running)    │ enum variant Running(State<Running>)    │
+/// │                             │ From<State<Running>> for the machine    │
+/// │                             │ context() / context_mut() arms          │
+/// │                             │ state_index() / sprite_name() arms,     │
+/// │                             │ so StateClips can find its animation    │
+/// └─────────────────────────────┴─────────────────────────────────────────┘
+/// ┌─ table line ────────────────┬─ generated ─────────────────────────────┐
+/// │ Running, Event::Jump =>     │ transition() arm, the body's result is  │
+/// │   [Jumping]                 │ .into() the machine                     │
+/// │   |state, animations| ...   │ TRANSITIONS rows, one per target        │
+/// │ (anything not listed)       │ ignored, the machine stays as it was    │
+/// └─────────────────────────────┴─────────────────────────────────────────┘
+///
+/// Targets are declared, not inferred : a body can land in several states
+/// (Jumping -> Jumping or Running), each with an optional guard saying when
+/// - [Jumping if "in the air", Running if "landed"]
+/// - a target that isn't a state fails to compile, a body landing outside
+///   its declared targets fails a debug_assert
+///
+/// The state wrapper must provide `fn context(&self) -> &Context` and
+/// `fn context_mut(&mut self) -> &mut Context`, and every marker type must
+/// implement SpriteState (its animation name)
+use crate::sprite::{AnimationId, AnimationSet};
+use std::fmt::Write;
+
+macro_rules! state_machine {
+    (@guard) => {
+        None
+    };
+    (@guard $guard:literal) => {
+        Some($guard)
+    };
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $machine:ident : $wrapper:ident {
+            context: $context:ty,
+            event: $event:ty,
+            env: $env:ty,
+            states: { $($variant:ident($marker:ty)),* $(,)? },
+            transitions: {
+                $(
+                    $from:ident, $on:pat =>
+                    [$($to:ident $(if $guard:literal)?),+ $(,)?]
+                    |$state:ident, $arg:ident| $body:expr
+                ),* $(,)?
+            } $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $machine {
+            $($variant($wrapper<$marker>),)*
+        }
+
+        $(
+            impl From<$wrapper<$marker>> for $machine {
+                fn from(state: $wrapper<$marker>) -> Self {
+                    $machine::$variant(state)
+                }
+            }
+        )*
+
+        impl $machine {
+            /// Every state, in `states` order
+            #[allow(dead_code)]
+            const STATES: &'static [&'static str] = &[$(stringify!($variant),)*];
+
+            /// The transition table, one row per (state, event, target)
+            #[allow(dead_code)]
+            const TRANSITIONS: &'static [$crate::sprite::machine::Transition] = &[
+                $($(
+                    $crate::sprite::machine::Transition {
+                        from: stringify!($from),
+                        event: stringify!($on),
+                        to: {
+                            // fails to compile when the target isn't a state
+                            let _ = $machine::$to;
+                            stringify!($to)
+                        },
+                        guard: state_machine!(@guard $($guard)?),
+                    },
+                )+)*
+            ];
+
+            /// Apply `event`, events the current state has no transition for
+            /// are ignored (e.g. trying to Jump while Sliding)
+            fn transition(self, event: $event, env: &$env) -> Self {
+                match (self, event) {
+                    $(
+                        ($machine::$from($state), $on) => {
+                            let $arg = env;
+                            let next: Self = ($body).into();
+                            debug_assert!(
+                                [$(stringify!($to)),+].contains(&next.state_name()),
+                                "{} on {} landed in undeclared {}",
+                                stringify!($from),
+                                stringify!($on),
+                                next.state_name(),
+                            );
+                            next
+                        }
+                    )*
+                    _ => self,
+                }
+            }
+
+            /// Name of the current state, as in `states`
+            #[allow(dead_code)]
+            fn state_name(&self) -> &'static str {
+                match self {
+                    $($machine::$variant(_) => stringify!($variant),)*
+                }
+            }
+
+            fn context(&self) -> &$context {
+                match self {
+                    $($machine::$variant(state) => state.context(),)*
+                }
+            }
+
+            /// For changes outside any transition, e.g. a reloaded sheet
+            #[allow(dead_code)]
+            fn context_mut(&mut self) -> &mut $context {
+                match self {
+                    $($machine::$variant(state) => state.context_mut(),)*
+                }
+            }
+
+            /// Position of the state in the `states` list
+            fn state_index(&self) -> usize {
+                #[allow(dead_code)]
+                enum Index {
+                    $($variant,)*
+                }
+                match self {
+                    $($machine::$variant(_) => Index::$variant as usize,)*
+                }
+            }
+
+            /// Animation name of the current state
+            #[allow(dead_code)]
+            fn sprite_name(&self) -> &'static str {
+                match self {
+                    $($machine::$variant(_) => {
+                        <$marker as $crate::sprite::SpriteState>::name()
+                    })*
+                }
+            }
+
+            /// Animation name of every state, in `states` order
+            fn sprite_names() -> Vec<&'static str> {
+                vec![$(<$marker as $crate::sprite::SpriteState>::name(),)*]
+            }
+        }
+    };
+}
+
+/// One row of a machine's transition table
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Transition {
+    pub from: &'static str,
+    /// the event pattern as written in the table, e.g. "Event::Jump | Event::CoyoteJump"
+    pub event: &'static str,
+    pub to: &'static str,
+    /// when this target is the one taken, if the event has several
+    pub guard: Option<&'static str>,
+}
+
+impl Transition {
+    /// Event names without their enum path : "Jump | CoyoteJump"
+    pub fn event_label(&self) -> String {
+        self.event
+            .split('|')
+            .map(|event| event.trim().rsplit("::").next().unwrap_or(event))
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+
+    /// Edge label for diagrams, the event then its guard : "Update [landed]"
+    pub fn label(&self) -> String {
+        match self.guard {
+            Some(guard) => format!("{} [{}]", self.event_label(), guard),
+            None => self.event_label(),
+        }
+    }
+}
+
+/// Graphviz DOT, render with `dot -Tsvg`
+pub fn to_dot(name: &str, states: &[&str], transitions: &[Transition]) -> String {
+    let mut dot = format!("digraph {} {{\n    rankdir=LR;\n", name);
+    for state in states {
+        let _ = writeln!(dot, "    {};", state);
+    }
+    for transition in transitions {
+        let _ = writeln!(
+            dot,
+            "    {} -> {} [label=\"{}\"];",
+            transition.from,
+            transition.to,
+            transition.label().replace('"', "\\\"")
+        );
+    }
+    dot.push_str("}\n");
+    dot
+}
+
+/// Mermaid stateDiagram, renders inline on GitHub / GitLab
+pub fn to_mermaid(states: &[&str], transitions: &[Transition]) -> String {
+    let mut mermaid = String::from("stateDiagram-v2\n");
+    if let Some(first) = states.first() {
+        let _ = writeln!(mermaid, "    [*] --> {}", first);
+    }
+    for transition in transitions {
+        let _ = writeln!(
+            mermaid,
+            "    {} --> {} : {}",
+            transition.from,
+            transition.to,
+            // ':' would end the label early
+            transition.label().replace(':', "#58;")
+        );
+    }
+    mermaid
+}
+
+/// AnimationId of every state in a machine, resolved once at load time so
+/// drawing is a Vec index, not a name lookup
+/// - None when the sheet doesn't ship the animation (see validate_sheet)
+#[derive(Debug, Clone)]
+pub struct StateClips {
+    ids: Vec<Option<AnimationId>>,
+}
+
+impl StateClips {
+    /// `names` : the machine's sprite_names()
+    pub fn resolve(names: &[&str], animations: &AnimationSet) -> Self {
+        Self {
+            ids: names.iter().map(|name| animations.id(name)).collect(),
+        }
+    }
+
+    /// `index` : the machine's state_index()
+    pub fn get(&self, index: usize) -> Option<AnimationId> {
+        self.ids.get(index).copied().flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Cell, Sheet, SheetRect};
+    use crate::sprite::{AnimationOverrides, SpriteState};
+
+    // a second character, built from the table alone
+    #[derive(Debug, Copy, Clone)]
+    struct Sitting;
+    #[derive(Debug, Copy, Clone)]
+    struct Walking;
+
+    impl SpriteState for Sitting {
+        fn name() -> &'static str {
+            "Sit"
+        }
+    }
+
+    impl SpriteState for Walking {
+        fn name() -> &'static str {
+            "Walk"
+        }
+    }
+
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    struct DogContext {
+        steps: u32,
+    }
+
+    #[derive(Debug, Copy, Clone)]
+    struct DogState<S> {
+        context: DogContext,
+        _state: S,
+    }
+
+    impl<S> DogState<S> {
+        fn context(&self) -> &DogContext {
+            &self.context
+        }
+
+        fn context_mut(&mut self) -> &mut DogContext {
+            &mut self.context
+        }
+    }
+
+    impl DogState<Sitting> {
+        fn stand_up(self) -> DogState<Walking> {
+            DogState {
+                context: self.context,
+                _state: Walking,
+            }
+        }
+    }
+
+    impl DogState<Walking> {
+        fn step(mut self, stride: u32) -> Self {
+            self.context.steps += stride;
+            self
+        }
+
+        fn sit(self) -> DogState<Sitting> {
+            DogState {
+                context: self.context,
+                _state: Sitting,
+            }
+        }
+    }
+
+    enum DogEvent {
+        Call,
+        Stay,
+        Update,
+    }
+
+    state_machine! {
+        #[derive(Debug, Copy, Clone)]
+        enum Dog: DogState {
+            context: DogContext,
+            event: DogEvent,
+            env: u32,
+            states: {
+                Sitting(Sitting),
+                Walking(Walking),
+            },
+            transitions: {
+                Sitting, DogEvent::Call => [Walking] |state, _stride| state.stand_up(),
+                Walking, DogEvent::Stay => [Sitting] |state, _stride| state.sit(),
+                Walking, DogEvent::Update => [Walking if "always"] |state, stride| {
+                    state.step(*stride)
+                },
+            },
+        }
+    }
+
+    #[test]
+    fn test_machine_from_table() {
+        let dog: Dog = DogState {
+            context: DogContext { steps: 0 },
+            _state: Sitting,
+        }
+        .into();
+        // not in the table : ignored
+        let dog = dog.transition(DogEvent::Update, &2);
+        assert_eq!(dog.context().steps, 0);
+        assert_eq!(dog.sprite_name(), "Sit");
+
+        let dog = dog
+            .transition(DogEvent::Call, &2)
+            .transition(DogEvent::Update, &2)
+            .transition(DogEvent::Update, &2);
+        assert!(matches!(dog, Dog::Walking(_)));
+        assert_eq!(dog.context().steps, 4);
+        assert_eq!(dog.state_index(), 1);
+
+        let dog = dog.transition(DogEvent::Stay, &2);
+        assert_eq!(dog.state_index(), 0);
+    }
+
+    #[test]
+    fn test_clips_follow_state_order() {
+        let frames = [("Walk (1).png", 0), ("Walk (2).png", 10)]
+            .into_iter()
+            .map(|(key, x)| {
+                let frame = SheetRect {
+                    x,
+                    y: 0,
+                    w: 10,
+                    h: 10,
+                };
+                (key.to_string(), Cell::new(frame))
+            })
+            .collect();
+        let animations =
+            AnimationSet::from_sheet(&Sheet { frames }, &AnimationOverrides::default());
+        let clips = StateClips::resolve(&Dog::sprite_names(), &animations);
+        assert_eq!(clips.get(0), None, "no Sit frames in the sheet");
+        assert_eq!(clips.get(1), animations.id("Walk"));
+        assert_eq!(clips.get(2), None);
+    }
+
+    #[test]
+    fn test_table_exports_as_dot_and_mermaid() {
+        assert_eq!(Dog::STATES, ["Sitting", "Walking"]);
+        assert_eq!(
+            Dog::TRANSITIONS[2],
+            Transition {
+                from: "Walking",
+                event: "DogEvent::Update",
+                to: "Walking",
+                guard: Some("always"),
+            }
+        );
+        assert_eq!(
+            to_dot("Dog", Dog::STATES, Dog::TRANSITIONS),
+            "digraph Dog {\n    rankdir=LR;\n    Sitting;\n    Walking;\n    \
+             Sitting -> Walking [label=\"Call\"];\n    \
+             Walking -> Sitting [label=\"Stay\"];\n    \
+             Walking -> Walking [label=\"Update [always]\"];\n}\n"
+        );
+        assert_eq!(
+            to_mermaid(Dog::STATES, Dog::TRANSITIONS),
+            "stateDiagram-v2\n    [*] --> Sitting\n    \
+             Sitting --> Walking : Call\n    \
+             Walking --> Sitting : Stay\n    \
+             Walking --> Walking : Update [always]\n"
+        );
+    }
+}
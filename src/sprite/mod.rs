@@ -1,8 +1,15 @@
 // This is a directory based mod structure
 // mod.rs is the entry point for the sprite/ directory (sprite.rs)
 // allowing organization of related code into submodules
+mod animation;
+// state_machine! has to be defined before the characters using it
+#[macro_use]
+mod machine;
+mod player;
 mod red_hat_boy;
+mod render;
 mod state;
+mod validate;
 
 // TABLE:
 // ┌─────────────── File Structure ──────────────────
@@ -11,15 +18,32 @@ mod state;
 // │  ├─ SpriteState trait
 // │  └─ Basic sprite types
 // │
+// ├─ animation.rs     // Animations discovered from the Sheet
+// │  ├─ AnimationSet
+// │  └─ Override file (speed, durations, loop mode, events)
+// │
+// ├─ player.rs        // Animation : plays one per LoopMode
+// │  └─ frame(), finished(), entered(), set_rate()
+// │
+// ├─ machine.rs       // state_machine! macro
+// │  ├─ Transition table -> enum, From, transition()
+// │  └─ StateClips (animation per state)
+// │
+// ├─ validate.rs      // Load-time Sheet checks
+// │  └─ ValidationReport
+// │
 // ├─ state.rs         // State management
 // │  ├─ Constants
 // │  ├─ State types
 // │  └─ State implementations
 // │
-// └─ red_hat_boy.rs   // Character logic
-//    ├─ Events
-//    ├─ State machine
-//    └─ Character implementation
+// ├─ red_hat_boy.rs   // Character logic
+// │  ├─ Events
+// │  ├─ State machine
+// │  └─ RedHatBoySimulation (no web_sys, native tests)
+// │
+// └─ render.rs        // The only web_sys in sprite/
+//    └─ RedHatBoy (simulation + image, draws)
 // ┌──────────────────────────────────────────────────────────────────────────┐
 // │                      Code Structure vs Photoshop Concepts                │
 // ├────────────────┬──────────────────────┬──────────────────────────────────┤
@@ -33,10 +57,15 @@ mod state;
 // │   states.rs    │ struct Running       │ "Running_Animation" layer group  │
 // │                │ struct Sliding       │ "Slide_Animation" layer group    │
 // │                │ struct Jumping       │ "Jump_Animation" layer group     │
+// │                │ struct DoubleJumping │ "Jump_Animation" again           │
+// │                │ struct Hurt          │ "Hurt_Animation" layer group     │
+// │                │ struct Dead          │ "Dead_Animation" layer group     │
 // ├────────────────┼──────────────────────┼──────────────────────────────────┤
 // │                │ SpriteMetadata       │ Layer Comp settings              │
 // │ red_hat_boy.rs │ frame_count          │ Number of frames in Timeline     │
 // │                │ animation_speed      │ Frame delay settings             │
+// │                │ frame_durations      │ Per-frame delay in the Timeline  │
+// │                │ speed_multiplier     │ Timeline playback speed          │
 // │                │ default_size         │ Canvas/Artboard dimensions       │
 // ├────────────────┼──────────────────────┼──────────────────────────────────┤
 // │    lib.rs      │ Project structure    │ Photoshop Project Manager        │
@@ -45,9 +74,17 @@ mod state;
 // └────────────────┴──────────────────────┴──────────────────────────────────┘
 
 use crate::engine::Size;
+pub use animation::{
+    AnimationEvent, AnimationId, AnimationOverrides, AnimationSet, FrameTrim, LoopMode,
+};
+pub use machine::{to_dot, to_mermaid, Transition};
+pub use player::{Animation, TICK_US};
 // TODO: Explain why we have to pub export here?
-pub use red_hat_boy::RedHatBoy;
+pub use red_hat_boy::RedHatBoySimulation;
+pub use render::RedHatBoy;
+pub use state::Facing;
 use std::num::NonZeroU8;
+pub use validate::validate_sheet;
 
 pub const FRAME_TICK_RATE: u8 = 3;
 pub const DEFAULT_SPRITE_SIZE: Size = Size {
@@ -57,59 +94,111 @@ pub const DEFAULT_SPRITE_SIZE: Size = Size {
 
 // ELI5:
 // ┌─────────────── Animation Frame Sequences ─────────────────┐
-// │  State      Frames    Description                         │
-// ├────────────┬─────────┬────────────────────────────────────┤
-// │  Idle      │ [1-10]  │ Idle stand cycle                   │
-// ├────────────┼─────────┼────────────────────────────────────┤
-// │  Running   │ [1-8]   │ Looping run cycle                  │
-// ├────────────┼─────────┼────────────────────────────────────┤
-// │  Sliding   │ [1-5]   │ Slide animation, returns to run    │
-// ├────────────┼─────────┼────────────────────────────────────┤
-// │  Jumping   │ [1-12]  │ Jump animation, returns to run     │
-// └────────────┴─────────┴────────────────────────────────────┘
-const IDLE_FRAMES: u8 = 10;
-const RUN_FRAMES: u8 = 8;
-const SLIDE_FRAMES: u8 = 5;
-const JUMP_FRAMES: u8 = 12;
+// │  State      Animation  Description                        │
+// ├────────────┬──────────┬───────────────────────────────────┤
+// │  Idle      │ "Idle"   │ Idle stand cycle           (Loop) │
+// ├────────────┼──────────┼───────────────────────────────────┤
+// │  Running   │ "Run"    │ Looping run cycle          (Loop) │
+// ├────────────┼──────────┼───────────────────────────────────┤
+// │  Sliding   │ "Slide"  │ Slide animation, returns   (Once) │
+// │            │          │ to run once finished              │
+// ├────────────┼──────────┼───────────────────────────────────┤
+// │  Jumping   │ "Jump"   │ Jump animation, returns    (Hold  │
+// │            │          │ to run when landed          Last) │
+// ├────────────┼──────────┼───────────────────────────────────┤
+// │  Double    │ "Jump"   │ Air jump, restarts the     (Hold  │
+// │  Jumping   │          │ animation                   Last) │
+// ├────────────┼──────────┼───────────────────────────────────┤
+// │  Hurt      │ "Hurt"   │ Knockback, back to run     (Hold  │
+// │            │          │ once finished and landed    Last) │
+// ├────────────┼──────────┼───────────────────────────────────┤
+// │  Dead      │ "Dead"   │ Falls, holds the last      (Hold  │
+// │            │          │ frame for good              Last) │
+// └────────────┴──────────┴───────────────────────────────────┘
+// - frame counts are NOT listed here, they come from the loaded Sheet
+// - (modes) are the states' own, see SpriteState::mode()
 
 /// SpriteMetaData
 /// - frame_count - private initialization via new(frame_count)
-/// - animation_speed
+/// - animation_speed (ticks per frame, for frames without a duration)
+/// - frame_durations (milliseconds per frame, borrowed from the
+///   AnimationSet, one per frame or empty)
+/// - speed_multiplier (scales every duration, 2.0 plays twice as fast)
+/// - mode (LoopMode, the override file's or the state's)
 /// - default_size (bounding box)
-pub struct SpriteMetaData {
+#[derive(Debug, Copy, Clone)]
+pub struct SpriteMetaData<'a> {
     frame_count: NonZeroU8, // private, must be init with new()
     pub animation_speed: u8,
+    pub frame_durations: &'a [u16],
+    pub speed_multiplier: f32,
+    pub mode: LoopMode,
     pub default_size: Size,
 }
 
-impl SpriteMetaData {
+impl SpriteMetaData<'static> {
     pub fn new(frame_count: u8) -> Self {
         Self {
             frame_count: NonZeroU8::new(frame_count).expect("frame_count must be > 0"),
             animation_speed: FRAME_TICK_RATE,
+            frame_durations: &[],
+            speed_multiplier: 1.0,
+            mode: LoopMode::Loop,
             default_size: DEFAULT_SPRITE_SIZE,
         }
     }
 }
 
+impl SpriteMetaData<'_> {
+    pub fn frame_count(&self) -> u8 {
+        self.frame_count.get()
+    }
+
+    /// How long frame `index` stays on screen at normal speed, in
+    /// microseconds : its listed duration, or animation_speed ticks when the
+    /// animation lists none
+    pub fn frame_us(&self, index: u8) -> u32 {
+        match self.frame_durations.get(usize::from(index)) {
+            Some(ms) => u32::from(*ms) * 1000,
+            // no durations, validate_sheet rejects lists short of a frame
+            None => u32::from(self.animation_speed) * TICK_US,
+        }
+    }
+}
+
 pub trait SpriteState {
     // Required methods - must be implemented
     // TODO: Explain is it because we left these blank that they MUST be impl?
+    /// Animation name in the sheet, the prefix of its frame keys
     fn name() -> &'static str;
-    fn metadata() -> SpriteMetaData;
 
     // Default methods - shared implementation
-    fn frame_key(frame: u8) -> String {
-        format!("{} ({}).png", Self::name(), frame)
-    }
-
-    fn total_frames() -> u8 {
-        // Convert NonZeroU8 to u8 before mathing
-        Self::metadata().frame_count.get() * Self::metadata().animation_speed - 1
+    /// How the state plays its animation, unless the override file says
+    /// otherwise
+    fn mode() -> LoopMode {
+        LoopMode::Loop
     }
 
-    fn current_frame_name(frame: u8) -> String {
-        format!("{} ({}).png", Self::name(), (frame / FRAME_TICK_RATE + 1))
+    /// Metadata read from the animation discovered in the sheet
+    /// - falls back to a single frame when the sheet has no such animation
+    fn metadata(animations: &AnimationSet) -> SpriteMetaData<'_> {
+        match animations.get(Self::name()) {
+            Some(animation) => {
+                let frame_count = u8::try_from(animation.frame_count()).unwrap_or(u8::MAX);
+                SpriteMetaData {
+                    animation_speed: animation.speed,
+                    frame_durations: &animation.durations,
+                    speed_multiplier: animation.speed_multiplier,
+                    mode: animation.mode.unwrap_or(Self::mode()),
+                    default_size: animation.size,
+                    ..SpriteMetaData::new(frame_count.max(1))
+                }
+            }
+            None => SpriteMetaData {
+                mode: Self::mode(),
+                ..SpriteMetaData::new(1)
+            },
+        }
     }
 }
 
@@ -124,25 +213,23 @@ pub struct Running;
 pub struct Sliding;
 #[derive(Debug, Copy, Clone)]
 pub struct Jumping;
+#[derive(Debug, Copy, Clone)]
+pub struct DoubleJumping;
+#[derive(Debug, Copy, Clone)]
+pub struct Hurt;
+#[derive(Debug, Copy, Clone)]
+pub struct Dead;
 
 impl SpriteState for Idle {
     fn name() -> &'static str {
         "Idle"
     }
-
-    fn metadata() -> SpriteMetaData {
-        SpriteMetaData::new(IDLE_FRAMES)
-    }
 }
 
 impl SpriteState for Running {
     fn name() -> &'static str {
         "Run"
     }
-
-    fn metadata() -> SpriteMetaData {
-        SpriteMetaData::new(RUN_FRAMES)
-    }
 }
 
 impl SpriteState for Sliding {
@@ -150,8 +237,8 @@ impl SpriteState for Sliding {
         "Slide"
     }
 
-    fn metadata() -> SpriteMetaData {
-        SpriteMetaData::new(SLIDE_FRAMES)
+    fn mode() -> LoopMode {
+        LoopMode::Once
     }
 }
 
@@ -160,7 +247,59 @@ impl SpriteState for Jumping {
         "Jump"
     }
 
-    fn metadata() -> SpriteMetaData {
-        SpriteMetaData::new(JUMP_FRAMES)
+    fn mode() -> LoopMode {
+        LoopMode::HoldLast
+    }
+}
+
+// shares the sheet's "Jump" frames, played again from the first one
+impl SpriteState for DoubleJumping {
+    fn name() -> &'static str {
+        "Jump"
+    }
+
+    fn mode() -> LoopMode {
+        LoopMode::HoldLast
+    }
+}
+
+impl SpriteState for Hurt {
+    fn name() -> &'static str {
+        "Hurt"
+    }
+
+    fn mode() -> LoopMode {
+        LoopMode::HoldLast
     }
 }
+
+impl SpriteState for Dead {
+    fn name() -> &'static str {
+        "Dead"
+    }
+
+    fn mode() -> LoopMode {
+        LoopMode::HoldLast
+    }
+}
+
+/// Test sheet : frames 1 to 4 of each animation in `names`, all 100x120,
+/// compiled with `overrides`
+#[cfg(test)]
+pub(crate) fn test_animations(names: &[&str], overrides: &AnimationOverrides) -> AnimationSet {
+    use crate::engine::{Cell, Sheet, SheetRect};
+    let frames = names
+        .iter()
+        .flat_map(|name| (1..=4).map(move |i| format!("{} ({}).png", name, i)))
+        .map(|key| {
+            let frame = SheetRect {
+                x: 0,
+                y: 0,
+                w: 100,
+                h: 120,
+            };
+            (key, Cell::new(frame))
+        })
+        .collect();
+    AnimationSet::from_sheet(&Sheet { frames }, overrides)
+}
@@ -0,0 +1,336 @@
+/// Animation : plays one animation of a state, tick by tick
+/// - stores how far in it is, the frame to draw and whether it's done,
+///   so drawing and state logic never redo the wraparound maths
+/// - Copy and tiny, it lives in RedHatBoyContext and restarts on every
+///   state transition
+///
+/// TABLE: frame shown per step, 4 frames
+/// ┌─ mode ────┬─ steps 0.. ───────────────┬─ finished() from step ─┐
+/// │ Loop      │ 0 1 2 3 0 1 2 3 0 ...     │ 4 (one pass), stays    │
+/// │ Once      │ 0 1 2 3 0 0 0 ...         │ 4, back on the first   │
+/// │ HoldLast  │ 0 1 2 3 3 3 3 ...         │ 4, frozen on the last  │
+/// │ Reverse   │ 3 2 1 0 3 2 1 0 3 ...     │ 4 (one pass), stays    │
+/// │ PingPong  │ 0 1 2 3 2 1 0 1 2 ...     │ 6 (there and back)     │
+/// └───────────┴───────────────────────────┴────────────────────────┘
+/// finished() means "played all the way through at least once" : looping
+/// modes keep animating after it, Once and HoldLast stop
+///
+/// Time based : a step lasts its frame's duration, not a number of updates
+/// - durations come from data (SpriteMetaData::frame_us), `speed` ticks
+///   per frame for animations that list none
+/// - elapsed time is scaled by the data's speed_multiplier and by rate(),
+///   which states change at runtime (Running plays faster the faster he runs)
+/// - counted in whole microseconds, so the default durations land exactly
+///   on update boundaries and replays stay bit for bit the same
+///
+/// entered() is the frame that came on screen this update, for per-frame
+/// animation events :
+/// - once per frame shown, so a single frame loop only enters it once
+/// - not when Once comes back to rest on its first frame
+/// - a frame shorter than an update can be skipped, and never entered
+use crate::engine::FRAME_SIZE;
+use crate::sprite::{LoopMode, SpriteMetaData};
+
+/// One fixed update in microseconds
+pub const TICK_US: u32 = (FRAME_SIZE * 1000.0) as u32;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Animation {
+    /// microseconds played, kept inside 2 passes for looping modes
+    elapsed_us: u32,
+    rate: f32,
+    frame: u8,
+    finished: bool,
+    // set by the first update(), which enters the first frame shown
+    started: bool,
+    entered: Option<u8>,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Self {
+            elapsed_us: 0,
+            rate: 1.0,
+            frame: 0,
+            finished: false,
+            started: false,
+            entered: None,
+        }
+    }
+}
+
+impl Animation {
+    /// From the first frame at normal rate, nothing played yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Frame index to draw, into the animation's frames
+    /// - 0 until the first update(), which every state runs before drawing
+    pub fn frame(&self) -> u8 {
+        self.frame
+    }
+
+    pub fn finished(&self) -> bool {
+        self.finished
+    }
+
+    /// Frame that came on screen in the last update(), None if it's still
+    /// showing the same one
+    pub fn entered(&self) -> Option<u8> {
+        self.entered
+    }
+
+    pub fn rate(&self) -> f32 {
+        self.rate
+    }
+
+    /// Playback rate on top of the data's multiplier, 2.0 plays twice as
+    /// fast and 0.0 pauses, takes effect from the next update
+    pub fn set_rate(&mut self, rate: f32) {
+        self.rate = rate.max(0.0);
+    }
+
+    /// One tick further, playing `metadata`'s frames, durations and mode
+    pub fn update(&mut self, metadata: &SpriteMetaData) {
+        let scale = self.rate * metadata.speed_multiplier.max(0.0);
+        // float -> int casts saturate, a huge rate can't wrap around
+        self.elapsed_us = self
+            .elapsed_us
+            .saturating_add((TICK_US as f32 * scale).round() as u32);
+        let pass = pass_us(metadata);
+        if self.elapsed_us >= pass {
+            self.finished = true;
+            match metadata.mode {
+                // stop counting, the frame no longer changes
+                LoopMode::Once | LoopMode::HoldLast => self.elapsed_us = pass,
+                // keep the same phase, a pass later at most
+                LoopMode::Loop | LoopMode::Reverse | LoopMode::PingPong if pass > 0 => {
+                    self.elapsed_us = pass + (self.elapsed_us - pass) % pass;
+                }
+                _ => self.elapsed_us = 0,
+            }
+        }
+        let previous = self.frame;
+        self.frame = frame_at(metadata, self.elapsed_us, pass);
+        let resting = metadata.mode == LoopMode::Once && self.elapsed_us >= pass;
+        let changed = !self.started || self.frame != previous;
+        self.entered = (changed && !resting).then_some(self.frame);
+        self.started = true;
+    }
+}
+
+/// Frames in the order one pass plays them
+fn steps(metadata: &SpriteMetaData) -> impl Iterator<Item = u8> {
+    let frames = metadata.frame_count();
+    let last = frames - 1;
+    let mode = metadata.mode;
+    let count = match mode {
+        // there and back, the end frames aren't repeated
+        LoopMode::PingPong if frames > 1 => u16::from(frames) * 2 - 2,
+        _ => u16::from(frames),
+    };
+    (0..count).map(move |step| {
+        // a PingPong step can pass 255, its mirrored frame can't
+        let mirrored = |step: u16| u8::try_from(u16::from(last) * 2 - step).unwrap_or(last);
+        match mode {
+            LoopMode::Reverse => last - step as u8,
+            LoopMode::PingPong if step > u16::from(last) => mirrored(step),
+            _ => step as u8,
+        }
+    })
+}
+
+/// Length of one pass
+fn pass_us(metadata: &SpriteMetaData) -> u32 {
+    steps(metadata)
+        .map(|frame| metadata.frame_us(frame))
+        .fold(0, u32::saturating_add)
+}
+
+fn frame_at(metadata: &SpriteMetaData, elapsed_us: u32, pass: u32) -> u8 {
+    if elapsed_us >= pass {
+        match metadata.mode {
+            LoopMode::Once => return 0,
+            LoopMode::HoldLast => return metadata.frame_count() - 1,
+            _ => {}
+        }
+    }
+    let mut into_pass = elapsed_us.checked_rem(pass).unwrap_or(0);
+    let mut shown = 0;
+    for frame in steps(metadata) {
+        let duration = metadata.frame_us(frame);
+        shown = frame;
+        if into_pass < duration {
+            break;
+        }
+        into_pass -= duration;
+    }
+    shown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(frames: u8, speed: u8, mode: LoopMode) -> SpriteMetaData<'static> {
+        SpriteMetaData {
+            animation_speed: speed,
+            mode,
+            ..SpriteMetaData::new(frames)
+        }
+    }
+
+    /// (frame, finished) after each of `ticks` updates
+    fn play(metadata: &SpriteMetaData, ticks: usize) -> Vec<(u8, bool)> {
+        let mut animation = Animation::new();
+        (0..ticks)
+            .map(|_| {
+                animation.update(metadata);
+                (animation.frame(), animation.finished())
+            })
+            .collect()
+    }
+
+    fn frames(metadata: &SpriteMetaData, ticks: usize) -> Vec<u8> {
+        play(metadata, ticks)
+            .into_iter()
+            .map(|(frame, _)| frame)
+            .collect()
+    }
+
+    #[test]
+    fn test_every_mode_matches_the_table() {
+        let frames_for = |mode| frames(&metadata(4, 1, mode), 9);
+        assert_eq!(frames_for(LoopMode::Loop), [1, 2, 3, 0, 1, 2, 3, 0, 1]);
+        assert_eq!(frames_for(LoopMode::Once), [1, 2, 3, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(frames_for(LoopMode::HoldLast), [1, 2, 3, 3, 3, 3, 3, 3, 3]);
+        assert_eq!(frames_for(LoopMode::Reverse), [2, 1, 0, 3, 2, 1, 0, 3, 2]);
+        assert_eq!(frames_for(LoopMode::PingPong), [1, 2, 3, 2, 1, 0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_finished_after_one_full_pass() {
+        let finished_at = |mode| {
+            play(&metadata(4, 2, mode), 20)
+                .iter()
+                .position(|(_, finished)| *finished)
+                .map(|update| update + 1)
+        };
+        assert_eq!(finished_at(LoopMode::Loop), Some(8));
+        assert_eq!(finished_at(LoopMode::Once), Some(8));
+        assert_eq!(finished_at(LoopMode::HoldLast), Some(8));
+        assert_eq!(finished_at(LoopMode::Reverse), Some(8));
+        assert_eq!(finished_at(LoopMode::PingPong), Some(12));
+        // and stays finished
+        assert!(play(&metadata(4, 2, LoopMode::Loop), 20)[8..]
+            .iter()
+            .all(|(_, finished)| *finished));
+    }
+
+    #[test]
+    fn test_long_loops_keep_their_phase() {
+        let loop_mode = metadata(3, 2, LoopMode::PingPong);
+        let played = frames(&loop_mode, 70_000);
+        let period = 4 * 2;
+        assert_eq!(played[69_999], played[69_999 - period]);
+        assert_eq!(frames(&metadata(1, 3, LoopMode::PingPong), 5), [0; 5]);
+    }
+
+    #[test]
+    fn test_entered_once_per_frame_shown() {
+        let entered = |metadata: &SpriteMetaData| {
+            let mut animation = Animation::new();
+            (0..10)
+                .map(|_| {
+                    animation.update(metadata);
+                    animation.entered()
+                })
+                .collect::<Vec<_>>()
+        };
+        let (none, some) = (None, Some);
+        assert_eq!(
+            entered(&metadata(3, 2, LoopMode::Loop)),
+            [
+                some(0),
+                some(1),
+                none,
+                some(2),
+                none,
+                some(0),
+                none,
+                some(1),
+                none,
+                some(2)
+            ]
+        );
+        // resting back on the first frame isn't entering it
+        assert_eq!(
+            entered(&metadata(2, 2, LoopMode::Once)),
+            [
+                some(0),
+                some(1),
+                none,
+                none,
+                none,
+                none,
+                none,
+                none,
+                none,
+                none
+            ]
+        );
+        assert_eq!(
+            entered(&metadata(1, 1, LoopMode::Loop)),
+            [
+                some(0),
+                none,
+                none,
+                none,
+                none,
+                none,
+                none,
+                none,
+                none,
+                none
+            ]
+        );
+    }
+
+    #[test]
+    fn test_frames_last_their_listed_duration() {
+        // ~2 updates then ~4 (a tick is 16.666ms), the third frame doesn't
+        // list one and falls back to `speed`, 1 update
+        let listed = SpriteMetaData {
+            frame_durations: &[33, 66],
+            ..metadata(3, 1, LoopMode::Loop)
+        };
+        assert_eq!(frames(&listed, 8), [0, 1, 1, 1, 1, 2, 0, 0]);
+    }
+
+    #[test]
+    fn test_rate_and_multiplier_scale_time() {
+        let normal = metadata(4, 2, LoopMode::Loop);
+        assert_eq!(frames(&normal, 4), [0, 1, 1, 2]);
+
+        let doubled = SpriteMetaData {
+            speed_multiplier: 2.0,
+            ..normal
+        };
+        assert_eq!(frames(&doubled, 4), [1, 2, 3, 0]);
+
+        // changed while playing, from the next update on
+        let mut animation = Animation::new();
+        animation.update(&normal);
+        animation.set_rate(2.0);
+        animation.update(&normal);
+        assert_eq!(animation.frame(), 1);
+        animation.update(&normal);
+        assert_eq!(animation.frame(), 2, "a whole frame per update");
+        animation.set_rate(0.0);
+        animation.update(&normal);
+        assert_eq!(animation.frame(), 2, "paused");
+        animation.set_rate(-1.0);
+        assert_eq!(animation.rate(), 0.0, "no playing backwards");
+    }
+}
@@ -1,27 +1,58 @@
-#[cfg(debug_assertions)]
-use crate::engine::DebugDraw;
-use crate::engine::{Point, Rect, Renderer, Sheet, Size};
-use crate::sprite;
-use crate::sprite::state::{IsJumping, IsSliding, RedHatBoyContext, RedHatBoyState};
-use crate::sprite::{Idle, Jumping, Running, Sliding, SpriteState};
+use crate::engine::{Point, Rect, Size};
+use crate::sprite::machine::{StateClips, Transition};
+use crate::sprite::state::{
+    AirJump, Facing, IsHurt, IsJumping, IsRunning, IsSliding, RedHatBoyContext, RedHatBoyState,
+    TookDamage,
+};
+use crate::sprite::{
+    AnimationEvent, AnimationSet, Dead, DoubleJumping, Hurt, Idle, Jumping, Running, Sliding,
+    SpriteState,
+};
+use std::collections::HashSet;
 use std::rc::Rc;
-use web_sys::HtmlImageElement;
 
 /// ELI5:
 /// ┌──────────────── State Transition Flow ──────────────────┐
 /// │  From State  →  Event   →  To State                     │
 /// ├─────────────────────────────────────────────────────────┤
 /// │  Idle        →  Run     →  Running                      │
+/// │  Running / (Double)Jumping : Run → steer, Brake → slow  │
 /// │  Running     →  Slide   →  Sliding                      │
 /// │  Running     →  Jump    →  Jumping                      │
+/// │  Running     →  CoyoteJump →  Jumping                   │
+/// │  Idle        →  CoyoteJump →  Jumping (just braked)     │
+/// │  Sliding     →  CoyoteJump →  Jumping (slide cut short) │
+/// │  (Double)Jumping → Jump → DoubleJumping (air jump left) │
+/// │  (Double)Jumping → ReleaseJump → same, rising slower    │
+/// │  Idle / Running / Sliding / (Double)Jumping :           │
+/// │              →  TakeDamage → Hurt (health left)         │
+/// │              →  TakeDamage → Dead (last hit point)      │
+/// │  any but Dead → KnockOut →  Dead                        │
 /// │  -------        ------                                  │
+/// │  Running     →  Update  →  Idle (braked to a stop)      │
 /// │  Sliding     →  Update  →  Running (when complete)      │
-/// │  Jumping     →  Update  →  Running (when landed)        │
+/// │  (Double)Jumping → Update → Running (when landed)       │
+/// │  Hurt        →  Update  →  Running (played + landed)    │
+/// │  Dead        →  Update  →  Dead (holds the last frame)  │
 /// └─────────────────────────────────────────────────────────┘
+#[derive(Debug, Copy, Clone)]
 pub enum Event {
-    Run,
+    /// run input held, facing the way it points
+    Run(Facing),
+    /// no run input, slow down to a stop
+    Brake,
     Slide,
+    /// off the ground when running, an air jump when in the air
     Jump,
+    /// jump input not held, a rising jump is cut short
+    ReleaseJump,
+    /// a jump pressed inside the coyote window, just after the boy could
+    /// last jump : braked to a stop or started a slide (see engine::buffer)
+    CoyoteJump,
+    /// hit by something, ignored during invulnerability frames
+    TakeDamage,
+    /// dead on the spot, whatever the health
+    KnockOut,
     Update,
 }
 
@@ -56,43 +87,187 @@ pub enum Event {
 // | Box<T>               | - Has attachments       | Contains pointers       |
 // +----------------------+-------------------------+-------------------------+
 //
-#[derive(Debug, Copy, Clone)]
-enum RedHatBoyStateMachine {
-    Idle(RedHatBoyState<Idle>),
-    Running(RedHatBoyState<Running>),
-    Sliding(RedHatBoyState<Sliding>),
-    Jumping(RedHatBoyState<Jumping>),
+state_machine! {
+    #[derive(Debug, Copy, Clone)]
+    enum RedHatBoyStateMachine: RedHatBoyState {
+        context: RedHatBoyContext,
+        event: Event,
+        env: AnimationSet,
+        states: {
+            Idle(Idle),
+            Running(Running),
+            Sliding(Sliding),
+            Jumping(Jumping),
+            DoubleJumping(DoubleJumping),
+            Hurt(Hurt),
+            Dead(Dead),
+        },
+        // Consumption vs reference ----------------------------------------
+        // [Consume] when:
+        // - operation fundamentally transforms the object (state transition)
+        // - ensure old state can't be accessed
+        // - operation needs exclusive access to all fields
+        // [Reference] when:
+        // - operation only needs to read data
+        // - multiple references might be needed
+        // - operation makes temporary modification
+        //
+        // every transition CONSUMES the typed state and returns a new one
+        // - the `state` passed in is moved -> no longer accessible
+        // - unlisted (state, event) pairs keep the current state, e.g.
+        // trying to Jump while Sliding
+        transitions: {
+            Idle, Event::Run(facing) => [Running] |state, animations| {
+                state.run(facing, Self::get_size_for_state::<Running>(animations))
+            },
+            Running, Event::Run(facing) => [Running] |state, _animations| state.steer(facing),
+            Running, Event::Brake => [Running] |state, _animations| state.brake(),
+            Jumping, Event::Run(facing) => [Jumping] |state, _animations| state.steer(facing),
+            Jumping, Event::Brake => [Jumping] |state, _animations| state.brake(),
+            DoubleJumping, Event::Run(facing) => [DoubleJumping] |state, _animations| {
+                state.steer(facing)
+            },
+            DoubleJumping, Event::Brake => [DoubleJumping] |state, _animations| state.brake(),
+            Running, Event::Slide => [Sliding] |state, animations| {
+                state.slide(Self::get_size_for_state::<Sliding>(animations))
+            },
+            Running, Event::Jump | Event::CoyoteJump => [Jumping] |state, animations| {
+                state.jump(Self::get_size_for_state::<Jumping>(animations))
+            },
+            Idle, Event::CoyoteJump => [Jumping] |state, animations| {
+                state.jump(Self::get_size_for_state::<Jumping>(animations))
+            },
+            Sliding, Event::CoyoteJump => [Jumping] |state, animations| {
+                state.stand().jump(Self::get_size_for_state::<Jumping>(animations))
+            },
+            Jumping, Event::Jump => [DoubleJumping if "air jump left", Jumping if "none left"]
+                |state, animations| {
+                    state.air_jump(Self::get_size_for_state::<DoubleJumping>(animations))
+                },
+            DoubleJumping, Event::Jump => [DoubleJumping] |state, animations| {
+                state.air_jump(Self::get_size_for_state::<DoubleJumping>(animations))
+            },
+            Jumping, Event::ReleaseJump => [Jumping] |state, _animations| state.release_jump(),
+            DoubleJumping, Event::ReleaseJump => [DoubleJumping] |state, _animations| {
+                state.release_jump()
+            },
+            Idle, Event::TakeDamage =>
+                [Hurt if "health left", Dead if "last hit point", Idle if "invulnerable"]
+                |state, animations| {
+                    let (hurt, dead) = Self::damage_sizes(animations);
+                    state.take_damage(hurt, dead)
+                },
+            Running, Event::TakeDamage =>
+                [Hurt if "health left", Dead if "last hit point", Running if "invulnerable"]
+                |state, animations| {
+                    let (hurt, dead) = Self::damage_sizes(animations);
+                    state.take_damage(hurt, dead)
+                },
+            Sliding, Event::TakeDamage =>
+                [Hurt if "health left", Dead if "last hit point", Sliding if "invulnerable"]
+                |state, animations| {
+                    let (hurt, dead) = Self::damage_sizes(animations);
+                    state.take_damage(hurt, dead)
+                },
+            Jumping, Event::TakeDamage =>
+                [Hurt if "health left", Dead if "last hit point", Jumping if "invulnerable"]
+                |state, animations| {
+                    let (hurt, dead) = Self::damage_sizes(animations);
+                    state.take_damage(hurt, dead)
+                },
+            DoubleJumping, Event::TakeDamage =>
+                [Hurt if "health left", Dead if "last hit point", DoubleJumping if "invulnerable"]
+                |state, animations| {
+                    let (hurt, dead) = Self::damage_sizes(animations);
+                    state.take_damage(hurt, dead)
+                },
+            Idle, Event::KnockOut => [Dead] |state, animations| {
+                state.knock_out(Self::dead_size(animations))
+            },
+            Running, Event::KnockOut => [Dead] |state, animations| {
+                state.knock_out(Self::dead_size(animations))
+            },
+            Sliding, Event::KnockOut => [Dead] |state, animations| {
+                state.knock_out(Self::dead_size(animations))
+            },
+            Jumping, Event::KnockOut => [Dead] |state, animations| {
+                state.knock_out(Self::dead_size(animations))
+            },
+            DoubleJumping, Event::KnockOut => [Dead] |state, animations| {
+                state.knock_out(Self::dead_size(animations))
+            },
+            Hurt, Event::KnockOut => [Dead] |state, animations| {
+                state.knock_out(Self::dead_size(animations))
+            },
+            Idle, Event::Update => [Idle] |state, animations| state.update(animations),
+            Running, Event::Update => [Running if "moving", Idle if "stopped"]
+                |state, animations| state.update(animations),
+            Sliding, Event::Update => [Sliding if "playing", Running if "slide over"]
+                |state, animations| state.update(animations),
+            Jumping, Event::Update => [Jumping if "in the air", Running if "landed"]
+                |state, animations| state.update(animations),
+            DoubleJumping, Event::Update => [DoubleJumping if "in the air", Running if "landed"]
+                |state, animations| state.update(animations),
+            Hurt, Event::Update => [Hurt if "knocked back", Running if "played and landed"]
+                |state, animations| state.update(animations),
+            Dead, Event::Update => [Dead] |state, animations| state.update(animations),
+        },
+    }
 }
 
-impl From<RedHatBoyState<sprite::Idle>> for RedHatBoyStateMachine {
-    fn from(state: RedHatBoyState<sprite::Idle>) -> Self {
-        RedHatBoyStateMachine::Idle(state)
+impl From<IsHurt> for RedHatBoyStateMachine {
+    fn from(is_hurt: IsHurt) -> Self {
+        match is_hurt {
+            IsHurt::Done(running_state) => running_state.into(),
+            IsHurt::InProgress(hurt_state) => hurt_state.into(),
+        }
     }
 }
 
-impl From<RedHatBoyState<sprite::Running>> for RedHatBoyStateMachine {
-    fn from(state: RedHatBoyState<sprite::Running>) -> Self {
-        RedHatBoyStateMachine::Running(state)
+// generic over the state damage was taken in, an invulnerable boy stays in it
+impl<S> From<TookDamage<S>> for RedHatBoyStateMachine
+where
+    RedHatBoyState<S>: Into<RedHatBoyStateMachine>,
+{
+    fn from(took_damage: TookDamage<S>) -> Self {
+        match took_damage {
+            TookDamage::Hurt(hurt_state) => hurt_state.into(),
+            TookDamage::KnockedOut(dead_state) => dead_state.into(),
+            TookDamage::Invulnerable(state) => state.into(),
+        }
     }
 }
 
-impl From<RedHatBoyState<sprite::Sliding>> for RedHatBoyStateMachine {
-    fn from(state: RedHatBoyState<sprite::Sliding>) -> Self {
-        RedHatBoyStateMachine::Sliding(state)
+// generic over Jumping / DoubleJumping, like TookDamage
+impl<S> From<IsJumping<S>> for RedHatBoyStateMachine
+where
+    RedHatBoyState<S>: Into<RedHatBoyStateMachine>,
+{
+    fn from(is_jumping: IsJumping<S>) -> Self {
+        match is_jumping {
+            IsJumping::Done(running_state) => running_state.into(),
+            IsJumping::InProgress(jumping_state) => jumping_state.into(),
+        }
     }
 }
 
-impl From<RedHatBoyState<sprite::Jumping>> for RedHatBoyStateMachine {
-    fn from(state: RedHatBoyState<sprite::Jumping>) -> Self {
-        RedHatBoyStateMachine::Jumping(state)
+impl<S> From<AirJump<S>> for RedHatBoyStateMachine
+where
+    RedHatBoyState<S>: Into<RedHatBoyStateMachine>,
+{
+    fn from(air_jump: AirJump<S>) -> Self {
+        match air_jump {
+            AirJump::Jumped(double_jumping_state) => double_jumping_state.into(),
+            AirJump::NoneLeft(state) => state.into(),
+        }
     }
 }
 
-impl From<IsJumping> for RedHatBoyStateMachine {
-    fn from(is_jumping: IsJumping) -> Self {
-        match is_jumping {
-            IsJumping::Done(running_state) => running_state.into(),
-            IsJumping::InProgress(jumping_state) => jumping_state.into(),
+impl From<IsRunning> for RedHatBoyStateMachine {
+    fn from(is_running: IsRunning) -> Self {
+        match is_running {
+            IsRunning::Moving(running_state) => running_state.into(),
+            IsRunning::Stopped(idle_state) => idle_state.into(),
         }
     }
 }
@@ -110,88 +285,49 @@ impl From<IsSliding> for RedHatBoyStateMachine {
 }
 
 impl RedHatBoyStateMachine {
-    // Consumption vs reference ----------------------------------------------
-    // [Consume] when:
-    // - operation fundamentally transforms the object (state transition)
-    // - ensure old state can't be accessed
-    // - operation needs exclusive access to all fields
-    // [Reference] when:
-    // - operation only needs to read data
-    // - multiple references might be needed
-    // - operation makes temporary modification
-    //
-    // CONSUMING self (state instance) and returning a new Self (state)
-    // - the `self` passed in as an argument is moved -> no longer accessible
-    // - &mut self would return a reference
-    fn transition(self, event: Event, sheet: Option<&Sheet>) -> Self {
-        use RedHatBoyStateMachine::*;
-        match (self, event) {
-            (Idle(state), Event::Run) => {
-                let size = Self::get_size_for_state::<crate::sprite::Running>(
-                    sheet.expect("Sheet not found"),
-                );
-                state.run(size).into()
-            }
-            (Running(state), Event::Slide) => {
-                let size = Self::get_size_for_state::<crate::sprite::Sliding>(
-                    sheet.expect("Sheet not found"),
-                );
-                state.slide(size).into()
-            }
-            (Running(state), Event::Jump) => {
-                let size = Self::get_size_for_state::<crate::sprite::Jumping>(
-                    sheet.expect("Sheet not found"),
-                );
-                state.jump(size).into()
-            }
-            (Idle(state), Event::Update) => state.update().into(),
-            (Running(state), Event::Update) => state.update().into(),
-            (Sliding(state), Event::Update) => state.update().into(),
-            (Jumping(state), Event::Update) => state.update().into(),
-            // This default arm is necessary because :
-            // - handles invalid state transitions(e.g. trying to Jump while Sliding)
-            // - maintains the current state for unsupported transitions
-            // - defensive programming practice for future state additions
-            _ => self,
-        }
+    /// Bounding box size comes from the first frame of the state's animation
+    /// - a sheet without it falls back to DEFAULT_SPRITE_SIZE, quietly :
+    ///   validate_sheet reports the MissingAnimation at load
+    fn get_size_for_state<S: SpriteState>(animations: &AnimationSet) -> Size {
+        S::metadata(animations).default_size
     }
 
-    fn get_size_for_state<S: SpriteState>(sheet: &Sheet) -> Size {
-        let frame_key = S::frame_key(1);
-        sheet
-            .frames
-            .get(&frame_key)
-            .map(|cell| Size {
-                width: cell.frame.w,
-                height: cell.frame.h,
-            })
-            .unwrap_or_else(|| {
-                log!("Warning: Missing sprite data for state: {}", S::name());
-                S::metadata().default_size
-            })
+    fn dead_size(animations: &AnimationSet) -> Size {
+        Self::get_size_for_state::<crate::sprite::Dead>(animations)
     }
 
-    fn update(self) -> Self {
-        // updates() are transitions(Event::Update,) because :
-        // - unified state transition mechanism
-        // - consistend handling of state changes
-        // - simpler state machine logic
-        self.transition(Event::Update, None)
+    /// (Hurt, Dead) sizes : which one is used depends on the health left
+    fn damage_sizes(animations: &AnimationSet) -> (Size, Size) {
+        (
+            Self::get_size_for_state::<crate::sprite::Hurt>(animations),
+            Self::dead_size(animations),
+        )
     }
 
-    // TODO: Find out if this can be simplified with a macro?
-    fn context(&self) -> &RedHatBoyContext {
-        use RedHatBoyStateMachine::*;
+    /// A running boy can jump, a jumping one while he has air jumps left
+    fn can_jump(&self) -> bool {
         match self {
-            Idle(state) => state.context(),
-            Running(state) => state.context(),
-            Sliding(state) => state.context(),
-            Jumping(state) => state.context(),
+            RedHatBoyStateMachine::Running(_) => true,
+            RedHatBoyStateMachine::Jumping(_) | RedHatBoyStateMachine::DoubleJumping(_) => {
+                self.context().air_jumps > 0
+            }
+            _ => false,
         }
     }
+
+    fn update(self, animations: &AnimationSet) -> Self {
+        // updates() are transitions(Event::Update,) because :
+        // - unified state transition mechanism
+        // - consistend handling of state changes
+        // - simpler state machine logic
+        self.transition(Event::Update, animations)
+    }
 }
 
-pub struct RedHatBoy {
+/// The simulation half of the boy : state machine, physics, animation timing
+/// and events, with no web_sys in sight so it runs under a native #[test]
+/// - RedHatBoy adds the image and draws it
+pub struct RedHatBoySimulation {
     state: RedHatBoyStateMachine,
     // update to reference to eliminate cloning for memory perf improvement :
     // ELI5:
@@ -217,77 +353,215 @@ pub struct RedHatBoy {
     // │ │  Rc<Sheet>   │      │             │                           │
     // │ └──────────────┘      └─────────────┘                           │
     // └─────────────────────────────────────────────────────────────────┘
-    sheet: Rc<Sheet>,
-    image: HtmlImageElement,
+    // - the Sheet is now compiled into an AnimationSet at load time, frame
+    // counts, playback settings and source rects live there and are shared
+    // the same way
+    animations: Rc<AnimationSet>,
+    // ids resolved once in new(), so draw() never looks up a name
+    clips: StateClips,
+    // set on the transition into Dead, until the game takes it
+    died: bool,
+    // event names the game listens for, and those fired since it last took them
+    subscribed: HashSet<String>,
+    events: Vec<AnimationEvent>,
 }
 
-/// RedHatBoy
+/// RedHatBoySimulation
 /// - update() -> statemachine::update()
 /// - handle state transition -> RedHatBoyStateMachine::transition()
-///     - run_right() ...
-impl RedHatBoy {
-    pub fn new(sheet: Sheet, image: HtmlImageElement) -> Self {
-        let sheet = Rc::new(sheet);
+///     - run() ...
+impl RedHatBoySimulation {
+    pub fn new(animations: AnimationSet) -> Self {
+        let animations = Rc::new(animations);
         let bounding_box_size =
-            RedHatBoyStateMachine::get_size_for_state::<crate::sprite::Idle>(&sheet);
-        RedHatBoy {
+            RedHatBoyStateMachine::get_size_for_state::<crate::sprite::Idle>(&animations);
+        RedHatBoySimulation {
             state: RedHatBoyStateMachine::Idle(RedHatBoyState::new(bounding_box_size)),
-            sheet,
-            image,
+            clips: StateClips::resolve(&RedHatBoyStateMachine::sprite_names(), &animations),
+            animations,
+            died: false,
+            subscribed: HashSet::new(),
+            events: Vec::new(),
+        }
+    }
+
+    /// Animations every RedHatBoy state will request from the sheet
+    /// - once each, Jumping and DoubleJumping share "Jump"
+    pub fn required_animations() -> Vec<&'static str> {
+        let mut names = RedHatBoyStateMachine::sprite_names();
+        let mut seen = HashSet::new();
+        names.retain(|name| seen.insert(*name));
+        names
+    }
+
+    /// Every state, in the order the machine declares them
+    pub fn states() -> &'static [&'static str] {
+        RedHatBoyStateMachine::STATES
+    }
+
+    /// (state, event, target, guard) rows, straight from the `transitions` table
+    /// - `cargo run --bin state_diagram` renders them to docs/
+    pub fn transition_table() -> &'static [Transition] {
+        RedHatBoyStateMachine::TRANSITIONS
+    }
+
+    /// Swap in re-loaded animations without touching the current state
+    /// - used by development hot reload, see RedHatBoy::reload
+    #[cfg(debug_assertions)]
+    pub fn reload_animations(&mut self, animations: AnimationSet) {
+        self.clips = StateClips::resolve(&RedHatBoyStateMachine::sprite_names(), &animations);
+        // the frame box comes from the sheet, re-read it for the current state
+        let size = animations
+            .get(self.state.sprite_name())
+            .map_or(crate::sprite::DEFAULT_SPRITE_SIZE, |animation| {
+                animation.size
+            });
+        self.state.context_mut().bounding_box_size = size;
+        self.animations = Rc::new(animations);
+    }
+
+    /// Air jumps per jump, 1 (double jump) by default and 0 to turn them off
+    /// - only takes effect on a boy that hasn't started running yet
+    pub fn with_air_jumps(mut self, count: u8) -> Self {
+        if let RedHatBoyStateMachine::Idle(idle) = self.state {
+            self.state = idle.with_air_jumps(count).into();
+        }
+        self
+    }
+
+    /// Animation events to queue for take_events(), by the names the override
+    /// file gives them, e.g. "footstep"
+    /// - events nobody subscribed to are dropped as they fire
+    pub fn subscribe(mut self, names: &[&str]) -> Self {
+        self.subscribed
+            .extend(names.iter().map(|name| name.to_string()));
+        self
+    }
+
+    /// Every new state goes through here, so entering Dead is never missed
+    fn set_state(&mut self, state: RedHatBoyStateMachine) {
+        if !self.is_dead() && matches!(state, RedHatBoyStateMachine::Dead(_)) {
+            self.died = true;
         }
+        self.state = state;
+    }
+
+    fn transition(&mut self, event: Event) {
+        self.set_state(self.state.transition(event, &self.animations));
     }
 
     pub fn update(&mut self) {
         // TODO: Explain why this forces us to derive the state machine as copy?
         // - somehow it consumes self via mut self ??? I don't get it
-        self.state = self.state.update();
+        self.set_state(self.state.update(&self.animations));
+        self.queue_events();
     }
 
-    pub fn draw(&mut self, renderer: &Renderer) {
-        let frame_name = self.get_current_frame_name();
-        let sprite = self.sheet.frames.get(&frame_name).expect("Cell not found");
+    /// Events on the frame the update just brought on screen, if any
+    fn queue_events(&mut self) {
+        let Some(frame) = self.state.context().animation.entered() else {
+            return;
+        };
+        let Some(id) = self.clips.get(self.state.state_index()) else {
+            return;
+        };
+        let animation = self.state.sprite_name();
+        let fired = self
+            .animations
+            .events_at(id, frame)
+            .iter()
+            .filter(|name| self.subscribed.contains(*name))
+            .map(|name| AnimationEvent {
+                name: name.clone(),
+                animation,
+                frame,
+            });
+        self.events.extend(fired);
+    }
 
-        renderer.draw_sprite(
-            &self.image,
-            &Rect {
-                position: Point {
-                    x: sprite.frame.x,
-                    y: sprite.frame.y,
-                },
-                size: Size {
-                    width: sprite.frame.w,
-                    height: sprite.frame.h,
-                },
-            },
-            &Rect {
-                position: Point {
-                    x: self.position().x,
-                    y: self.position().y,
-                },
-                size: Size {
-                    width: sprite.frame.w,
-                    height: sprite.frame.h,
-                },
-            },
-        );
+    /// Subscribed animation events fired since the last call, oldest first
+    /// - take them every update, they queue up until then
+    pub fn take_events(&mut self) -> Vec<AnimationEvent> {
+        std::mem::take(&mut self.events)
+    }
 
-        #[cfg(debug_assertions)]
-        {
-            let bounding_box = Rect::new(self.position(), self.bounding_box_size());
-            bounding_box.draw_debug(renderer);
-        }
+    /// Call every update the run input is held, see brake()
+    pub fn run(&mut self, facing: Facing) {
+        self.transition(Event::Run(facing));
     }
 
-    pub fn run_right(&mut self) {
-        self.state = self.state.transition(Event::Run, Some(&self.sheet));
+    /// Call every update the run input isn't held
+    pub fn brake(&mut self) {
+        self.transition(Event::Brake);
     }
 
     pub fn slide(&mut self) {
-        self.state = self.state.transition(Event::Slide, Some(&self.sheet));
+        self.transition(Event::Slide);
     }
 
     pub fn jump(&mut self) {
-        self.state = self.state.transition(Event::Jump, Some(&self.sheet));
+        self.transition(Event::Jump);
+    }
+
+    /// Jump input not held this update : cuts a rising jump short, so how
+    /// high the boy goes depends on how long jump is held
+    pub fn release_jump(&mut self) {
+        self.transition(Event::ReleaseJump);
+    }
+
+    /// Late jump, allowed for a few ticks after the boy stopped running
+    pub fn coyote_jump(&mut self) {
+        self.transition(Event::CoyoteJump);
+    }
+
+    pub fn take_damage(&mut self) {
+        self.transition(Event::TakeDamage);
+    }
+
+    pub fn knock_out(&mut self) {
+        self.transition(Event::KnockOut);
+    }
+
+    pub fn state_name(&self) -> &'static str {
+        self.state.state_name()
+    }
+
+    pub fn is_dead(&self) -> bool {
+        matches!(self.state, RedHatBoyStateMachine::Dead(_))
+    }
+
+    /// The "died" signal : true once, on the first call after the boy died
+    pub fn take_died(&mut self) -> bool {
+        std::mem::take(&mut self.died)
+    }
+
+    pub fn facing(&self) -> Facing {
+        self.state.context().facing
+    }
+
+    pub fn health(&self) -> u8 {
+        self.state.context().health
+    }
+
+    /// Running, or in the air with an air jump left
+    pub fn can_jump(&self) -> bool {
+        self.state.can_jump()
+    }
+
+    /// Whether a late jump press still counts, the states with a CoyoteJump
+    /// row
+    pub fn can_coyote_jump(&self) -> bool {
+        matches!(
+            self.state,
+            RedHatBoyStateMachine::Idle(_)
+                | RedHatBoyStateMachine::Running(_)
+                | RedHatBoyStateMachine::Sliding(_)
+        )
+    }
+
+    /// Only a running boy can slide, other states drop the event
+    pub fn can_slide(&self) -> bool {
+        matches!(self.state, RedHatBoyStateMachine::Running(_))
     }
 
     // Addresses Law of Demeter
@@ -302,14 +576,494 @@ impl RedHatBoy {
         self.state.context().bounding_box_size
     }
 
-    pub fn get_current_frame_name(&self) -> String {
-        use RedHatBoyStateMachine::*;
-        // Match state to the correct current SpriteState impl
-        match self.state {
-            Idle(_) => crate::sprite::Idle::current_frame_name(self.state.context().frame),
-            Running(_) => crate::sprite::Running::current_frame_name(self.state.context().frame),
-            Sliding(_) => crate::sprite::Sliding::current_frame_name(self.state.context().frame),
-            Jumping(_) => crate::sprite::Jumping::current_frame_name(self.state.context().frame),
+    /// The frame box : the whole sheet frame, padding included
+    pub fn bounding_box(&self) -> Rect {
+        Rect::new(self.position(), self.bounding_box_size())
+    }
+
+    /// The collision box : the current frame's hitbox from the override
+    /// file, the frame box when it sets none
+    pub fn hitbox(&self) -> Rect {
+        let Some(id) = self.clips.get(self.state.state_index()) else {
+            return self.bounding_box();
+        };
+        let frame = self.state.context().animation.frame();
+        match (
+            self.animations.trim_at(id, frame),
+            self.animations.hitbox_at(id, frame),
+        ) {
+            // placed in the frame as drawn, which is the (untrimmed) source's
+            // size and not the state's bounding box
+            (Some(trim), Some(hitbox)) => {
+                place_in_frame(Rect::new(self.position(), trim.size), hitbox, self.facing())
+            }
+            _ => self.bounding_box(),
+        }
+    }
+
+    /// Where current_frame() is drawn : at its trim offset in the untrimmed
+    /// frame, mirrored with the frame when facing Left
+    pub fn frame_destination(&self) -> Option<Rect> {
+        let id = self.clips.get(self.state.state_index())?;
+        let frame = self.state.context().animation.frame();
+        let source = self.animations.frame_at(id, frame)?;
+        let trim = self.animations.trim_at(id, frame)?;
+        Some(place_in_frame(
+            Rect::new(self.position(), trim.size),
+            Rect::new(trim.offset, source.size),
+            self.facing(),
+        ))
+    }
+
+    /// Shown this update, false on the off beats of the invulnerability blink
+    pub fn visible(&self) -> bool {
+        self.state.context().invulnerable_ticks % 8 < 4
+    }
+
+    /// Source rect of the frame to draw, in the sheet image
+    pub fn current_frame(&self) -> Option<&Rect> {
+        let id = self.clips.get(self.state.state_index())?;
+        self.animations
+            .frame_at(id, self.state.context().animation.frame())
+    }
+
+    /// Sheet key of the frame being drawn, for debugging
+    pub fn get_current_frame_name(&self) -> Option<&str> {
+        let id = self.clips.get(self.state.state_index())?;
+        self.animations
+            .frame_name_at(id, self.state.context().animation.frame())
+    }
+}
+
+/// World rect of a rect given relative to the frame's top left, a hitbox or
+/// a trimmed frame's pixels
+/// - `frame_box` is where the frame is drawn, at its untrimmed source size
+/// - facing Left draws the frame mirrored, so the rect mirrors with it
+fn place_in_frame(frame_box: Rect, rect: Rect, facing: Facing) -> Rect {
+    let x = match facing {
+        Facing::Right => rect.position.x,
+        Facing::Left => frame_box.size.width - rect.position.x - rect.size.width,
+    };
+    Rect::new(
+        Point {
+            x: frame_box.position.x + x,
+            y: frame_box.position.y + rect.position.y,
+        },
+        rect.size,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Cell, Sheet, SheetRect};
+    use crate::sprite::{to_dot, to_mermaid, AnimationOverrides};
+
+    fn animations() -> AnimationSet {
+        animations_with(&AnimationOverrides::default())
+    }
+
+    const ANIMATIONS: [&str; 6] = ["Idle", "Run", "Slide", "Jump", "Hurt", "Dead"];
+
+    fn animations_with(overrides: &AnimationOverrides) -> AnimationSet {
+        crate::sprite::test_animations(&ANIMATIONS, overrides)
+    }
+
+    /// Update until `done`, failing after a few seconds worth of ticks
+    fn update_until(
+        machine: RedHatBoyStateMachine,
+        animations: &AnimationSet,
+        done: impl Fn(&RedHatBoyStateMachine) -> bool,
+    ) -> RedHatBoyStateMachine {
+        let mut machine = machine;
+        for _ in 0..300 {
+            if done(&machine) {
+                return machine;
+            }
+            machine = machine.update(animations);
+        }
+        panic!("still not done : {:?}", machine);
+    }
+
+    fn running(animations: &AnimationSet) -> RedHatBoyStateMachine {
+        let idle: RedHatBoyStateMachine =
+            RedHatBoyState::new(Idle::metadata(animations).default_size).into();
+        idle.transition(Event::Run(Facing::Right), animations)
+    }
+
+    #[test]
+    fn test_damage_knocks_back_then_invulnerability_runs_out() {
+        let animations = animations();
+        let hurt = running(&animations).transition(Event::TakeDamage, &animations);
+        assert!(matches!(hurt, RedHatBoyStateMachine::Hurt(_)));
+        assert_eq!(hurt.context().health, 2);
+        assert!(hurt.context().velocity.x < 0, "knocked back");
+
+        // Hurt can't be hurt again
+        let hurt = hurt.transition(Event::TakeDamage, &animations);
+        assert_eq!(hurt.context().health, 2);
+
+        let recovered = update_until(hurt, &animations, |machine| {
+            matches!(machine, RedHatBoyStateMachine::Running(_))
+        });
+        assert_eq!(recovered.context().velocity.x, 0, "back on his feet");
+        let recovered = recovered
+            .transition(Event::Run(Facing::Right), &animations)
+            .update(&animations);
+        assert!(recovered.context().velocity.x > 0, "running again");
+        // still blinking, the hit is ignored
+        let ignored = recovered.transition(Event::TakeDamage, &animations);
+        assert!(matches!(ignored, RedHatBoyStateMachine::Running(_)));
+        assert_eq!(ignored.context().health, 2);
+
+        let vulnerable = update_until(ignored, &animations, |machine| {
+            machine.context().invulnerable_ticks == 0
+        });
+        let hurt_again = vulnerable.transition(Event::TakeDamage, &animations);
+        assert!(matches!(hurt_again, RedHatBoyStateMachine::Hurt(_)));
+        assert_eq!(hurt_again.context().health, 1);
+    }
+
+    #[test]
+    fn test_dead_falls_plays_once_and_stays_dead() {
+        let animations = animations();
+        let jumping = running(&animations)
+            .transition(Event::Jump, &animations)
+            .update(&animations);
+        let dead = jumping.transition(Event::KnockOut, &animations);
+        assert!(matches!(dead, RedHatBoyStateMachine::Dead(_)));
+        assert_eq!(dead.context().velocity.x, 0);
+        assert_eq!(dead.context().health, 0);
+
+        let last_frame = Dead::metadata(&animations).frame_count() - 1;
+        let held = (0..50).fold(dead, |machine, _| {
+            machine
+                .transition(Event::Run(Facing::Right), &animations)
+                .transition(Event::TakeDamage, &animations)
+                .update(&animations)
+        });
+        assert!(matches!(held, RedHatBoyStateMachine::Dead(_)));
+        assert_eq!(held.context().animation.frame(), last_frame);
+        assert!(held.context().animation.finished());
+        // fell back to the floor, where the jump started from
+        assert!(held.context().position.y > jumping.context().position.y);
+        assert_eq!(
+            held.context().position.y,
+            running(&animations).context().position.y
+        );
+
+        // the last hit point knocks out too
+        let mut machine = running(&animations);
+        for _ in 0..3 {
+            machine = update_until(machine, &animations, |machine| {
+                matches!(machine, RedHatBoyStateMachine::Running(_))
+                    && machine.context().invulnerable_ticks == 0
+            });
+            machine = machine.transition(Event::TakeDamage, &animations);
+        }
+        assert!(matches!(machine, RedHatBoyStateMachine::Dead(_)));
+    }
+
+    #[test]
+    fn test_checked_in_diagrams_match_the_table() {
+        let rerun = "the transition table changed, run `cargo run --bin state_diagram`";
+        assert_eq!(
+            include_str!("../../docs/red_hat_boy.dot"),
+            to_dot(
+                "RedHatBoy",
+                RedHatBoySimulation::states(),
+                RedHatBoySimulation::transition_table()
+            ),
+            "{}",
+            rerun
+        );
+        assert_eq!(
+            include_str!("../../docs/red_hat_boy.mmd"),
+            to_mermaid(
+                RedHatBoySimulation::states(),
+                RedHatBoySimulation::transition_table()
+            ),
+            "{}",
+            rerun
+        );
+    }
+
+    #[test]
+    fn test_hitbox_sits_inside_the_frame_and_mirrors_facing_left() {
+        let frame_box = Rect::new(
+            Point { x: 100, y: 400 },
+            Size {
+                width: 160,
+                height: 136,
+            },
+        );
+        let slide = Rect::new(
+            Point { x: 20, y: 70 },
+            Size {
+                width: 100,
+                height: 60,
+            },
+        );
+        let right = place_in_frame(frame_box, slide, Facing::Right);
+        assert_eq!((right.position.x, right.position.y), (120, 470));
+        assert_eq!(right.size.width, 100);
+
+        // 20 padding on the left becomes 20 on the right
+        let left = place_in_frame(frame_box, slide, Facing::Left);
+        assert_eq!((left.position.x, left.position.y), (140, 470));
+        assert_eq!(left.position.x + left.size.width, 100 + 160 - 20);
+    }
+
+    #[test]
+    fn test_hitbox_mirrors_inside_the_frame_drawn() {
+        // Run (2) is narrower than Run (1), which sizes the bounding box
+        let frames = [
+            ("Idle (1).png", 100),
+            ("Run (1).png", 100),
+            ("Run (2).png", 60),
+        ]
+        .into_iter()
+        .map(|(key, w)| {
+            let frame = SheetRect {
+                x: 0,
+                y: 0,
+                w,
+                h: 120,
+            };
+            (key.to_string(), Cell::new(frame))
+        })
+        .collect();
+        let overrides: AnimationOverrides = serde_json::from_str(
+            r#"{ "Run": { "hitbox": { "x": 10, "y": 0, "w": 20, "h": 10 } } }"#,
+        )
+        .unwrap();
+        let mut boy =
+            RedHatBoySimulation::new(AnimationSet::from_sheet(&Sheet { frames }, &overrides));
+        boy.run(Facing::Left);
+        for _ in 0..60 {
+            if boy
+                .current_frame()
+                .is_some_and(|frame| frame.size.width == 60)
+            {
+                break;
+            }
+            boy.update();
+        }
+        assert_eq!(boy.bounding_box_size().width, 100);
+        assert_eq!(boy.current_frame().unwrap().size.width, 60);
+        let hitbox = boy.hitbox();
+        assert_eq!(
+            hitbox.position.x + hitbox.size.width,
+            boy.position().x + 60 - 10
+        );
+    }
+
+    #[test]
+    fn test_trimmed_frames_draw_at_their_offset() {
+        // 100x120 frames with 30 / 20 of transparent padding cropped off the
+        // left / top, and 10 off the right
+        let sheet: Sheet = serde_json::from_str(
+            r#"{ "frames": {
+                "Idle (1).png": { "frame": { "x": 0, "y": 0, "w": 60, "h": 100 },
+                    "spriteSourceSize": { "x": 30, "y": 20, "w": 60, "h": 100 },
+                    "sourceSize": { "w": 100, "h": 120 } },
+                "Run (1).png": { "frame": { "x": 60, "y": 0, "w": 60, "h": 100 },
+                    "spriteSourceSize": { "x": 30, "y": 20, "w": 60, "h": 100 },
+                    "sourceSize": { "w": 100, "h": 120 } } } }"#,
+        )
+        .unwrap();
+        let overrides: AnimationOverrides = serde_json::from_str(
+            r#"{ "Run": { "hitbox": { "x": 30, "y": 20, "w": 60, "h": 100 } } }"#,
+        )
+        .unwrap();
+        let mut boy = RedHatBoySimulation::new(AnimationSet::from_sheet(&sheet, &overrides));
+        let size = boy.bounding_box_size();
+        assert_eq!((size.width, size.height), (100, 120), "the untrimmed frame");
+
+        boy.run(Facing::Right);
+        let position = boy.position();
+        let drawn = boy.frame_destination().unwrap();
+        assert_eq!(drawn.position.x, position.x + 30);
+        assert_eq!(drawn.position.y, position.y + 20);
+        assert_eq!((drawn.size.width, drawn.size.height), (60, 100));
+        // the hitbox is relative to the untrimmed frame, and covers the pixels
+        assert_eq!(boy.hitbox(), drawn);
+
+        // mirrored, the 10 cropped off the right is now on the left
+        boy.run(Facing::Left);
+        let drawn = boy.frame_destination().unwrap();
+        assert_eq!(drawn.position.x, boy.position().x + 10);
+        assert_eq!(boy.hitbox(), drawn);
+    }
+
+    #[test]
+    fn test_reload_resizes_the_frame_box() {
+        let mut boy = RedHatBoySimulation::new(animations());
+        boy.run(Facing::Right);
+        assert_eq!(boy.bounding_box_size().height, 120);
+
+        let frames = (1..=4)
+            .map(|i| {
+                let frame = SheetRect {
+                    x: 0,
+                    y: 0,
+                    w: 80,
+                    h: 60,
+                };
+                (format!("Run ({}).png", i), Cell::new(frame))
+            })
+            .collect();
+        boy.reload_animations(AnimationSet::from_sheet(
+            &Sheet { frames },
+            &AnimationOverrides::default(),
+        ));
+        let size = boy.bounding_box_size();
+        assert_eq!((size.width, size.height), (80, 60));
+    }
+
+    #[test]
+    fn test_subscribed_animation_events_fire_as_their_frame_comes_on() {
+        let overrides: AnimationOverrides = serde_json::from_str(
+            r#"{ "Run": { "events": { "1": ["footstep"], "3": ["footstep", "ignored"] } },
+                 "Slide": { "events": { "1": ["dust"] } } }"#,
+        )
+        .unwrap();
+        let mut boy =
+            RedHatBoySimulation::new(animations_with(&overrides)).subscribe(&["footstep", "dust"]);
+        let names = |events: Vec<AnimationEvent>| {
+            events
+                .into_iter()
+                .map(|event| (event.name, event.animation, event.frame))
+                .collect::<Vec<_>>()
+        };
+
+        boy.run(Facing::Right);
+        boy.update();
+        assert_eq!(
+            names(boy.take_events()),
+            [("footstep".to_string(), "Run", 0)]
+        );
+        assert!(boy.take_events().is_empty(), "taken once");
+
+        boy.slide();
+        boy.update();
+        assert_eq!(names(boy.take_events()), [("dust".to_string(), "Slide", 0)]);
+    }
+
+    // native only, proptest doesn't build for wasm32
+    #[cfg(not(target_arch = "wasm32"))]
+    mod properties {
+        use super::*;
+        use crate::sprite::state::FLOOR;
+        use proptest::collection::vec;
+        use proptest::prelude::*;
+
+        /// Any event, knock outs rare enough that most runs stay alive
+        fn event() -> impl Strategy<Value = Event> {
+            prop_oneof![
+                10 => Just(Event::Run(Facing::Right)),
+                10 => Just(Event::Run(Facing::Left)),
+                10 => Just(Event::Brake),
+                10 => Just(Event::Slide),
+                10 => Just(Event::Jump),
+                10 => Just(Event::ReleaseJump),
+                10 => Just(Event::CoyoteJump),
+                5 => Just(Event::TakeDamage),
+                1 => Just(Event::KnockOut),
+                40 => Just(Event::Update),
+            ]
+        }
+
+        fn apply(boy: &mut RedHatBoySimulation, event: Event) {
+            match event {
+                // through update() so animation events are queued too
+                Event::Update => boy.update(),
+                event => boy.transition(event),
+            }
+        }
+
+        proptest! {
+            // transition() debug_asserts every result is a declared target,
+            // so a table / body mismatch fails here as a panic
+            #[test]
+            fn prop_no_event_sequence_panics(events in vec(event(), 0..300)) {
+                let mut boy = RedHatBoySimulation::new(animations());
+                for event in events {
+                    apply(&mut boy, event);
+                    // the per-tick reads the game and draw() do
+                    boy.hitbox();
+                    boy.current_frame();
+                    boy.take_events();
+                }
+            }
+
+            // missing animations are validate_sheet's to report, the
+            // simulation plays on with DEFAULT_SPRITE_SIZE single frames
+            #[test]
+            fn prop_incomplete_sheets_play_on(
+                names in proptest::sample::subsequence(ANIMATIONS.to_vec(), 0..ANIMATIONS.len()),
+                events in vec(event(), 0..300),
+            ) {
+                let sheet = crate::sprite::test_animations(&names, &AnimationOverrides::default());
+                let mut boy = RedHatBoySimulation::new(sheet);
+                for event in events {
+                    apply(&mut boy, event);
+                    boy.hitbox();
+                    boy.current_frame();
+                    boy.take_events();
+                    prop_assert!(boy.position().y <= FLOOR);
+                }
+            }
+
+            #[test]
+            fn prop_y_never_exceeds_the_floor(events in vec(event(), 0..300)) {
+                let mut boy = RedHatBoySimulation::new(animations());
+                for event in events {
+                    apply(&mut boy, event);
+                    prop_assert!(boy.position().y <= FLOOR, "{:?} sank to {}", event, boy.position().y);
+                }
+            }
+
+            #[test]
+            fn prop_health_never_grows_and_the_dead_stay_dead(events in vec(event(), 0..300)) {
+                let mut boy = RedHatBoySimulation::new(animations());
+                let mut health = boy.health();
+                let mut died = 0;
+                for event in events {
+                    let was_dead = boy.is_dead();
+                    apply(&mut boy, event);
+                    prop_assert!(boy.health() <= health);
+                    prop_assert!(!was_dead || boy.is_dead(), "{:?} brought him back", event);
+                    health = boy.health();
+                    died += usize::from(boy.take_died());
+                }
+                prop_assert_eq!(died, usize::from(boy.is_dead()), "died signal fires once");
+            }
+        }
+
+        proptest! {
+            // minutes of play each, a few cases are plenty
+            #![proptest_config(ProptestConfig::with_cases(8))]
+
+            #[test]
+            fn prop_long_runs_never_overflow(
+                events in vec(event(), 0..300),
+                run in prop::option::of(prop_oneof![Just(Facing::Right), Just(Facing::Left)]),
+            ) {
+                let mut boy = RedHatBoySimulation::new(animations());
+                for event in events {
+                    apply(&mut boy, event);
+                }
+                // 20 minutes : past i16 both standing still and running
+                for _ in 0..72_000 {
+                    match run {
+                        Some(facing) => boy.run(facing),
+                        None => boy.brake(),
+                    }
+                    boy.update();
+                    prop_assert!(boy.position().y <= FLOOR);
+                }
+            }
         }
     }
 }
@@ -0,0 +1,65 @@
+#[cfg(debug_assertions)]
+use crate::engine::DebugDraw;
+use crate::engine::{Rect, Renderer};
+use crate::sprite::{Facing, RedHatBoySimulation};
+use web_sys::HtmlImageElement;
+
+/// The rendering half : a RedHatBoySimulation plus the sheet image it draws
+/// from, the only part that needs a browser
+/// - the game drives it through simulation_mut()
+pub struct RedHatBoy {
+    simulation: RedHatBoySimulation,
+    image: HtmlImageElement,
+}
+
+impl RedHatBoy {
+    pub fn new(simulation: RedHatBoySimulation, image: HtmlImageElement) -> Self {
+        RedHatBoy { simulation, image }
+    }
+
+    /// Swap in a re-loaded sheet / image without touching the current state
+    /// - used by development hot reload
+    #[cfg(debug_assertions)]
+    pub fn reload(&mut self, animations: crate::sprite::AnimationSet, image: HtmlImageElement) {
+        self.simulation.reload_animations(animations);
+        self.image = image;
+    }
+
+    /// The simulation it draws, for reading its state
+    pub fn simulation(&self) -> &RedHatBoySimulation {
+        &self.simulation
+    }
+
+    /// The simulation it draws, for the game to drive
+    pub fn simulation_mut(&mut self) -> &mut RedHatBoySimulation {
+        &mut self.simulation
+    }
+
+    pub fn draw(&self, renderer: &Renderer) {
+        // sheets are validated at load, a miss here skips the sprite for this
+        // frame instead of taking the whole game down
+        let boy = &self.simulation;
+        let Some(source) = boy.current_frame() else {
+            log!("Warning: No frame to draw for {}", boy.state_name());
+            return;
+        };
+
+        // trimmed frames are drawn at their offset in the untrimmed frame
+        let destination = boy.frame_destination().unwrap_or(Rect {
+            position: boy.position(),
+            size: source.size,
+        });
+        if boy.visible() {
+            match boy.facing() {
+                Facing::Right => renderer.draw_sprite(&self.image, source, &destination),
+                Facing::Left => renderer.draw_sprite_flipped(&self.image, source, &destination),
+            }
+        }
+
+        #[cfg(debug_assertions)]
+        {
+            boy.bounding_box().draw_debug(renderer);
+            renderer.draw_bounding_box(&boy.hitbox(), "#ff0000");
+        }
+    }
+}
@@ -6,17 +6,68 @@
 ///
 /// Doesn't know about RedHatBoyStateMachine ... TODO: Explain why?
 use crate::engine::{Point, Size};
-use crate::sprite::{self, SpriteState};
+use crate::sprite::{self, Animation, AnimationSet, SpriteMetaData, SpriteState};
 
 // physics consts
-const JUMP_SPEED: i16 = -25; // negative because top left is origin
+// negative because top left is origin
+const JUMP_SPEED: i16 = -25;
+// letting go of jump while still rising faster than this slows down to it,
+// a tap is a hop, holding jumps the full JUMP_SPEED height
+const JUMP_CUT_SPEED: i16 = -10;
+// extra jumps in the air before landing, 1 = double jump
+const DEFAULT_AIR_JUMPS: u8 = 1;
 const GRAVITY: i16 = 1;
-const FLOOR: i16 = 475;
+pub(crate) const FLOOR: i16 = 475;
+// top speed, reached RUNNING_SPEED / RUN_ACCELERATION ticks after starting
 const RUNNING_SPEED: i16 = 3;
+const RUN_ACCELERATION: i16 = 1;
+// braking, or turning round while still moving the other way
+const RUN_DECELERATION: i16 = 1;
+// run animation rate at a crawl, it plays at 1.0 at RUNNING_SPEED
+const MIN_RUN_ANIMATION_RATE: f32 = 0.4;
+// damage consts
+const MAX_HEALTH: u8 = 3;
+const KNOCKBACK_SPEED: i16 = 3; // pushed back, against the way he faces
+const KNOCKBACK_BOUNCE: i16 = -8;
+// ticks damage is ignored after a hit, Hurt itself lasts 24 of them
+const INVULNERABLE_TICKS: u8 = 90;
 
-pub enum IsJumping {
+/// Which way the boy faces, and runs while he isn't braking
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Facing {
+    Right,
+    Left,
+}
+
+impl Facing {
+    /// +1 / -1 along x
+    fn sign(self) -> i16 {
+        match self {
+            Facing::Right => 1,
+            Facing::Left => -1,
+        }
+    }
+}
+
+/// Returns an enum because Running can :
+/// - keep running           (Moving), braking included
+/// - stop, braked to 0 speed (Stopped)
+pub enum IsRunning {
+    Moving(RedHatBoyState<sprite::Running>),
+    Stopped(RedHatBoyState<sprite::Idle>),
+}
+
+pub enum IsJumping<S> {
     Done(RedHatBoyState<sprite::Running>),
-    InProgress(RedHatBoyState<sprite::Jumping>),
+    InProgress(RedHatBoyState<S>),
+}
+
+/// Returns an enum because an air jump needs one left :
+/// - Jumped   (DoubleJumping, the animation starts over)
+/// - NoneLeft (still falling, the state is unchanged)
+pub enum AirJump<S> {
+    Jumped(RedHatBoyState<sprite::DoubleJumping>),
+    NoneLeft(RedHatBoyState<S>),
 }
 
 pub enum IsSliding {
@@ -24,15 +75,55 @@ pub enum IsSliding {
     InProgress(RedHatBoyState<sprite::Sliding>),
 }
 
-#[derive(Debug, Copy, Clone)]
+pub enum IsHurt {
+    Done(RedHatBoyState<sprite::Running>),
+    InProgress(RedHatBoyState<sprite::Hurt>),
+}
+
+/// Returns an enum because damage can :
+/// - Hurt        (health left)
+/// - KnockOut    (last hit point)
+/// - be ignored  (invulnerability frames), the state is unchanged
+pub enum TookDamage<S> {
+    Hurt(RedHatBoyState<sprite::Hurt>),
+    KnockedOut(RedHatBoyState<sprite::Dead>),
+    Invulnerable(RedHatBoyState<S>),
+}
+
+/// States that can take damage, Hurt and Dead can't :
+/// - take_damage() only exists on RedHatBoyState<S: Vulnerable>
+pub trait Vulnerable {}
+impl Vulnerable for sprite::Idle {}
+impl Vulnerable for sprite::Running {}
+impl Vulnerable for sprite::Sliding {}
+impl Vulnerable for sprite::Jumping {}
+impl Vulnerable for sprite::DoubleJumping {}
+
+/// States in the air after a jump : they steer, cut the jump short when it's
+/// released and air jump while air_jumps last
+pub trait Airborne: SpriteState {}
+impl Airborne for sprite::Jumping {}
+impl Airborne for sprite::DoubleJumping {}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 /// Shared data for :
 /// - physics : position + velocity
-/// - display : state + frame count
+/// - display : state + animation + facing (the sprite flips for Left)
 pub struct RedHatBoyContext {
-    pub frame: u8,
+    /// the current state's animation, played per the state's LoopMode
+    pub animation: Animation,
     pub position: Point,
     pub velocity: Point,
+    pub facing: Facing,
+    /// no run input : slowing down towards 0, then Idle
+    pub braking: bool,
+    /// air jumps left in this jump, refilled to max_air_jumps on take off
+    pub air_jumps: u8,
+    pub max_air_jumps: u8,
     pub bounding_box_size: Size,
+    pub health: u8,
+    /// counts down every update, damage is ignored until it reaches 0
+    pub invulnerable_ticks: u8,
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -51,6 +142,46 @@ impl<S> RedHatBoyState<S> {
     pub fn context(&self) -> &RedHatBoyContext {
         &self.context
     }
+
+    pub fn context_mut(&mut self) -> &mut RedHatBoyContext {
+        &mut self.context
+    }
+
+    /// Dies on the spot whatever the health, e.g. falling off the world
+    pub fn knock_out(self, size: Size) -> RedHatBoyState<sprite::Dead> {
+        let mut context = self
+            .context
+            .on_state_transition()
+            .stop()
+            .with_bounding_box_size(size);
+        context.health = 0;
+        RedHatBoyState {
+            context,
+            _state: sprite::Dead,
+        }
+    }
+}
+
+impl<S: Vulnerable> RedHatBoyState<S> {
+    pub fn take_damage(self, hurt_size: Size, dead_size: Size) -> TookDamage<S> {
+        if self.context.invulnerable_ticks > 0 {
+            return TookDamage::Invulnerable(self);
+        }
+        if self.context.health <= 1 {
+            return TookDamage::KnockedOut(self.knock_out(dead_size));
+        }
+        let mut context = self
+            .context
+            .on_state_transition()
+            .knock_back()
+            .with_bounding_box_size(hurt_size);
+        context.health -= 1;
+        context.invulnerable_ticks = INVULNERABLE_TICKS;
+        TookDamage::Hurt(RedHatBoyState {
+            context,
+            _state: sprite::Hurt,
+        })
+    }
 }
 
 impl RedHatBoyState<sprite::Idle> {
@@ -58,26 +189,49 @@ impl RedHatBoyState<sprite::Idle> {
         let position = Point { x: 0, y: FLOOR };
         RedHatBoyState {
             context: RedHatBoyContext {
-                frame: 0,
+                animation: Animation::new(),
                 position,
                 velocity: Point { x: 0, y: 0 },
+                facing: Facing::Right,
+                braking: false,
+                air_jumps: DEFAULT_AIR_JUMPS,
+                max_air_jumps: DEFAULT_AIR_JUMPS,
                 bounding_box_size,
+                health: MAX_HEALTH,
+                invulnerable_ticks: 0,
             },
             _state: sprite::Idle {},
         }
     }
 
-    pub fn update(mut self) -> Self {
-        self.context = self.context.update(sprite::Idle::total_frames());
+    pub fn update(mut self, animations: &AnimationSet) -> Self {
+        self.context = self.context.update(&sprite::Idle::metadata(animations));
         self
     }
 
-    pub fn run(self, size: Size) -> RedHatBoyState<sprite::Running> {
+    /// Air jumps per jump, 0 turns double jumping off
+    pub fn with_air_jumps(mut self, count: u8) -> Self {
+        self.context.air_jumps = count;
+        self.context.max_air_jumps = count;
+        self
+    }
+
+    /// A coyote jump, pressed just after braking to a stop
+    pub fn jump(self, size: Size) -> RedHatBoyState<sprite::Jumping> {
+        RedHatBoyState {
+            context: self.context,
+            _state: sprite::Running,
+        }
+        .jump(size)
+    }
+
+    /// Starts from a standstill, update() then speeds up to RUNNING_SPEED
+    pub fn run(self, facing: Facing, size: Size) -> RedHatBoyState<sprite::Running> {
         RedHatBoyState {
             context: self
                 .context
                 .on_state_transition()
-                .run_right()
+                .steer(facing)
                 .with_bounding_box_size(size),
             _state: sprite::Running {},
         }
@@ -85,11 +239,45 @@ impl RedHatBoyState<sprite::Idle> {
 }
 
 impl RedHatBoyState<sprite::Running> {
-    pub fn update(mut self) -> Self {
-        self.context = self.context.update(sprite::Running::total_frames());
+    /// Returns an enum because braking ends in Idle once the boy stands still
+    pub fn update(mut self, animations: &AnimationSet) -> IsRunning {
+        self.context = self.context.accelerate();
+        // legs keep up with the ground : slower while speeding up or braking
+        let rate = f32::from(self.context.velocity.x.abs()) / f32::from(RUNNING_SPEED);
+        self.context
+            .animation
+            .set_rate(rate.max(MIN_RUN_ANIMATION_RATE));
+        self.context = self.context.update(&sprite::Running::metadata(animations));
+        if self.context.braking && self.context.velocity.x == 0 {
+            IsRunning::Stopped(self.stand_still(sprite::Idle::metadata(animations).default_size))
+        } else {
+            IsRunning::Moving(self)
+        }
+    }
+
+    /// Run input held : turn round if needed, and stop braking
+    pub fn steer(mut self, facing: Facing) -> Self {
+        self.context = self.context.steer(facing);
         self
     }
 
+    /// Run input released : slow down, update() stops in Idle
+    pub fn brake(mut self) -> Self {
+        self.context = self.context.brake();
+        self
+    }
+
+    pub fn stand_still(self, size: Size) -> RedHatBoyState<sprite::Idle> {
+        RedHatBoyState {
+            context: self
+                .context
+                .on_state_transition()
+                .stop()
+                .with_bounding_box_size(size),
+            _state: sprite::Idle,
+        }
+    }
+
     pub fn slide(self, size: Size) -> RedHatBoyState<sprite::Sliding> {
         RedHatBoyState {
             context: self
@@ -101,9 +289,10 @@ impl RedHatBoyState<sprite::Running> {
     }
 
     pub fn jump(self, size: Size) -> RedHatBoyState<sprite::Jumping> {
+        let mut context = *self.context();
+        context.air_jumps = context.max_air_jumps;
         RedHatBoyState {
-            context: self
-                .context()
+            context: context
                 .set_vertical_velocity(JUMP_SPEED)
                 .on_state_transition()
                 .with_bounding_box_size(size),
@@ -116,10 +305,10 @@ impl RedHatBoyState<sprite::Sliding> {
     /// Returns an enum because Sliding can:
     /// - End      (Done)
     /// - Continue (InProgress)
-    pub fn update(mut self) -> IsSliding {
-        self.context = self.context.update(sprite::Sliding::total_frames());
-        // on every update we check if animation is complete
-        if self.context.frame >= sprite::Sliding::total_frames() {
+    pub fn update(mut self, animations: &AnimationSet) -> IsSliding {
+        self.context = self.context.update(&sprite::Sliding::metadata(animations));
+        // Sliding plays Once, the slide lasts exactly one pass
+        if self.context.animation.finished() {
             IsSliding::Done(self.stand())
         } else {
             IsSliding::InProgress(self)
@@ -134,9 +323,11 @@ impl RedHatBoyState<sprite::Sliding> {
     }
 }
 
-impl RedHatBoyState<sprite::Jumping> {
-    pub fn update(mut self) -> IsJumping {
-        self.context = self.context.update(sprite::Jumping::total_frames());
+/// Shared by Jumping and DoubleJumping
+impl<S: Airborne> RedHatBoyState<S> {
+    /// Steerable in the air, the same as on the ground
+    pub fn update(mut self, animations: &AnimationSet) -> IsJumping<S> {
+        self.context = self.context.accelerate().update(&S::metadata(animations));
         if self.context.position.y >= FLOOR {
             IsJumping::Done(self.land())
         } else {
@@ -144,6 +335,40 @@ impl RedHatBoyState<sprite::Jumping> {
         }
     }
 
+    pub fn steer(mut self, facing: Facing) -> Self {
+        self.context = self.context.steer(facing);
+        self
+    }
+
+    pub fn brake(mut self) -> Self {
+        self.context = self.context.brake();
+        self
+    }
+
+    /// Jump input released : stop rising so fast, see JUMP_CUT_SPEED
+    /// - already slower, or falling : nothing changes
+    pub fn release_jump(mut self) -> Self {
+        self.context.velocity.y = self.context.velocity.y.max(JUMP_CUT_SPEED);
+        self
+    }
+
+    /// Jump again mid-air, at full JUMP_SPEED whether rising or falling
+    pub fn air_jump(self, size: Size) -> AirJump<S> {
+        if self.context.air_jumps == 0 {
+            return AirJump::NoneLeft(self);
+        }
+        let mut context = self
+            .context
+            .set_vertical_velocity(JUMP_SPEED)
+            .on_state_transition()
+            .with_bounding_box_size(size);
+        context.air_jumps -= 1;
+        AirJump::Jumped(RedHatBoyState {
+            context,
+            _state: sprite::DoubleJumping,
+        })
+    }
+
     pub fn land(self) -> RedHatBoyState<sprite::Running> {
         RedHatBoyState {
             context: self.context.on_state_transition(),
@@ -152,28 +377,65 @@ impl RedHatBoyState<sprite::Jumping> {
     }
 }
 
+impl RedHatBoyState<sprite::Hurt> {
+    /// Knocked back until the animation is over AND the boy is back on the
+    /// floor, holding the last frame if the hit came mid-air
+    pub fn update(mut self, animations: &AnimationSet) -> IsHurt {
+        self.context = self.context.update(&sprite::Hurt::metadata(animations));
+        if self.context.animation.finished() && self.context.position.y >= FLOOR {
+            let size = sprite::Running::metadata(animations).default_size;
+            IsHurt::Done(self.recover(size))
+        } else {
+            IsHurt::InProgress(self)
+        }
+    }
+
+    /// Back on his feet from a standstill, braking into Idle unless the run
+    /// input is still held
+    pub fn recover(self, size: Size) -> RedHatBoyState<sprite::Running> {
+        RedHatBoyState {
+            context: self
+                .context
+                .on_state_transition()
+                .stop()
+                .with_bounding_box_size(size),
+            _state: sprite::Running,
+        }
+    }
+}
+
+impl RedHatBoyState<sprite::Dead> {
+    /// Falls to the floor and plays once, holding the last frame (HoldLast) :
+    /// there is no way out of Dead
+    pub fn update(mut self, animations: &AnimationSet) -> Self {
+        self.context = self.context.update(&sprite::Dead::metadata(animations));
+        self
+    }
+}
+
 impl RedHatBoyContext {
     /// ::update per frame
-    /// - set frame_count -> render frame
+    /// - play the animation a tick further (Animation handles the LoopMode)
     /// - set velocity -> position
-    pub fn update(mut self, frame_count: u8) -> Self {
+    pub fn update(mut self, metadata: &SpriteMetaData) -> Self {
         // add gravity
         self.velocity.y += GRAVITY;
-        // update render frame
-        if self.frame < frame_count {
-            self.frame += 1;
-        } else {
-            self.frame = 0;
-        }
+        self.animation.update(metadata);
         // update transform position
-        self.position.x += self.velocity.x;
-        self.position.y += self.velocity.y;
+        // - saturating : running one way long enough reaches the edge of
+        //   i16 (about 3 minutes at RUNNING_SPEED), and stops there
+        self.position.x = self.position.x.saturating_add(self.velocity.x);
+        self.position.y = self.position.y.saturating_add(self.velocity.y);
 
         // detect collision and resolve
-        if self.position.y > FLOOR {
+        // - landing stops the fall, else gravity piles up while standing
+        //   and overflows after a few minutes
+        if self.position.y >= FLOOR {
             self.position.y = FLOOR;
+            self.velocity.y = 0;
         }
 
+        self.invulnerable_ticks = self.invulnerable_ticks.saturating_sub(1);
         self
     }
 
@@ -183,8 +445,8 @@ impl RedHatBoyContext {
     ///         - because each state will likely have variable frame count
     ///         - else we risk accessing out of index frame => runtime ERROR
     fn on_state_transition(mut self) -> Self {
-        // reset frame
-        self.frame = 0;
+        // restart from the first frame
+        self.animation = Animation::new();
         self
     }
 
@@ -194,8 +456,33 @@ impl RedHatBoyContext {
         self
     }
 
-    fn run_right(mut self) -> Self {
-        self.velocity.x = RUNNING_SPEED;
+    fn steer(mut self, facing: Facing) -> Self {
+        self.facing = facing;
+        self.braking = false;
+        self
+    }
+
+    fn brake(mut self) -> Self {
+        self.braking = true;
+        self
+    }
+
+    /// One tick of horizontal acceleration towards the target speed :
+    /// - RUNNING_SPEED the way he faces, or 0 while braking
+    /// - decelerates while braking or still moving against `facing`
+    fn accelerate(mut self) -> Self {
+        let target = if self.braking {
+            0
+        } else {
+            self.facing.sign() * RUNNING_SPEED
+        };
+        let against = self.velocity.x.signum() == -self.facing.sign();
+        let rate = if self.braking || against {
+            RUN_DECELERATION
+        } else {
+            RUN_ACCELERATION
+        };
+        self.velocity.x += (target - self.velocity.x).clamp(-rate, rate);
         self
     }
 
@@ -203,4 +490,248 @@ impl RedHatBoyContext {
         self.velocity.y = y;
         self
     }
+
+    fn knock_back(mut self) -> Self {
+        self.velocity.x = -self.facing.sign() * KNOCKBACK_SPEED;
+        self.velocity.y = KNOCKBACK_BOUNCE;
+        self
+    }
+
+    /// Drop the horizontal speed, gravity still applies
+    fn stop(mut self) -> Self {
+        self.velocity.x = 0;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sprite::AnimationOverrides;
+
+    fn animations() -> AnimationSet {
+        sprite::test_animations(&["Idle", "Run", "Jump"], &AnimationOverrides::default())
+    }
+
+    fn idle() -> RedHatBoyState<sprite::Idle> {
+        RedHatBoyState::new(Size {
+            width: 100,
+            height: 120,
+        })
+    }
+
+    fn size() -> Size {
+        idle().context.bounding_box_size
+    }
+
+    fn moving(is_running: IsRunning) -> RedHatBoyState<sprite::Running> {
+        match is_running {
+            IsRunning::Moving(running) => running,
+            IsRunning::Stopped(_) => panic!("stopped while still running"),
+        }
+    }
+
+    #[test]
+    fn test_speeds_up_to_the_top_speed() {
+        let animations = animations();
+        let mut running = idle().run(Facing::Right, size());
+        assert_eq!(running.context.velocity.x, 0, "starts from a standstill");
+
+        let mut speeds = vec![];
+        for _ in 0..6 {
+            running = moving(running.update(&animations));
+            speeds.push(running.context.velocity.x);
+        }
+        assert_eq!(speeds, [1, 2, 3, 3, 3, 3]);
+        assert_eq!(running.context.position.x, 1 + 2 + 3 * 4);
+    }
+
+    #[test]
+    fn test_run_animation_plays_faster_at_top_speed() {
+        let animations = animations();
+        let running = moving(idle().run(Facing::Right, size()).update(&animations));
+        assert_eq!(running.context.animation.rate(), MIN_RUN_ANIMATION_RATE);
+
+        let mut running = running;
+        for _ in 0..5 {
+            running = moving(running.update(&animations));
+        }
+        assert_eq!(running.context.animation.rate(), 1.0);
+
+        // braking slows the legs down with the boy
+        let running = moving(running.brake().update(&animations));
+        assert_eq!(running.context.animation.rate(), 2.0 / 3.0);
+    }
+
+    #[test]
+    fn test_running_left_faces_left_and_turns_round_through_zero() {
+        let animations = animations();
+        let mut running = idle().run(Facing::Left, size());
+        for _ in 0..5 {
+            running = moving(running.update(&animations));
+        }
+        assert_eq!(running.context.facing, Facing::Left);
+        assert_eq!(running.context.velocity.x, -RUNNING_SPEED);
+        assert!(running.context.position.x < 0);
+
+        // faces right at once, the speed takes its time
+        let mut running = running.steer(Facing::Right);
+        assert_eq!(running.context.facing, Facing::Right);
+        let mut speeds = vec![];
+        for _ in 0..7 {
+            running = moving(running.update(&animations));
+            speeds.push(running.context.velocity.x);
+        }
+        assert_eq!(speeds, [-2, -1, 0, 1, 2, 3, 3]);
+
+        // knocked back against the way he faces
+        let hurt = match running.take_damage(size(), size()) {
+            TookDamage::Hurt(hurt) => hurt,
+            _ => panic!("should be hurt"),
+        };
+        assert_eq!(hurt.context.velocity.x, -KNOCKBACK_SPEED);
+    }
+
+    #[test]
+    fn test_braking_stops_in_idle() {
+        let animations = animations();
+        let mut running = idle().run(Facing::Right, size());
+        for _ in 0..5 {
+            running = moving(running.update(&animations));
+        }
+        let mut running = running.brake();
+        let idle = loop {
+            match running.update(&animations) {
+                IsRunning::Moving(still_running) => {
+                    assert!(still_running.context.velocity.x > 0, "never reverses");
+                    running = still_running;
+                }
+                IsRunning::Stopped(idle) => break idle,
+            }
+        };
+        assert_eq!(idle.context.velocity.x, 0);
+        assert_eq!(idle.context.animation.frame(), 0);
+        assert!(idle.context.braking);
+
+        // and runs again from there
+        let running = moving(idle.run(Facing::Right, size()).update(&animations));
+        assert!(!running.context.braking);
+        assert_eq!(running.context.velocity.x, 1);
+    }
+
+    #[test]
+    fn test_steering_in_the_air() {
+        let animations = animations();
+        let running = moving(idle().run(Facing::Right, size()).update(&animations));
+        let jumping = running.jump(size()).steer(Facing::Left);
+        let jumping = match jumping.update(&animations) {
+            IsJumping::InProgress(jumping) => jumping,
+            IsJumping::Done(_) => panic!("landed on the first tick"),
+        };
+        assert_eq!(jumping.context.facing, Facing::Left);
+        assert_eq!(jumping.context.velocity.x, 0);
+        let jumping = match jumping.brake().update(&animations) {
+            IsJumping::InProgress(jumping) => jumping,
+            IsJumping::Done(_) => panic!("landed on the second tick"),
+        };
+        assert_eq!(jumping.context.velocity.x, 0, "braking holds 0");
+    }
+
+    /// Highest point (lowest y) reached before landing
+    fn apex<S: Airborne>(jumping: RedHatBoyState<S>, animations: &AnimationSet) -> i16 {
+        let mut jumping = jumping;
+        let mut top = jumping.context.position.y;
+        loop {
+            match jumping.update(animations) {
+                IsJumping::InProgress(still_jumping) => {
+                    top = top.min(still_jumping.context.position.y);
+                    jumping = still_jumping;
+                }
+                IsJumping::Done(_) => return top,
+            }
+        }
+    }
+
+    #[test]
+    fn test_releasing_jump_early_jumps_lower() {
+        let animations = animations();
+        let running = idle().run(Facing::Right, size());
+        let held = apex(running.jump(size()), &animations);
+        let tapped = apex(running.jump(size()).release_jump(), &animations);
+        assert!(
+            tapped > held,
+            "a tap ({}) tops out under a hold ({})",
+            tapped,
+            held
+        );
+
+        // released half way up : in between
+        let mut jumping = running.jump(size());
+        for _ in 0..5 {
+            jumping = match jumping.update(&animations) {
+                IsJumping::InProgress(jumping) => jumping,
+                IsJumping::Done(_) => panic!("landed while rising"),
+            };
+        }
+        let half = apex(jumping.release_jump(), &animations);
+        assert!(held < half && half < tapped);
+
+        // falling already : releasing changes nothing
+        let falling = RedHatBoyState {
+            context: RedHatBoyContext {
+                velocity: Point { x: 0, y: 4 },
+                ..jumping.context
+            },
+            _state: sprite::Jumping,
+        };
+        assert_eq!(falling.release_jump().context.velocity.y, 4);
+    }
+
+    #[test]
+    fn test_air_jumps_run_out_and_refill_on_take_off() {
+        let animations = animations();
+        let running = idle().with_air_jumps(2).run(Facing::Right, size());
+        let jumping = match running.jump(size()).update(&animations) {
+            IsJumping::InProgress(jumping) => jumping,
+            IsJumping::Done(_) => panic!("landed on the first tick"),
+        };
+        assert!(jumping.context.animation != Animation::new());
+
+        let double = match jumping.air_jump(size()) {
+            AirJump::Jumped(double) => double,
+            AirJump::NoneLeft(_) => panic!("2 air jumps to start with"),
+        };
+        assert_eq!(
+            double.context.animation,
+            Animation::new(),
+            "the animation starts over"
+        );
+        assert_eq!(double.context.velocity.y, JUMP_SPEED);
+        let double = match double.air_jump(size()) {
+            AirJump::Jumped(double) => double,
+            AirJump::NoneLeft(_) => panic!("1 air jump left"),
+        };
+        let double = match double.air_jump(size()) {
+            AirJump::Jumped(_) => panic!("no air jumps left"),
+            AirJump::NoneLeft(double) => double,
+        };
+        assert_eq!(double.context.air_jumps, 0);
+
+        // landing alone doesn't refill them, taking off again does
+        let mut double = double;
+        let landed = loop {
+            match double.update(&animations) {
+                IsJumping::InProgress(still_jumping) => double = still_jumping,
+                IsJumping::Done(running) => break running,
+            }
+        };
+        assert_eq!(landed.jump(size()).context.air_jumps, 2);
+
+        // 0 turns air jumps off
+        let no_air_jumps = idle().with_air_jumps(0).run(Facing::Right, size());
+        assert!(matches!(
+            no_air_jumps.jump(size()).air_jump(size()),
+            AirJump::NoneLeft(_)
+        ));
+    }
 }
@@ -0,0 +1,479 @@
+/// Load-time validation of a sprite sheet against the states that use it
+/// - catches missing animations, numbering gaps and out of bounds frames
+///   BEFORE the first draw() instead of panicking mid-game
+/// - collects EVERY problem into a report rather than stopping at the first
+///
+/// ELI5:
+/// ┌──────────────── Validation Pass ─────────────────────────────┐
+/// │                                                              │
+/// │  states ──► ["Idle", "Run", ...] ──┐                         │
+/// │                                    ▼                         │
+/// │  AnimationSet ──────────────► animation exists?              │
+/// │                               frames numbered 1..n?          │
+/// │                               at most 255 frames?            │
+/// │                               a duration for every frame?    │
+/// │                               events / hitboxes on frames    │
+/// │                               it ships?                      │
+/// │                               hitboxes inside their frame?   │
+/// │                                    │                         │
+/// │  Sheet + image size ────────► frame inside the image?        │
+/// │                                    │                         │
+/// │                                    ▼                         │
+/// │                           ValidationReport { issues }        │
+/// └──────────────────────────────────────────────────────────────┘
+use crate::engine::{Rect, Sheet, Size};
+use crate::sprite::animation::parse_frame_key;
+use crate::sprite::AnimationSet;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationIssue {
+    /// a state requests an animation the sheet doesn't ship
+    MissingAnimation { animation: String },
+    /// frame numbers skip a value, e.g. Run (1), Run (2), Run (4)
+    MissingFrame { animation: String, frame: u16 },
+    /// two keys parse to the same frame number, e.g. Run (3) and run_03
+    DuplicateFrame { animation: String, frame: u16 },
+    /// more frames than the u8 frame index can reach
+    TooManyFrames { animation: String, frames: usize },
+    /// the override file lists durations, but not one per frame : it's
+    /// every frame or none, see AnimationOverride
+    DurationCount {
+        animation: String,
+        frames: usize,
+        durations: usize,
+    },
+    /// the override file has events or a hitbox on a frame number the sheet
+    /// doesn't ship
+    UnknownFrame { animation: String, frame: u16 },
+    /// a frame's hitbox has no area to collide with
+    EmptyHitbox { animation: String, frame: u16 },
+    /// a frame's hitbox reaches outside the frame, where nothing is drawn
+    HitboxOutOfBounds {
+        animation: String,
+        frame: u16,
+        hitbox: Rect,
+        size: Size,
+    },
+    /// frame has no area to draw
+    EmptyFrame { key: String },
+    /// frame reaches outside the sheet image
+    OutOfBounds {
+        key: String,
+        x: i16,
+        y: i16,
+        w: i16,
+        h: i16,
+        image: Size,
+    },
+}
+
+impl fmt::Display for ValidationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationIssue::MissingAnimation { animation } => {
+                write!(f, "missing animation '{}'", animation)
+            }
+            ValidationIssue::MissingFrame { animation, frame } => {
+                write!(f, "animation '{}' is missing frame {}", animation, frame)
+            }
+            ValidationIssue::DuplicateFrame { animation, frame } => {
+                write!(
+                    f,
+                    "animation '{}' has frame {} more than once",
+                    animation, frame
+                )
+            }
+            ValidationIssue::TooManyFrames { animation, frames } => write!(
+                f,
+                "animation '{}' has {} frames, more than {}",
+                animation,
+                frames,
+                u8::MAX
+            ),
+            ValidationIssue::DurationCount {
+                animation,
+                frames,
+                durations,
+            } => write!(
+                f,
+                "animation '{}' has {} frames but {} durations",
+                animation, frames, durations
+            ),
+            ValidationIssue::UnknownFrame { animation, frame } => write!(
+                f,
+                "animation '{}' has overrides on frame {}, which the sheet doesn't ship",
+                animation, frame
+            ),
+            ValidationIssue::EmptyHitbox { animation, frame } => write!(
+                f,
+                "animation '{}' frame {} has a hitbox with no area",
+                animation, frame
+            ),
+            ValidationIssue::HitboxOutOfBounds {
+                animation,
+                frame,
+                hitbox,
+                size,
+            } => write!(
+                f,
+                "animation '{}' frame {} has a hitbox ({}, {}, {}x{}) outside its {}x{} frame",
+                animation,
+                frame,
+                hitbox.position.x,
+                hitbox.position.y,
+                hitbox.size.width,
+                hitbox.size.height,
+                size.width,
+                size.height
+            ),
+            ValidationIssue::EmptyFrame { key } => write!(f, "frame '{}' has no area", key),
+            ValidationIssue::OutOfBounds {
+                key,
+                x,
+                y,
+                w,
+                h,
+                image,
+            } => write!(
+                f,
+                "frame '{}' ({}, {}, {}x{}) is outside the {}x{} image",
+                key, x, y, w, h, image.width, image.height
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ValidationReport {
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl fmt::Display for ValidationReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "sprite sheet has {} problem(s)", self.issues.len())?;
+        for issue in &self.issues {
+            write!(f, "\n - {}", issue)?;
+        }
+        Ok(())
+    }
+}
+
+// lets `?` turn a failed report into an anyhow::Error
+impl std::error::Error for ValidationReport {}
+
+/// Check every animation a character requests, plus every frame of the
+/// sheet, against the dimensions of the sheet image
+pub fn validate_sheet(
+    sheet: &Sheet,
+    animations: &AnimationSet,
+    required: &[&str],
+    image: Size,
+) -> ValidationReport {
+    let mut issues = Vec::new();
+
+    for name in required {
+        let Some(animation) = animations.get(name) else {
+            issues.push(ValidationIssue::MissingAnimation {
+                animation: name.to_string(),
+            });
+            continue;
+        };
+
+        // frames are sorted, so any jump in numbering is a missing frame and
+        // a repeat is a duplicate
+        let numbers = animation
+            .frames
+            .iter()
+            .filter_map(|key| parse_frame_key(key).map(|(_, number)| number));
+        let mut expected = 1;
+        let mut previous = None;
+        for number in numbers {
+            if previous.replace(number) == Some(number) {
+                // reported once, however many more copies follow
+                let frame = ValidationIssue::DuplicateFrame {
+                    animation: name.to_string(),
+                    frame: number,
+                };
+                if issues.last() != Some(&frame) {
+                    issues.push(frame);
+                }
+                continue;
+            }
+            issues.extend(
+                (expected..number).map(|frame| ValidationIssue::MissingFrame {
+                    animation: name.to_string(),
+                    frame,
+                }),
+            );
+            expected = number + 1;
+        }
+
+        if animation.frame_count() > usize::from(u8::MAX) {
+            issues.push(ValidationIssue::TooManyFrames {
+                animation: name.to_string(),
+                frames: animation.frame_count(),
+            });
+        }
+
+        // no durations at all is fine, every frame then plays for `speed`
+        let durations = animation.durations.len();
+        if durations > 0 && durations != animation.frame_count() {
+            issues.push(ValidationIssue::DurationCount {
+                animation: name.to_string(),
+                frames: animation.frame_count(),
+                durations,
+            });
+        }
+
+        // the animation's hitbox is checked on every frame it applies to,
+        // frames can differ in size
+        if let Some(id) = animations.id(name) {
+            // against the untrimmed frame, hitboxes don't move with a trim
+            let numbered = animation.frames.iter().zip(&animation.trims);
+            for (index, (key, trim)) in (0..=u8::MAX).zip(numbered) {
+                let Some(hitbox) = animations.hitbox_at(id, index) else {
+                    continue;
+                };
+                let frame = parse_frame_key(key).map_or(u16::from(index) + 1, |(_, n)| n);
+                if hitbox.size.width <= 0 || hitbox.size.height <= 0 {
+                    issues.push(ValidationIssue::EmptyHitbox {
+                        animation: name.to_string(),
+                        frame,
+                    });
+                } else if hitbox.position.x < 0
+                    || hitbox.position.y < 0
+                    || i32::from(hitbox.position.x) + i32::from(hitbox.size.width)
+                        > i32::from(trim.size.width)
+                    || i32::from(hitbox.position.y) + i32::from(hitbox.size.height)
+                        > i32::from(trim.size.height)
+                {
+                    issues.push(ValidationIssue::HitboxOutOfBounds {
+                        animation: name.to_string(),
+                        frame,
+                        hitbox,
+                        size: trim.size,
+                    });
+                }
+            }
+        }
+
+        issues.extend(
+            animation
+                .unknown_frames
+                .iter()
+                .map(|frame| ValidationIssue::UnknownFrame {
+                    animation: name.to_string(),
+                    frame: *frame,
+                }),
+        );
+    }
+
+    // sort keys so the report reads the same on every load
+    let mut keys: Vec<&String> = sheet.frames.keys().collect();
+    keys.sort();
+    for key in keys {
+        let rect = &sheet.frames[key].frame;
+        if rect.w <= 0 || rect.h <= 0 {
+            issues.push(ValidationIssue::EmptyFrame { key: key.clone() });
+        } else if rect.x < 0
+            || rect.y < 0
+            || i32::from(rect.x) + i32::from(rect.w) > i32::from(image.width)
+            || i32::from(rect.y) + i32::from(rect.h) > i32::from(image.height)
+        {
+            issues.push(ValidationIssue::OutOfBounds {
+                key: key.clone(),
+                x: rect.x,
+                y: rect.y,
+                w: rect.w,
+                h: rect.h,
+                image,
+            });
+        }
+    }
+
+    ValidationReport { issues }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::{Cell, SheetRect};
+    use crate::sprite::animation::AnimationOverride;
+    use crate::sprite::AnimationOverrides;
+
+    const IMAGE: Size = Size {
+        width: 100,
+        height: 100,
+    };
+
+    fn sheet(frames: &[(&str, i16, i16, i16, i16)]) -> Sheet {
+        Sheet {
+            frames: frames
+                .iter()
+                .map(|(key, x, y, w, h)| {
+                    let frame = SheetRect {
+                        x: *x,
+                        y: *y,
+                        w: *w,
+                        h: *h,
+                    };
+                    (key.to_string(), Cell::new(frame))
+                })
+                .collect(),
+        }
+    }
+
+    fn validate(sheet: &Sheet, required: &[&str]) -> ValidationReport {
+        let animations = AnimationSet::from_sheet(sheet, &AnimationOverrides::default());
+        validate_sheet(sheet, &animations, required, IMAGE)
+    }
+
+    #[test]
+    fn test_valid_sheet_has_no_issues() {
+        let sheet = sheet(&[
+            ("Run (1).png", 0, 0, 50, 50),
+            ("Run (2).png", 50, 50, 50, 50),
+        ]);
+        assert!(validate(&sheet, &["Run"]).is_ok());
+    }
+
+    #[test]
+    fn test_reports_every_problem() {
+        let sheet = sheet(&[
+            ("Run (1).png", 0, 0, 50, 50),
+            ("Run (4).png", 80, 0, 50, 50),
+            ("Run (5).png", 0, 0, 0, 10),
+        ]);
+        let report = validate(&sheet, &["Idle", "Run"]);
+
+        assert_eq!(
+            report.issues,
+            vec![
+                ValidationIssue::MissingAnimation {
+                    animation: "Idle".to_string()
+                },
+                ValidationIssue::MissingFrame {
+                    animation: "Run".to_string(),
+                    frame: 2
+                },
+                ValidationIssue::MissingFrame {
+                    animation: "Run".to_string(),
+                    frame: 3
+                },
+                ValidationIssue::OutOfBounds {
+                    key: "Run (4).png".to_string(),
+                    x: 80,
+                    y: 0,
+                    w: 50,
+                    h: 50,
+                    image: IMAGE,
+                },
+                ValidationIssue::EmptyFrame {
+                    key: "Run (5).png".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_reports_duplicate_frame_numbers_once() {
+        let twos = sheet(&[
+            ("Run (1).png", 0, 0, 50, 50),
+            ("Run (2).png", 0, 0, 50, 50),
+            ("Run 2.png", 0, 0, 50, 50),
+            ("run_02.png", 0, 0, 50, 50),
+            ("Run (3).png", 0, 0, 50, 50),
+        ]);
+        // run_02 is the lowercase "run" animation, not a copy
+        assert_eq!(
+            validate(&twos, &["Run"]).issues,
+            vec![ValidationIssue::DuplicateFrame {
+                animation: "Run".to_string(),
+                frame: 2
+            }]
+        );
+
+        let copies = sheet(&[
+            ("Run (1).png", 0, 0, 50, 50),
+            ("Run (01).png", 0, 0, 50, 50),
+            ("Run 1.png", 0, 0, 50, 50),
+        ]);
+        assert_eq!(validate(&copies, &["Run"]).issues.len(), 1);
+    }
+
+    #[test]
+    fn test_durations_must_cover_every_frame() {
+        let sheet = sheet(&[
+            ("Run (1).png", 0, 0, 50, 50),
+            ("Run (2).png", 50, 0, 50, 50),
+        ]);
+        let mut overrides = AnimationOverrides::default();
+        overrides.animations.insert(
+            "Run".to_string(),
+            AnimationOverride {
+                durations: Some(vec![80]),
+                ..AnimationOverride::default()
+            },
+        );
+        let animations = AnimationSet::from_sheet(&sheet, &overrides);
+
+        assert_eq!(
+            validate_sheet(&sheet, &animations, &["Run"], IMAGE).issues,
+            vec![ValidationIssue::DurationCount {
+                animation: "Run".to_string(),
+                frames: 2,
+                durations: 1,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_hitboxes_must_have_area_and_fit_their_frame() {
+        // Run (2) is narrower, the animation's hitbox only fits Run (1)
+        let sheet = sheet(&[
+            ("Run (1).png", 0, 0, 50, 50),
+            ("Run (2).png", 50, 0, 30, 50),
+            ("Run (3).png", 0, 50, 50, 50),
+        ]);
+        let overrides: AnimationOverrides = serde_json::from_str(
+            r#"{ "Run": {
+                "hitbox": { "x": 10, "y": 10, "w": 30, "h": 30 },
+                "frame_hitboxes": { "3": { "x": 0, "y": 0, "w": 0, "h": 20 } }
+            } }"#,
+        )
+        .unwrap();
+        let animations = AnimationSet::from_sheet(&sheet, &overrides);
+
+        assert_eq!(
+            validate_sheet(&sheet, &animations, &["Run"], IMAGE).issues,
+            vec![
+                ValidationIssue::HitboxOutOfBounds {
+                    animation: "Run".to_string(),
+                    frame: 2,
+                    hitbox: Rect::new(
+                        crate::engine::Point { x: 10, y: 10 },
+                        Size {
+                            width: 30,
+                            height: 30
+                        }
+                    ),
+                    size: Size {
+                        width: 30,
+                        height: 50
+                    },
+                },
+                ValidationIssue::EmptyHitbox {
+                    animation: "Run".to_string(),
+                    frame: 3,
+                },
+            ]
+        );
+    }
+}
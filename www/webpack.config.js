@@ -8,6 +8,10 @@ const path = require("path");
 const HtmlWebpackPlugin = require("html-webpack-plugin");
 // pluin to copy individual files or entire directories to  build directory
 const CopyWebpackPlugin = require("copy-webpack-plugin");
+// read asset mtimes for the hot reload version endpoint
+const fs = require("fs");
+
+const STATIC_DIR = path.resolve(__dirname, "../static");
 
 module.exports = {
   // starts webpack configuration object to be exported
@@ -37,7 +41,7 @@ module.exports = {
     }),
     // plugin will copy files from '../static' directory to output directory
     new CopyWebpackPlugin({
-      patterns: [{ from: "../static", to: "" }],
+      patterns: [{ from: STATIC_DIR, to: "" }],
     }),
   ],
   devServer: {
@@ -47,5 +51,20 @@ module.exports = {
     compress: true,
     port: 8080,
     hot: true,
+    // dev only : { "rhb.json": <mtime ms>, ... } polled by the game to
+    // hot reload changed assets (see src/engine/hot_reload.rs)
+    setupMiddlewares: (middlewares, devServer) => {
+      devServer.app.get("/asset-version.json", (_req, res) => {
+        const versions = {};
+        for (const name of fs.readdirSync(STATIC_DIR)) {
+          versions[name] = Math.floor(
+            fs.statSync(path.join(STATIC_DIR, name)).mtimeMs,
+          );
+        }
+        res.set("Cache-Control", "no-store");
+        res.json(versions);
+      });
+      return middlewares;
+    },
   },
 };